@@ -19,6 +19,58 @@ variable "calendar_ids" {
 
 variable "service_acct_key" {}
 
+variable "mbta_api_key" {
+  default = ""
+}
+
+variable "routes" {
+  default = ""
+}
+
+variable "route_types" {
+  default = ""
+}
+
+variable "stops" {
+  default = ""
+}
+
+variable "alert_filter" {
+  default = ""
+}
+
+variable "caldav_username" {
+  default = ""
+}
+
+variable "caldav_password" {
+  default = ""
+}
+
+variable "graph_tenant_id" {
+  default = ""
+}
+
+variable "graph_client_id" {
+  default = ""
+}
+
+variable "graph_client_secret" {
+  default = ""
+}
+
+variable "graph_user" {
+  default = ""
+}
+
+variable "summary_concurrency" {
+  default = ""
+}
+
+variable "write_concurrency" {
+  default = ""
+}
+
 data "aws_caller_identity" "current" {}
 
 provider "aws" {
@@ -109,7 +161,20 @@ resource "aws_lambda_function" "mbtalerts" {
       {
         GOOGLE_SERVICE_ACCOUNT_KEY = var.service_acct_key
       },
-      var.calendar_ids != "" ? { GOOGLE_CALENDAR_IDS = var.calendar_ids } : { GOOGLE_CALENDAR_ID = var.calendar_id }
+      var.calendar_ids != "" ? { GOOGLE_CALENDAR_IDS = var.calendar_ids } : { GOOGLE_CALENDAR_ID = var.calendar_id },
+      var.mbta_api_key != "" ? { MBTA_API_KEY = var.mbta_api_key } : {},
+      var.routes != "" ? { MBTA_ROUTES = var.routes } : {},
+      var.route_types != "" ? { MBTA_ROUTE_TYPES = var.route_types } : {},
+      var.stops != "" ? { MBTA_STOPS = var.stops } : {},
+      var.alert_filter != "" ? { MBTA_ALERT_FILTER = var.alert_filter } : {},
+      var.caldav_username != "" ? { CALDAV_USERNAME = var.caldav_username } : {},
+      var.caldav_password != "" ? { CALDAV_PASSWORD = var.caldav_password } : {},
+      var.graph_tenant_id != "" ? { GRAPH_TENANT_ID = var.graph_tenant_id } : {},
+      var.graph_client_id != "" ? { GRAPH_CLIENT_ID = var.graph_client_id } : {},
+      var.graph_client_secret != "" ? { GRAPH_CLIENT_SECRET = var.graph_client_secret } : {},
+      var.graph_user != "" ? { GRAPH_USER = var.graph_user } : {},
+      var.summary_concurrency != "" ? { SYNC_SUMMARY_CONCURRENCY = var.summary_concurrency } : {},
+      var.write_concurrency != "" ? { SYNC_WRITE_CONCURRENCY = var.write_concurrency } : {}
     )
   }
 }
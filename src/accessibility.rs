@@ -0,0 +1,201 @@
+//! Elevator, escalator and other accessibility outages. Riders who depend on
+//! them follow these station by station rather than line by line, so they get
+//! a calendar and titles of their own.
+
+use crate::filter::EffectFilter;
+use crate::stops::StopDirectory;
+use crate::summary::{first_sentence, strip_line_prefix};
+use crate::types::{Alert, Effect, FacilityType};
+
+/// The effects routed to the accessibility calendar.
+pub const ACCESSIBILITY_EFFECTS: &[Effect] = &[
+    Effect::ElevatorClosure,
+    Effect::EscalatorClosure,
+    Effect::AccessIssue,
+];
+
+pub fn is_accessibility_alert(alert: &Alert) -> bool {
+    ACCESSIBILITY_EFFECTS.contains(&alert.attributes.effect)
+}
+
+/// The filter the accessibility calendar uses unless configured with its own.
+pub fn accessibility_filter() -> EffectFilter {
+    EffectFilter {
+        include_effects: ACCESSIBILITY_EFFECTS.to_vec(),
+        ..Default::default()
+    }
+}
+
+/// What's out: from the facility when we know it, else from the effect.
+fn outage(alert: &Alert, facility_type: Option<&FacilityType>) -> String {
+    match (facility_type, &alert.attributes.effect) {
+        (Some(FacilityType::Elevator), _) | (None, Effect::ElevatorClosure) => {
+            "Elevator out".to_owned()
+        }
+        (Some(FacilityType::Escalator), _) | (None, Effect::EscalatorClosure) => {
+            "Escalator out".to_owned()
+        }
+        (Some(facility_type), _) if facility_type.is_known() => {
+            format!("{} out", facility_type.label())
+        }
+        _ => "Access issue".to_owned(),
+    }
+}
+
+/// The line a facility endpoint is on: "Red" from "Red Line platform".
+fn endpoint_line(endpoint: &str) -> Option<&str> {
+    let (before, _) = endpoint.split_once(" Line")?;
+    before.split_whitespace().last()
+}
+
+/// Shortens a facility's short name where it can: "Red Line platform to Green
+/// Line platform" is a transfer, so it becomes "Red to Green transfer".
+/// Anything else ("Tremont Street to lobby") is kept as is.
+fn facility_detail(short_name: &str) -> String {
+    if let Some((from, to)) = short_name.split_once(" to ")
+        && let (Some(from), Some(to)) = (endpoint_line(from), endpoint_line(to))
+    {
+        return format!("{from} to {to} transfer");
+    }
+    short_name.to_owned()
+}
+
+/// A title naming what's out and where, e.g. "Elevator out: Park Street (Red
+/// to Green transfer)". `None` for alerts that aren't accessibility alerts.
+///
+/// Without facility or station data, the header's first sentence stands in
+/// for the place.
+pub fn accessibility_title(alert: &Alert, stops: &StopDirectory) -> Option<String> {
+    if !is_accessibility_alert(alert) {
+        return None;
+    }
+
+    let facility = alert
+        .facility_ids()
+        .into_iter()
+        .find_map(|id| stops.facility(id));
+    let outage = outage(alert, facility.map(|f| &f.attributes.facility_type));
+
+    let station = facility
+        .and_then(|f| f.stop())
+        .or_else(|| stops.stations(alert).into_iter().next())
+        .map(|station| stops.name(station));
+    let Some(station) = station else {
+        let header = first_sentence(strip_line_prefix(&alert.attributes.header));
+        return Some(format!("{outage}: {header}"));
+    };
+
+    Some(
+        match facility.and_then(|f| f.attributes.short_name.as_deref()) {
+            Some(short_name) => format!("{outage}: {station} ({})", facility_detail(short_name)),
+            None => format!("{outage}: {station}"),
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{Facilities, InformedEntity, Stops};
+
+    fn directory() -> StopDirectory {
+        let stops: Stops = serde_json::from_str(
+            r#"{"data": [
+                {"id": "place-pktrm", "attributes": {"name": "Park Street"},
+                 "relationships": {"parent_station": {"data": null}}}
+            ]}"#,
+        )
+        .unwrap();
+        let facilities: Facilities = serde_json::from_str(
+            r#"{"data": [
+                {"id": "804", "attributes": {
+                    "long_name": "Park Street Elevator 804 (Red Line platform to Green Line platform)",
+                    "short_name": "Red Line platform to Green Line platform",
+                    "type": "ELEVATOR"},
+                 "relationships": {"stop": {"data": {"id": "place-pktrm"}}}},
+                {"id": "805", "attributes": {
+                    "short_name": "Tremont Street to lobby",
+                    "type": "ESCALATOR"},
+                 "relationships": {"stop": {"data": {"id": "place-pktrm"}}}}
+            ]}"#,
+        )
+        .unwrap();
+        let mut directory = StopDirectory::default();
+        directory.extend(stops);
+        directory.extend_facilities(facilities);
+        directory
+    }
+
+    fn facility_alert(effect: &str, facility: &str) -> Alert {
+        Alert::builder()
+            .header("Park Street Elevator 804 unavailable due to maintenance")
+            .effect(effect)
+            .entity(InformedEntity {
+                stop: Some("place-pktrm".to_owned()),
+                facility: Some(facility.to_owned()),
+                ..Default::default()
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_title_elevator_transfer() {
+        assert_eq!(
+            accessibility_title(&facility_alert("ELEVATOR_CLOSURE", "804"), &directory()),
+            Some("Elevator out: Park Street (Red to Green transfer)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_title_escalator_keeps_other_short_names() {
+        assert_eq!(
+            accessibility_title(&facility_alert("ESCALATOR_CLOSURE", "805"), &directory()),
+            Some("Escalator out: Park Street (Tremont Street to lobby)".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_title_unknown_facility_uses_station() {
+        assert_eq!(
+            accessibility_title(&facility_alert("ELEVATOR_CLOSURE", "999"), &directory()),
+            Some("Elevator out: Park Street".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_title_without_directory_uses_header() {
+        let alert = Alert::builder()
+            .header("Elevator at Alewife unavailable. Use the ramp.")
+            .effect("ELEVATOR_CLOSURE")
+            .build();
+        assert_eq!(
+            accessibility_title(&alert, &StopDirectory::default()),
+            Some("Elevator out: Elevator at Alewife unavailable".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_title_access_issue() {
+        let alert = Alert::builder()
+            .effect("ACCESS_ISSUE")
+            .stop("Red", "place-pktrm")
+            .build();
+        assert_eq!(
+            accessibility_title(&alert, &directory()),
+            Some("Access issue: Park Street".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_title_none_for_other_alerts() {
+        let alert = Alert::builder().effect("DELAY").route("Red").build();
+        assert_eq!(accessibility_title(&alert, &directory()), None);
+    }
+
+    #[test]
+    fn test_accessibility_filter_keeps_only_outages() {
+        let filter = accessibility_filter();
+        assert!(filter.matches(&facility_alert("ELEVATOR_CLOSURE", "804")));
+        assert!(!filter.matches(&Alert::builder().effect("DELAY").build()));
+    }
+}
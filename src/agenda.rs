@@ -0,0 +1,230 @@
+//! A day-by-day look ahead at planned disruptions, grouped by line, for
+//! planning the week's commutes.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Days, NaiveDate, TimeZone, Timelike};
+use chrono_tz::America::New_York;
+use chrono_tz::Tz;
+
+use crate::line_name;
+use crate::types::Alert;
+
+/// Service runs past midnight, so a day's alerts are the ones active from 3am
+/// to 3am the next day: overnight work ending at 2:30am stays on the night it
+/// started. Daylight saving changes at 2am, so 3am always exists exactly once.
+const SERVICE_DAY_START_HOUR: u32 = 3;
+
+/// The service day `time` falls in.
+pub fn service_date(time: DateTime<Tz>) -> NaiveDate {
+    let date = time.date_naive();
+    if time.hour() < SERVICE_DAY_START_HOUR {
+        date.pred_opt().unwrap_or(date)
+    } else {
+        date
+    }
+}
+
+fn day_start(date: NaiveDate) -> DateTime<Tz> {
+    date.and_hms_opt(SERVICE_DAY_START_HOUR, 0, 0)
+        .and_then(|start| New_York.from_local_datetime(&start).single())
+        .expect("3am exists exactly once every day")
+}
+
+/// One alert on one day of the agenda.
+#[derive(Debug)]
+pub struct AgendaEntry<'a> {
+    pub alert: &'a Alert,
+    /// When the disruption begins, if partway through the day.
+    pub starts: Option<DateTime<Tz>>,
+    /// When it ends, if partway through the day.
+    pub ends: Option<DateTime<Tz>>,
+}
+
+impl AgendaEntry<'_> {
+    /// "all day", "from 8:45pm", "until 5am", or "8:45pm - 2:30am".
+    pub fn hours(&self) -> String {
+        let time = |t: DateTime<Tz>| {
+            let format = if t.minute() == 0 { "%-I%P" } else { "%-I:%M%P" };
+            t.format(format).to_string()
+        };
+        match (self.starts, self.ends) {
+            (None, None) => "all day".to_owned(),
+            (Some(s), None) => format!("from {}", time(s)),
+            (None, Some(e)) => format!("until {}", time(e)),
+            (Some(s), Some(e)) => format!("{} - {}", time(s), time(e)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AgendaDay<'a> {
+    pub date: NaiveDate,
+    /// Each line with alerts that day, in name order, and its alerts in the
+    /// order they begin.
+    pub lines: Vec<(&'static str, Vec<AgendaEntry<'a>>)>,
+}
+
+/// The `days` service days from the one `from` falls in, each with the alerts
+/// active sometime between `from` and the end of that day.
+pub fn agenda<'a>(alerts: &[&'a Alert], from: DateTime<Tz>, days: u64) -> Vec<AgendaDay<'a>> {
+    let first = service_date(from);
+    (0..days)
+        .filter_map(|offset| first.checked_add_days(Days::new(offset)))
+        .map(|date| {
+            let start = day_start(date).max(from);
+            let end = day_start(date + Days::new(1));
+
+            let mut lines: BTreeMap<&'static str, Vec<AgendaEntry<'a>>> = BTreeMap::new();
+            for &alert in alerts {
+                if let Some(entry) = entry(alert, start, end) {
+                    lines.entry(line_name(alert)).or_default().push(entry);
+                }
+            }
+            for entries in lines.values_mut() {
+                entries.sort_by_key(|entry| entry.starts.unwrap_or(start));
+            }
+
+            AgendaDay {
+                date,
+                lines: lines.into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+/// `alert` between `start` and `end`, if any of its active periods overlap
+/// them. Periods that overlap the same day are merged into one span.
+fn entry(alert: &Alert, start: DateTime<Tz>, end: DateTime<Tz>) -> Option<AgendaEntry<'_>> {
+    let mut span: Option<(DateTime<Tz>, DateTime<Tz>)> = None;
+    for period in &alert.attributes.active_period {
+        let from = period
+            .start
+            .map_or(start, |s| s.with_timezone(&New_York).max(start));
+        let until = period
+            .end
+            .map_or(end, |e| e.with_timezone(&New_York).min(end));
+        if from < until {
+            span = Some(match span {
+                Some((a, b)) => (a.min(from), b.max(until)),
+                None => (from, until),
+            });
+        }
+    }
+
+    let (from, until) = span?;
+    Some(AgendaEntry {
+        alert,
+        starts: (from > start).then_some(from),
+        ends: (until < end).then_some(until),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Tz> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&New_York)
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    fn alert(id: &str, route: &str, start: &str, end: Option<&str>) -> Alert {
+        Alert::builder()
+            .id(id)
+            .route(route)
+            .period(Some(start), end)
+            .build()
+    }
+
+    #[test]
+    fn test_service_date_runs_past_midnight() {
+        assert_eq!(
+            service_date(at("2025-10-25T02:30:00-04:00")),
+            date("2025-10-24")
+        );
+        assert_eq!(
+            service_date(at("2025-10-25T03:00:00-04:00")),
+            date("2025-10-25")
+        );
+    }
+
+    #[test]
+    fn test_multi_day_alert_spans_each_day() {
+        let shuttle = alert(
+            "1",
+            "Red",
+            "2025-10-24T20:45:00-04:00",
+            Some("2025-10-27T02:30:00-04:00"),
+        );
+        let days = agenda(&[&shuttle], at("2025-10-24T09:00:00-04:00"), 4);
+
+        let hours: Vec<Vec<String>> = days
+            .iter()
+            .map(|day| {
+                day.lines
+                    .iter()
+                    .flat_map(|(_, entries)| entries.iter().map(AgendaEntry::hours))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            hours,
+            vec![
+                vec!["from 8:45pm".to_owned()],
+                vec!["all day".to_owned()],
+                vec!["until 2:30am".to_owned()],
+                vec![],
+            ]
+        );
+        assert_eq!(days[3].date, date("2025-10-27"));
+    }
+
+    #[test]
+    fn test_alerts_are_grouped_by_line_in_start_order() {
+        let late = alert("late", "Red", "2025-10-24T21:00:00-04:00", None);
+        let early = alert(
+            "early",
+            "Red",
+            "2025-10-24T10:00:00-04:00",
+            Some("2025-10-24T15:00:00-04:00"),
+        );
+        let orange = alert("orange", "Orange", "2025-10-24T12:00:00-04:00", None);
+        let days = agenda(
+            &[&late, &orange, &early],
+            at("2025-10-24T09:00:00-04:00"),
+            1,
+        );
+
+        let lines: Vec<(&str, Vec<&str>)> = days[0]
+            .lines
+            .iter()
+            .map(|(line, entries)| (*line, entries.iter().map(|e| e.alert.id.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("Orange Line", vec!["orange"]),
+                ("Red Line", vec!["early", "late"])
+            ]
+        );
+        assert_eq!(days[0].lines[1].1[0].hours(), "10am - 3pm");
+    }
+
+    #[test]
+    fn test_ended_alerts_are_left_out() {
+        let over = alert(
+            "1",
+            "Red",
+            "2025-10-24T05:00:00-04:00",
+            Some("2025-10-24T08:00:00-04:00"),
+        );
+        let days = agenda(&[&over], at("2025-10-24T09:00:00-04:00"), 1);
+        assert!(days[0].lines.is_empty());
+    }
+}
@@ -5,22 +5,34 @@ use anyhow::Result;
 use jluszcz_rust_utils::bedrock::BedrockClient;
 use log::debug;
 
+use crate::config::SummarizerSettings;
 use crate::summary::strip_line_prefix;
 
+/// Writes event titles from alert headers. Bedrock does; tests stand in for it.
+pub trait Summarize: Sync {
+    fn generate_summary(&self, header: &str) -> impl Future<Output = Result<String>> + Send;
+}
+
 pub struct BedrockSummarizer {
     client: BedrockClient,
 }
 
 impl BedrockSummarizer {
-    /// Returns `None` when AWS credentials are not configured. The CLI runs without
-    /// credentials in local use; the Lambda is always credentialed.
-    pub async fn from_env() -> Option<Self> {
+    /// Returns `None` when summaries are disabled or AWS credentials are not
+    /// configured. The CLI runs without credentials in local use; the Lambda is
+    /// always credentialed.
+    pub async fn new(settings: &SummarizerSettings) -> Option<Self> {
+        if !settings.enabled {
+            return None;
+        }
         Some(Self {
             client: BedrockClient::from_env_if_credentialed().await?,
         })
     }
+}
 
-    pub async fn generate_summary(&self, header: &str) -> Result<String> {
+impl Summarize for BedrockSummarizer {
+    async fn generate_summary(&self, header: &str) -> Result<String> {
         let prompt = format!(
             "Create a concise summary title for the following public transit alert, \
              suitable for a calendar event title. The title should be brief (under 60 \
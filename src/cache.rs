@@ -0,0 +1,254 @@
+//! Cached API responses. Each is keyed by the query it answers, so one set of
+//! routes' alerts never stands in for another's, and reused only while it's
+//! younger than the cache's maximum age.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::APP_NAME;
+use crate::hash::Fnv1a;
+
+/// Stops and facilities rarely change, so they're kept at least this long.
+pub const REFERENCE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A cached response, with the query it answers and when it was fetched.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub name: String,
+    pub key: String,
+    pub fetched_at: DateTime<Utc>,
+    pub body: String,
+}
+
+impl CacheEntry {
+    fn read(path: &Path) -> Option<Self> {
+        let json = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json)
+            .inspect_err(|e| warn!("Ignoring unreadable cache entry {}: {e}", path.display()))
+            .ok()
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// How long ago the entry was fetched; zero if that's in the future.
+    pub fn age(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.fetched_at).to_std().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    /// `None` when the cache is disabled.
+    max_age: Option<Duration>,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, max_age: Duration) -> Self {
+        Self {
+            dir,
+            max_age: Some(max_age),
+        }
+    }
+
+    /// Always queries, and stores nothing.
+    pub fn disabled() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            max_age: None,
+        }
+    }
+
+    /// In the OS temp directory.
+    pub fn default_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("{APP_NAME}-cache"))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some()
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// This cache, keeping entries for at least `max_age`.
+    pub fn at_least(&self, max_age: Duration) -> Self {
+        Self {
+            dir: self.dir.clone(),
+            max_age: self.max_age.map(|age| age.max(max_age)),
+        }
+    }
+
+    /// Named for a hash of `key` that's the same from one build to the next,
+    /// so that an upgrade doesn't orphan every entry.
+    fn path(&self, name: &str, key: &str) -> PathBuf {
+        let mut hash = Fnv1a::new();
+        hash.feed(key);
+        self.dir.join(format!("{name}-{:016x}.json", hash.finish()))
+    }
+
+    /// The cached response to the `name` query with parameters `key`, if it's
+    /// fresh enough; otherwise `fetch`es and caches a new one.
+    pub async fn query<F, Fut>(&self, name: &str, key: &str, fetch: F) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String>>,
+    {
+        let Some(max_age) = self.max_age else {
+            return fetch().await;
+        };
+
+        let path = self.path(name, key);
+        if let Some(entry) = CacheEntry::read(&path)
+            && entry.key == key
+            && entry.age(Utc::now()) <= max_age
+        {
+            debug!("Using {name} cached at {}", entry.fetched_at);
+            return Ok(entry.body);
+        }
+
+        let entry = CacheEntry {
+            name: name.to_owned(),
+            key: key.to_owned(),
+            fetched_at: Utc::now(),
+            body: fetch().await?,
+        };
+        // A response we can't cache is still a response.
+        if let Err(e) = entry.write(&path) {
+            warn!("Failed to cache {name}: {e:#}");
+        }
+        Ok(entry.body)
+    }
+
+    /// Every entry in the cache directory, oldest first.
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.dir.display()));
+            }
+        };
+
+        let mut entries: Vec<CacheEntry> = dir
+            .filter_map(|file| CacheEntry::read(&file.ok()?.path()))
+            .collect();
+        entries.sort_by_key(|entry| entry.fetched_at);
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A cache in a directory of its own, removed when dropped.
+    struct TestCache(Cache);
+
+    impl TestCache {
+        fn new(test: &str, max_age: Duration) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "{APP_NAME}-test-cache-{test}-{}",
+                std::process::id()
+            ));
+            Self(Cache::new(dir, max_age))
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0.dir);
+        }
+    }
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn test_entry_paths_are_stable() {
+        let cache = Cache::new(PathBuf::from("cache"), HOUR);
+        assert_eq!(
+            cache.path("alerts", "a"),
+            Path::new("cache/alerts-089bc907b544c769.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fresh_entry_is_reused() -> Result<()> {
+        let cache = TestCache::new("fresh", HOUR);
+        let first = cache
+            .0
+            .query("alerts", "k", || async { Ok("one".to_owned()) });
+        assert_eq!(first.await?, "one");
+        let second = cache
+            .0
+            .query("alerts", "k", || async { Ok("two".to_owned()) });
+        assert_eq!(second.await?, "one");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_entries_are_keyed_by_query() -> Result<()> {
+        let cache = TestCache::new("keyed", HOUR);
+        cache
+            .0
+            .query("alerts", "red", || async { Ok("red".to_owned()) })
+            .await?;
+        let other = cache
+            .0
+            .query("alerts", "blue", || async { Ok("blue".to_owned()) });
+        assert_eq!(other.await?, "blue");
+        assert_eq!(cache.0.entries()?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stale_entry_is_refetched() -> Result<()> {
+        let cache = TestCache::new("stale", Duration::ZERO);
+        cache
+            .0
+            .query("alerts", "k", || async { Ok("one".to_owned()) })
+            .await?;
+        std::thread::sleep(Duration::from_millis(5));
+        let second = cache
+            .0
+            .query("alerts", "k", || async { Ok("two".to_owned()) });
+        assert_eq!(second.await?, "two");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_cache_stores_nothing() -> Result<()> {
+        let cache = Cache::disabled();
+        let body = cache.query("alerts", "k", || async { Ok("one".to_owned()) });
+        assert_eq!(body.await?, "one");
+        assert!(!cache.is_enabled());
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_of_missing_dir_is_empty() -> Result<()> {
+        let cache = Cache::new(PathBuf::from("/nonexistent/mbtalerts-cache"), HOUR);
+        assert!(cache.entries()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_at_least_raises_max_age() {
+        let cache = Cache::new(PathBuf::new(), Duration::from_secs(60));
+        assert_eq!(cache.at_least(HOUR).max_age(), Some(HOUR));
+        assert_eq!(Cache::disabled().at_least(HOUR).max_age(), None);
+    }
+}
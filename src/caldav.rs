@@ -0,0 +1,648 @@
+//! CalDAV (RFC 4791) calendars, for syncing to Nextcloud, Fastmail or
+//! Radicale instead of Google. A calendar ID that's a collection URL, e.g.
+//! `https://dav.example.com/calendars/me/red/`, is synced here.
+//!
+//! Each occurrence is its own calendar object resource, `<alert>-<period>.ics`
+//! in the collection, named for its alert and its period's [`PeriodId`], which
+//! records those, its state hash and its AI title as `X-MBTA-*` properties.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{debug, info};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Method, RequestBuilder, Url};
+
+use crate::calendar::{CalendarBackend, EventContent, EventKey, ExistingEvent, PeriodId};
+use crate::config::{CalDavSettings, Secret};
+use crate::ics::{
+    AI_SUMMARY_PROPERTY, ALERT_ID_PROPERTY, PERIOD_PROPERTY, STATE_HASH_PROPERTY, calendar_object,
+    event_fields, event_properties,
+};
+use crate::retry;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+const ICALENDAR: &str = "text/calendar; charset=utf-8";
+const XML: &str = "application/xml; charset=utf-8";
+
+/// Whether `calendar_id` names a CalDAV collection rather than a Google
+/// calendar.
+pub fn is_caldav_url(calendar_id: &str) -> bool {
+    calendar_id.starts_with("https://") || calendar_id.starts_with("http://")
+}
+
+pub struct CalDavCalendar {
+    client: &'static Client,
+    username: Option<String>,
+    password: Option<Secret>,
+}
+
+impl CalDavCalendar {
+    pub fn new(client: &'static Client, settings: &CalDavSettings) -> Self {
+        Self {
+            client,
+            username: settings.username.clone(),
+            password: settings.password.clone(),
+        }
+    }
+
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let req = self.client.request(method, url);
+        match &self.username {
+            Some(username) => req.basic_auth(username, self.password.as_ref().map(Secret::expose)),
+            None => req,
+        }
+    }
+
+    async fn send(&self, req: RequestBuilder, url: &str) -> Result<reqwest::Response> {
+        let response = req
+            .send()
+            .await
+            .with_context(|| format!("Failed to query {url}"))?;
+        retry::check(response).await
+    }
+
+    /// PUTs `event` to `url`, replacing whatever is there.
+    async fn put(&self, url: &str, event: EventContent<'_>) -> Result<()> {
+        let req = self
+            .request(Method::PUT, url)
+            .header(CONTENT_TYPE, ICALENDAR)
+            .body(calendar_object(event));
+        self.send(req, url).await?;
+        Ok(())
+    }
+}
+
+/// Asks for the events that end after `now` and have an alert ID: the ones
+/// this app wrote that a sync may still change.
+fn calendar_query(now: chrono::DateTime<Utc>) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="{DAV}" xmlns:C="{CALDAV}">
+  <D:prop><D:getetag/><C:calendar-data/></D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT">
+        <C:time-range start="{}"/>
+        <C:prop-filter name="{ALERT_ID_PROPERTY}"/>
+      </C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#,
+        now.format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+/// The href and calendar data of each resource in a multistatus response.
+fn calendar_objects(xml: &str) -> Result<Vec<(String, String)>> {
+    let doc = roxmltree::Document::parse(xml).context("Invalid CalDAV multistatus response")?;
+    Ok(doc
+        .descendants()
+        .filter(|node| node.has_tag_name((DAV, "response")))
+        .filter_map(|response| {
+            let href = response
+                .children()
+                .find(|node| node.has_tag_name((DAV, "href")))?
+                .text()?;
+            let data = response
+                .descendants()
+                .find(|node| node.has_tag_name((CALDAV, "calendar-data")))?
+                .text()?;
+            Some((href.trim().to_owned(), data.to_owned()))
+        })
+        .collect())
+}
+
+impl CalendarBackend for CalDavCalendar {
+    async fn list_alert_events(
+        &self,
+        calendar_id: &str,
+    ) -> Result<HashMap<EventKey, ExistingEvent>> {
+        let collection =
+            Url::parse(calendar_id).with_context(|| format!("Invalid CalDAV URL {calendar_id}"))?;
+
+        debug!("Listing calendar events for {calendar_id}");
+        let req = self
+            .request(Method::from_bytes(b"REPORT")?, calendar_id)
+            .header("Depth", "1")
+            .header(CONTENT_TYPE, XML)
+            .body(calendar_query(Utc::now()));
+        let xml = self.send(req, calendar_id).await?.text().await?;
+
+        let mut events = HashMap::new();
+        // Servers that ignore the prop-filter also return events of their own,
+        // which have no alert ID and are left alone.
+        for (href, data) in calendar_objects(&xml)? {
+            let mut properties = event_properties(&data);
+            let fields = event_fields(&properties);
+            let Some(alert_id) = properties.remove(ALERT_ID_PROPERTY) else {
+                continue;
+            };
+            let event_url = collection
+                .join(&href)
+                .with_context(|| format!("Invalid event href {href}"))?;
+            events.insert(
+                EventKey {
+                    alert_id,
+                    period: PeriodId::stored(properties.get(PERIOD_PROPERTY).map(String::as_str)),
+                },
+                ExistingEvent {
+                    event_id: event_url.to_string(),
+                    ai_summary: properties.remove(AI_SUMMARY_PROPERTY),
+                    state_hash: properties.remove(STATE_HASH_PROPERTY),
+                    fields,
+                },
+            );
+        }
+        info!("Listed {} calendar events for {calendar_id}", events.len());
+
+        Ok(events)
+    }
+
+    /// A PUT without `If-None-Match`, so an event for the same occurrence
+    /// that the listing missed is replaced rather than failing the sync.
+    async fn create_event(&self, calendar_id: &str, event: EventContent<'_>) -> Result<()> {
+        let alert = event.occurrence.alert;
+        let url = format!(
+            "{calendar_id}{}-{}.ics",
+            alert.id,
+            event.occurrence.period_id()
+        );
+        self.put(&url, event).await?;
+        info!("Created calendar event for {}", event.occurrence.key());
+        Ok(())
+    }
+
+    /// `event_id` is the event's URL.
+    async fn update_event(
+        &self,
+        _calendar_id: &str,
+        event_id: &str,
+        event: EventContent<'_>,
+    ) -> Result<()> {
+        self.put(event_id, event).await?;
+        info!(
+            "Updated calendar event {event_id} for {}",
+            event.occurrence.key()
+        );
+        Ok(())
+    }
+
+    async fn delete_event(&self, _calendar_id: &str, event_id: &str) -> Result<()> {
+        let req = self.request(Method::DELETE, event_id);
+        retry::ignore_gone(self.send(req, event_id).await, event_id)?;
+        info!("Deleted calendar event {event_id}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use axum::Router;
+    use axum::body::Bytes;
+    use axum::http::{HeaderMap, Method as HttpMethod, StatusCode as HttpStatus, Uri};
+    use axum::response::IntoResponse;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::ai::Summarize;
+    use crate::calendar::{CalendarConfig, SyncContext, SyncLimits, plan_calendar, sync_calendar};
+    use crate::config::ConcurrencySettings;
+    use crate::report::CalendarReport;
+    use crate::stops::StopDirectory;
+    use crate::types::Alert;
+
+    /// "Basic" credentials for `user:secret`.
+    const AUTHORIZATION: &str = "Basic dXNlcjpzZWNyZXQ=";
+    const COLLECTION: &str = "/calendars/user/mbta/";
+
+    /// A CalDAV server's calendar objects by path, and how many were written.
+    /// `failures` are statuses to answer requests for a path with, in turn,
+    /// before serving it. `in_flight` counts the writes being served, and
+    /// `most_in_flight` the most there have been at once.
+    #[derive(Debug, Default)]
+    struct Store {
+        objects: BTreeMap<String, String>,
+        writes: usize,
+        failures: BTreeMap<String, Vec<HttpStatus>>,
+        in_flight: usize,
+        most_in_flight: usize,
+    }
+
+    /// How long the stand-in takes over a write, so that writes sent together
+    /// overlap.
+    const WRITE_TIME: Duration = Duration::from_millis(20);
+
+    type SharedStore = Arc<Mutex<Store>>;
+
+    /// Just enough of a CalDAV server for a sync: a REPORT lists every object
+    /// in the collection, however it's filtered; PUT and DELETE write them,
+    /// taking [`WRITE_TIME`].
+    async fn stand_in(
+        axum::extract::State(store): axum::extract::State<SharedStore>,
+        method: HttpMethod,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> axum::response::Response {
+        if headers.get("authorization").and_then(|h| h.to_str().ok()) != Some(AUTHORIZATION) {
+            return HttpStatus::UNAUTHORIZED.into_response();
+        }
+        if method.as_str() != "REPORT" {
+            {
+                let mut store = store.lock().unwrap();
+                store.in_flight += 1;
+                store.most_in_flight = store.most_in_flight.max(store.in_flight);
+            }
+            tokio::time::sleep(WRITE_TIME).await;
+            store.lock().unwrap().in_flight -= 1;
+        }
+        let path = uri.path().to_owned();
+        let mut store = store.lock().unwrap();
+        if let Some(status) = store.failures.get_mut(&path).and_then(Vec::pop) {
+            return status.into_response();
+        }
+        match method.as_str() {
+            "REPORT" if path == COLLECTION => {
+                let responses: String = store
+                    .objects
+                    .iter()
+                    .map(|(href, data)| {
+                        format!(
+                            "<D:response><D:href>{href}</D:href><D:propstat><D:prop>\
+                             <C:calendar-data>{data}</C:calendar-data>\
+                             </D:prop></D:propstat></D:response>"
+                        )
+                    })
+                    .collect();
+                let xml = format!(
+                    r#"<?xml version="1.0"?><D:multistatus xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">{responses}</D:multistatus>"#
+                );
+                (HttpStatus::MULTI_STATUS, xml).into_response()
+            }
+            "PUT" if path.starts_with(COLLECTION) => {
+                let data = String::from_utf8(body.to_vec()).unwrap();
+                store.writes += 1;
+                match store.objects.insert(path, data) {
+                    Some(_) => HttpStatus::NO_CONTENT.into_response(),
+                    None => HttpStatus::CREATED.into_response(),
+                }
+            }
+            "DELETE" => {
+                store.writes += 1;
+                match store.objects.remove(&path) {
+                    Some(_) => HttpStatus::NO_CONTENT.into_response(),
+                    None => HttpStatus::NOT_FOUND.into_response(),
+                }
+            }
+            _ => HttpStatus::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    /// Serves a stand-in on a free port, returning its store and collection
+    /// URL.
+    async fn serve_stand_in() -> (SharedStore, String) {
+        let store = SharedStore::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().fallback(stand_in).with_state(store.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (store, format!("http://{addr}{COLLECTION}"))
+    }
+
+    fn caldav() -> CalDavCalendar {
+        let settings: CalDavSettings =
+            toml::from_str("username = \"user\"\npassword = \"secret\"").unwrap();
+        CalDavCalendar::new(Box::leak(Box::new(Client::new())), &settings)
+    }
+
+    fn alert(id: &str, header: &str, periods: usize) -> Alert {
+        let mut builder = Alert::builder().id(id).header(header).route("Red");
+        for day in 0..periods {
+            let date = format!("2099-06-0{}", day + 1);
+            builder = builder.period(
+                Some(&format!("{date}T10:00:00-04:00")),
+                Some(&format!("{date}T15:00:00-04:00")),
+            );
+        }
+        builder.build()
+    }
+
+    /// Writes event titles from alert headers, recording each header it's
+    /// given.
+    #[derive(Default)]
+    struct Summarizer {
+        headers: Mutex<Vec<String>>,
+    }
+
+    impl Summarize for Summarizer {
+        async fn generate_summary(&self, header: &str) -> Result<String> {
+            self.headers.lock().unwrap().push(header.to_owned());
+            Ok("Shuttles".to_owned())
+        }
+    }
+
+    async fn sync_with(
+        url: &str,
+        alerts: &[&Alert],
+        summarizer: Option<&Summarizer>,
+        limits: &SyncLimits,
+    ) -> Result<CalendarReport> {
+        let config = CalendarConfig::single(url.to_owned());
+        let stops = StopDirectory::default();
+        let cx = SyncContext {
+            config: &config,
+            summarizer,
+            stops: &stops,
+            limits,
+        };
+        sync_calendar(&caldav(), url, alerts, &cx).await
+    }
+
+    async fn sync(url: &str, alerts: &[&Alert]) -> Result<CalendarReport> {
+        sync_with(url, alerts, None, &SyncLimits::default()).await
+    }
+
+    /// The period `alert` gives `day`, counting from June 1.
+    fn period(day: usize) -> PeriodId {
+        format!("2099060{}T140000Z", day + 1).parse().unwrap()
+    }
+
+    fn key(alert_id: &str, day: usize) -> EventKey {
+        EventKey {
+            alert_id: alert_id.to_owned(),
+            period: period(day),
+        }
+    }
+
+    /// The path of the object for `alert_id`'s period on `day`.
+    fn object(alert_id: &str, day: usize) -> String {
+        format!("{COLLECTION}{alert_id}-{}.ics", period(day))
+    }
+
+    fn paths(store: &SharedStore) -> Vec<String> {
+        store.lock().unwrap().objects.keys().cloned().collect()
+    }
+
+    #[test]
+    fn test_is_caldav_url() {
+        assert!(is_caldav_url("https://dav.example.com/calendars/me/red/"));
+        assert!(!is_caldav_url("abc123@group.calendar.google.com"));
+    }
+
+    #[test]
+    fn test_calendar_objects() -> Result<()> {
+        let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+            <d:response>
+                <d:href>/dav/red/1-0.ics</d:href>
+                <d:propstat><d:prop><cal:calendar-data>BEGIN:VCALENDAR&#13;
+END:VCALENDAR</cal:calendar-data></d:prop></d:propstat>
+            </d:response>
+            <d:response><d:href>/dav/red/</d:href></d:response>
+        </d:multistatus>"#;
+        assert_eq!(
+            calendar_objects(xml)?,
+            vec![(
+                "/dav/red/1-0.ics".to_owned(),
+                "BEGIN:VCALENDAR\r\nEND:VCALENDAR".to_owned()
+            )]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_creates_one_object_per_period() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 2);
+        let delay = alert("2", "Red Line delays.", 1);
+        sync(&url, &[&shuttle, &delay]).await?;
+
+        assert_eq!(
+            paths(&store),
+            vec![object("1", 0), object("1", 1), object("2", 0)]
+        );
+        let listed = caldav().list_alert_events(&url).await?;
+        let first = &listed[&key("1", 0)];
+        assert_eq!(first.event_id, format!("{url}1-{}.ics", period(0)));
+        assert_eq!(
+            first.state_hash,
+            Some(crate::calendar::event_state_hash(
+                &shuttle,
+                0,
+                &StopDirectory::default()
+            ))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resync_writes_only_changes() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 1);
+        let delay = alert("2", "Red Line delays.", 1);
+        sync(&url, &[&shuttle, &delay]).await?;
+        assert_eq!(store.lock().unwrap().writes, 2);
+
+        // Unchanged: nothing to write.
+        let report = sync(&url, &[&shuttle, &delay]).await?;
+        assert_eq!(store.lock().unwrap().writes, 2);
+        assert_eq!(report.skipped.count(), 2);
+        assert_eq!(report.created.count() + report.updated.count(), 0);
+
+        // One alert changed and the other cleared.
+        let longer = alert("1", "Shuttle buses replace Red Line service all day.", 1);
+        let report = sync(&url, &[&longer]).await?;
+        assert_eq!(report.updated.events, vec![key("1", 0)]);
+        assert_eq!(report.deleted.events, vec![key("2", 0)]);
+        assert_eq!(report.skipped.count(), 0);
+        let store = store.lock().unwrap();
+        assert_eq!(store.writes, 4);
+        assert_eq!(
+            store.objects.keys().collect::<Vec<_>>(),
+            vec![&object("1", 0)]
+        );
+        assert!(store.objects[&object("1", 0)].contains("all day"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        let path = object("1", 0);
+        let failures = |statuses: Vec<HttpStatus>| {
+            store
+                .lock()
+                .unwrap()
+                .failures
+                .insert(path.clone(), statuses);
+        };
+
+        // A create is retried when it was turned away unapplied...
+        failures(vec![HttpStatus::TOO_MANY_REQUESTS]);
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 1);
+        let report = sync(&url, &[&shuttle]).await?;
+        assert_eq!(report.created.count(), 1);
+        assert!(report.errors.is_empty());
+
+        // ...and an update, which can't be duplicated, after server errors too.
+        failures(vec![
+            HttpStatus::TOO_MANY_REQUESTS,
+            HttpStatus::SERVICE_UNAVAILABLE,
+        ]);
+        let longer = alert("1", "Shuttle buses replace Red Line service all day.", 1);
+        let report = sync(&url, &[&longer]).await?;
+        assert_eq!(report.updated.count(), 1);
+        assert!(report.errors.is_empty());
+        assert!(store.lock().unwrap().objects[&path].contains("all day"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_creates_are_not_retried_after_server_errors() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        // A retry would be answered 200 without the object being written.
+        store.lock().unwrap().failures.insert(
+            object("1", 0),
+            vec![HttpStatus::OK, HttpStatus::BAD_GATEWAY],
+        );
+
+        // The server may have created the event before failing, so the sync
+        // leaves it to the next one, which lists what's there first.
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 1);
+        let report = sync(&url, &[&shuttle]).await?;
+        assert_eq!(report.failed.count(), 1);
+        assert!(report.errors[0].contains("502"), "{}", report.errors[0]);
+        assert!(paths(&store).is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_does_not_stop_the_others() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        store
+            .lock()
+            .unwrap()
+            .failures
+            .insert(object("1", 0), vec![HttpStatus::BAD_REQUEST]);
+
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 1);
+        let delay = alert("2", "Red Line delays.", 1);
+        let report = sync(&url, &[&shuttle, &delay]).await?;
+        assert_eq!(report.failed.events, vec![key("1", 0)]);
+        assert_eq!(report.created.count(), 1);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("400"), "{}", report.errors[0]);
+        assert_eq!(paths(&store), vec![object("2", 0)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_writes_in_flight_are_capped() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        let alerts: Vec<Alert> = (1..=6)
+            .map(|id| alert(&id.to_string(), "Red Line delays.", 1))
+            .collect();
+        let limits = SyncLimits::new(&ConcurrencySettings {
+            summaries: 4,
+            writes: 2,
+        });
+        let report = sync_with(&url, &alerts.iter().collect::<Vec<_>>(), None, &limits).await?;
+
+        assert_eq!(report.created.count(), 6);
+        let store = store.lock().unwrap();
+        assert_eq!(store.writes, 6);
+        assert_eq!(store.most_in_flight, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_each_alert_is_summarized_once() -> Result<()> {
+        let (_store, url) = serve_stand_in().await;
+        let limits = SyncLimits::default();
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 3);
+        let delay = alert("2", "Red Line delays.", 2);
+        sync_with(&url, &[&delay], None, &limits).await?;
+
+        // Three periods created and two updated, from one title each.
+        let summarizer = Summarizer::default();
+        let longer = alert("2", "Red Line delays of about 20 minutes.", 2);
+        let report = sync_with(&url, &[&shuttle, &longer], Some(&summarizer), &limits).await?;
+        assert_eq!(report.created.count(), 3);
+        assert_eq!(report.updated.count(), 2);
+        let mut headers = summarizer.headers.into_inner().unwrap();
+        headers.sort();
+        assert_eq!(
+            headers,
+            vec![
+                "Red Line delays of about 20 minutes.",
+                "Shuttle buses replace Red Line service.",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plan_diffs_without_writing() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        let shuttle = alert("1", "Shuttle buses replace Red Line service.", 1);
+        let delay = alert("2", "Red Line delays.", 1);
+        sync(&url, &[&shuttle, &delay]).await?;
+
+        let mut detailed = alert("1", "Shuttle buses replace Red Line service.", 1);
+        detailed.attributes.description = Some("Use Route 77 instead.".to_owned());
+        let closure = alert("3", "Red Line station closed.", 1);
+        let config = CalendarConfig::single(url.clone());
+        let stops = StopDirectory::default();
+        let limits = SyncLimits::default();
+        let cx = SyncContext::new(&config, None, &stops, &limits);
+        let plan = plan_calendar(&caldav(), &url, &[&detailed, &closure], &cx).await?;
+        assert_eq!(store.lock().unwrap().writes, 2);
+
+        assert_eq!(
+            plan.create.iter().map(|c| &c.key).collect::<Vec<_>>(),
+            vec![&key("3", 0)]
+        );
+        assert_eq!(plan.update.len(), 1);
+        assert_eq!(plan.update[0].key, key("1", 0));
+        let changes = &plan.update[0].changes;
+        assert_eq!(
+            changes.iter().map(|c| c.field).collect::<Vec<_>>(),
+            vec!["description"]
+        );
+        assert!(changes[0].after.as_ref().unwrap().contains("Route 77"));
+        assert_eq!(plan.delete, vec![key("2", 0)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_other_events_are_left_alone() -> Result<()> {
+        let (store, url) = serve_stand_in().await;
+        let theirs = format!("{COLLECTION}birthday.ics");
+        store.lock().unwrap().objects.insert(
+            theirs.clone(),
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:birthday\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+                .to_owned(),
+        );
+
+        sync(&url, &[]).await?;
+        assert_eq!(paths(&store), vec![theirs]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_credentials_fail_the_sync() {
+        let (_, url) = serve_stand_in().await;
+        let anonymous = CalDavCalendar::new(
+            Box::leak(Box::new(Client::new())),
+            &CalDavSettings::default(),
+        );
+        assert!(anonymous.list_alert_events(&url).await.is_err());
+    }
+}
@@ -1,37 +1,422 @@
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
 use gcp_auth::{CustomServiceAccount, TokenProvider};
 use jluszcz_rust_utils::query;
 use log::{debug, info, warn};
 use reqwest::{Client, Response};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::sync::Arc;
-
-use crate::ai::BedrockSummarizer;
-use crate::summary::{LinePrefixMode, generate_or_fallback};
-use crate::types::{Alert, Alerts};
-use crate::{Line, canonical_line, should_sync_alert};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::accessibility::{accessibility_filter, accessibility_title, is_accessibility_alert};
+use crate::ai::{BedrockSummarizer, Summarize};
+use crate::cache::Cache;
+use crate::caldav::{CalDavCalendar, is_caldav_url};
+use crate::config::{ConcurrencySettings, Config};
+use crate::feed_state::FeedState;
+use crate::filter::{EffectFilter, StationFilter};
+use crate::graph::{GraphCalendar, OUTLOOK_PREFIX, is_outlook_id};
+use crate::hash::Fnv1a;
+use crate::mbta::{MbtaClient, Validators};
+use crate::report::{CalendarReport, SyncReport};
+use crate::retry::{self, Retry, with_retries};
+use crate::stops::StopDirectory;
+use crate::summary::{AlertSummary, LinePrefixMode, generate_or_fallback};
+use crate::types::{ActivePeriod, Alert, Alerts, Cause, InformedEntity};
+use crate::{
+    Branch, Line, affected_details, alerts_if_modified, alerts_to_sync, entity_branches,
+    entity_line, primary_branch,
+};
 
 const CAL_API: &str = "https://www.googleapis.com/calendar/v3/calendars";
 const SCOPES: &[&str] = &["https://www.googleapis.com/auth/calendar.events"];
 
+#[derive(Debug, Clone)]
 pub enum CalendarConfig {
     Single(String),
     PerLine {
         map: HashMap<Line, String>,
+        /// Calendars for single branches; a branch without one falls back to
+        /// its line's.
+        branches: HashMap<Branch, String>,
+        /// The calendar for elevator, escalator and access outages, which then
+        /// go nowhere else.
+        accessibility: Option<String>,
         default: String,
+        /// Effect filters for calendars that don't take the run's own, by
+        /// calendar ID.
+        filters: HashMap<String, EffectFilter>,
     },
 }
 
+impl CalendarConfig {
+    pub fn single(id: String) -> Self {
+        let kind = BackendKind::of(&id);
+        CalendarConfig::Single(normalize_calendar_id(id, kind))
+    }
+
+    /// One calendar per line, branch or accessibility key, plus a required
+    /// `default` for everything else.
+    pub fn per_line(targets: HashMap<String, CalendarTarget>) -> Result<Self> {
+        let mut ids = HashMap::new();
+        let mut filters: HashMap<String, EffectFilter> = HashMap::new();
+        for (k, target) in targets {
+            let (id, filter, backend) = match target {
+                CalendarTarget::Id(id) => (id, None, None),
+                CalendarTarget::Detailed {
+                    id,
+                    filter,
+                    backend,
+                } => (id, filter, backend),
+            };
+            let kind = backend.unwrap_or(BackendKind::of(&id));
+            let id = normalize_calendar_id(id, kind);
+            // Calendars are dispatched by their IDs' form, which only an
+            // Outlook ID can be given by marking it.
+            if BackendKind::of(&id) != kind {
+                bail!("Calendar '{k}' is on {kind:?}, but its ID {id} isn't one of that backend's");
+            }
+            if let Some(filter) = filter {
+                filter
+                    .validate()
+                    .with_context(|| format!("Invalid filter for '{k}'"))?;
+                if filters.get(&id).is_some_and(|existing| *existing != filter) {
+                    bail!("Calendar {id} has conflicting filters");
+                }
+                filters.insert(id.clone(), filter);
+            }
+            ids.insert(k, id);
+        }
+
+        let default = ids
+            .remove("default")
+            .context("Calendar IDs must include a \"default\" key")?;
+        let accessibility = ids.remove(ACCESSIBILITY_KEY);
+        if let Some(id) = &accessibility {
+            filters
+                .entry(id.clone())
+                .or_insert_with(accessibility_filter);
+        }
+        let mut map = HashMap::new();
+        let mut branches = HashMap::new();
+        for (k, v) in ids {
+            if let Some(branch) = Branch::from_name(&k) {
+                branches.insert(branch, v);
+            } else {
+                let line = Line::from_name(&k).with_context(|| format!("Unknown line '{k}'"))?;
+                map.insert(line, v);
+            }
+        }
+        Ok(CalendarConfig::PerLine {
+            map,
+            branches,
+            accessibility,
+            default,
+            filters,
+        })
+    }
+
+    /// Every calendar this config names.
+    pub fn calendar_ids(&self) -> HashSet<&str> {
+        match self {
+            CalendarConfig::Single(id) => std::iter::once(id.as_str()).collect(),
+            CalendarConfig::PerLine {
+                map,
+                branches,
+                accessibility,
+                default,
+                ..
+            } => map
+                .values()
+                .chain(branches.values())
+                .chain(accessibility)
+                .map(String::as_str)
+                .chain(std::iter::once(default.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Each calendar ID with what goes on it, sorted, so the same config
+    /// always describes the same way, however its maps iterate.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = match self {
+            CalendarConfig::Single(id) => vec![id.clone()],
+            CalendarConfig::PerLine {
+                map,
+                branches,
+                accessibility,
+                default,
+                filters,
+            } => {
+                let mut lines: Vec<String> = map
+                    .iter()
+                    .map(|(line, id)| format!("{line:?}: {id}"))
+                    .collect();
+                lines.extend(
+                    branches
+                        .iter()
+                        .map(|(branch, id)| format!("{branch:?}: {id}")),
+                );
+                lines.extend(
+                    accessibility
+                        .iter()
+                        .map(|id| format!("{ACCESSIBILITY_KEY}: {id}")),
+                );
+                lines.push(format!("default: {default}"));
+                lines.extend(
+                    filters
+                        .iter()
+                        .map(|(id, filter)| format!("{id} filter: {filter:?}")),
+                );
+                lines
+            }
+        };
+        lines.sort();
+        lines
+    }
+
+    /// The filters to fetch alerts with so every calendar gets its share:
+    /// the calendars' own, and `effects` for the rest.
+    pub fn filters<'a>(&'a self, effects: &'a EffectFilter) -> Vec<&'a EffectFilter> {
+        let mut filters: Vec<&EffectFilter> = self
+            .calendar_ids()
+            .into_iter()
+            .filter_map(|id| self.filter(id))
+            .collect();
+        filters.push(effects);
+        filters
+    }
+
+    /// The effect filter `calendar_id` was configured with, if it has its own.
+    fn filter(&self, calendar_id: &str) -> Option<&EffectFilter> {
+        match self {
+            CalendarConfig::Single(_) => None,
+            CalendarConfig::PerLine { filters, .. } => filters.get(calendar_id),
+        }
+    }
+
+    /// The calendar for an entity on `line`, preferring its branch's own.
+    fn line_calendar(&self, line: Line, branch: Option<Branch>) -> Option<&str> {
+        let CalendarConfig::PerLine { map, branches, .. } = self else {
+            return None;
+        };
+        branch
+            .and_then(|branch| branches.get(&branch))
+            .or_else(|| map.get(&line))
+            .map(String::as_str)
+    }
+
+    /// The calendars for an informed entity on `line`: one per branch it
+    /// reaches, or the line's own. `None` where neither has a calendar.
+    fn entity_calendars(
+        &self,
+        alert: &Alert,
+        entity: &InformedEntity,
+        line: Line,
+    ) -> Vec<Option<&str>> {
+        let branches = entity_branches(alert, entity);
+        if branches.is_empty() {
+            return vec![self.line_calendar(line, None)];
+        }
+        branches
+            .into_iter()
+            .map(|branch| self.line_calendar(line, Some(branch)))
+            .collect()
+    }
+}
+
+/// A calendar service that events can be synced to. Each event records the
+/// [`EventKey`] it covers, the [`event_state_hash`] it was rendered from and
+/// any AI title, which is everything [`sync_calendar`] needs to decide what to
+/// write.
+pub trait CalendarBackend {
+    /// This app's events on `calendar_id` that haven't ended.
+    fn list_alert_events(
+        &self,
+        calendar_id: &str,
+    ) -> impl Future<Output = Result<HashMap<EventKey, ExistingEvent>>> + Send;
+
+    fn create_event(
+        &self,
+        calendar_id: &str,
+        event: EventContent<'_>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        event: EventContent<'_>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Deletes `event_id`. An event that's already gone (404 or 410) counts
+    /// as deleted, as it is when an earlier try went through.
+    fn delete_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// What an event says about itself when listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExistingEvent {
+    /// The backend's ID for the event, to update or delete it by.
+    pub event_id: String,
+    pub ai_summary: Option<String>,
+    pub state_hash: Option<String>,
+    pub fields: EventFields,
+}
+
+/// What an event shows, for a dry run to diff a planned update against. A
+/// field a backend couldn't read back is `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventFields {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub times: Option<EventTimes>,
+}
+
+impl EventFields {
+    /// What `event` renders to.
+    pub fn of(event: EventContent<'_>) -> Self {
+        Self {
+            summary: Some(event.summary.to_owned()),
+            description: Some(event_description(event.occurrence.alert, event.stops)),
+            times: Some(EventTimes::of(event.occurrence)),
+        }
+    }
+}
+
+/// Everything an occurrence's event is rendered from.
+#[derive(Debug, Clone, Copy)]
+pub struct EventContent<'a> {
+    pub occurrence: Occurrence<'a>,
+    pub summary: &'a str,
+    /// Bedrock's title, recorded so the next sync knows it has one.
+    pub ai_summary: Option<&'a str>,
+    pub stops: &'a StopDirectory,
+}
+
+impl<'a> EventContent<'a> {
+    /// `occurrence`'s event, titled from `summaries`.
+    fn with(
+        occurrence: Occurrence<'a>,
+        summaries: &'a HashMap<&str, AlertSummary>,
+        stops: &'a StopDirectory,
+    ) -> Self {
+        let summary = &summaries[occurrence.alert.id.as_str()];
+        Self {
+            occurrence,
+            summary: &summary.display,
+            ai_summary: summary.raw.as_deref(),
+            stops,
+        }
+    }
+}
+
+/// Syncs to each configured calendar on the backend its ID names.
 pub struct CalendarClient {
-    token_provider: Arc<dyn TokenProvider>,
+    /// Each only set up when a calendar is on it, so CalDAV alone needs no
+    /// service account.
+    google: Option<GoogleCalendar>,
+    caldav: Option<CalDavCalendar>,
+    graph: Option<GraphCalendar>,
     config: CalendarConfig,
+    summarizer: Option<BedrockSummarizer>,
+    limits: SyncLimits,
+}
+
+/// Caps on the Bedrock calls and calendar writes in flight at once. They're
+/// shared by every calendar a sync writes to, as the Google calendars all
+/// count against the service account's per-user quota.
+#[derive(Debug)]
+pub struct SyncLimits {
+    summaries: Semaphore,
+    writes: Semaphore,
+}
+
+impl SyncLimits {
+    pub fn new(settings: &ConcurrencySettings) -> Self {
+        Self {
+            summaries: Semaphore::new(settings.summaries),
+            writes: Semaphore::new(settings.writes),
+        }
+    }
+
+    /// Runs `summarize` once fewer than the limit of summaries are in flight.
+    async fn summarize<T>(&self, summarize: impl Future<Output = T>) -> T {
+        let _permit = permit(&self.summaries).await;
+        summarize.await
+    }
+
+    /// Runs `request`, with retries, once fewer than the limit of writes are
+    /// in flight.
+    async fn write<F>(&self, what: &str, retry: Retry, request: impl FnMut() -> F) -> Result<()>
+    where
+        F: Future<Output = Result<()>>,
+    {
+        let _permit = permit(&self.writes).await;
+        with_retries(what, retry, request).await
+    }
+}
+
+async fn permit(semaphore: &Semaphore) -> SemaphorePermit<'_> {
+    semaphore
+        .acquire()
+        .await
+        .expect("sync semaphores are never closed")
+}
+
+/// What every calendar in one sync shares: how alerts are routed and titled,
+/// the stops they're described with, and the limits on work in flight.
+pub struct SyncContext<'a, S = BedrockSummarizer> {
+    pub config: &'a CalendarConfig,
+    pub summarizer: Option<&'a S>,
+    pub stops: &'a StopDirectory,
+    pub limits: &'a SyncLimits,
+}
+
+impl<'a> SyncContext<'a> {
+    pub fn new(
+        config: &'a CalendarConfig,
+        summarizer: Option<&'a BedrockSummarizer>,
+        stops: &'a StopDirectory,
+        limits: &'a SyncLimits,
+    ) -> Self {
+        Self {
+            config,
+            summarizer,
+            stops,
+            limits,
+        }
+    }
+}
+
+impl Default for SyncLimits {
+    fn default() -> Self {
+        Self::new(&ConcurrencySettings::default())
+    }
+}
+
+/// The client for the backend one calendar is on.
+enum Backend<'a> {
+    Google(&'a GoogleCalendar),
+    CalDav(&'a CalDavCalendar),
+    Graph(&'a GraphCalendar),
+}
+
+/// Google Calendar, as a service account the calendars are shared with.
+pub struct GoogleCalendar {
+    token_provider: Arc<dyn TokenProvider>,
     /// The shared client: tuned timeouts, gzip, and a connection pool reused
     /// across the whole process, rather than a bare `Client::new()`.
     client: &'static Client,
-    summarizer: Option<BedrockSummarizer>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,13 +427,25 @@ struct EventList {
     next_page_token: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct CalendarEvent {
     id: String,
+    summary: Option<String>,
+    description: Option<String>,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
     #[serde(rename = "extendedProperties")]
     extended_properties: Option<ExtendedProperties>,
 }
 
+/// A timed event's `dateTime`, or an all-day event's `date`.
+#[derive(Debug, Deserialize)]
+struct EventTime {
+    #[serde(rename = "dateTime")]
+    date_time: Option<DateTime<FixedOffset>>,
+    date: Option<NaiveDate>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ExtendedProperties {
     private: Option<HashMap<String, String>>,
@@ -75,60 +472,211 @@ impl CalendarEvent {
     fn alert_state_hash(&self) -> Option<&str> {
         self.get_private_property("mbta_alert_state_hash")
     }
+
+    fn period_id(&self) -> PeriodId {
+        PeriodId::stored(self.get_private_property("mbta_alert_period"))
+    }
+
+    fn times(&self) -> Option<EventTimes> {
+        let (start, end) = (self.start.as_ref()?, self.end.as_ref()?);
+        match (start.date_time, end.date_time, start.date) {
+            (Some(s), Some(e), _) => Some(EventTimes::Timed(s, e)),
+            (_, _, Some(date)) => Some(EventTimes::AllDay(date)),
+            _ => None,
+        }
+    }
+
+    fn fields(&self) -> EventFields {
+        EventFields {
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            times: self.times(),
+        }
+    }
 }
 
 const CALENDAR_ID_SUFFIX: &str = "@group.calendar.google.com";
 
-fn normalize_calendar_id(id: String) -> String {
-    if id.ends_with(CALENDAR_ID_SUFFIX) {
-        id
-    } else {
-        format!("{id}{CALENDAR_ID_SUFFIX}")
+/// Completes a bare Google calendar ID. A CalDAV collection URL is kept as is,
+/// but ends in a slash so event URLs can be resolved against it. An Outlook ID
+/// is opaque, so it's marked with [`OUTLOOK_PREFIX`] to be told apart.
+fn normalize_calendar_id(id: String, backend: BackendKind) -> String {
+    match backend {
+        BackendKind::CalDav if !id.ends_with('/') => format!("{id}/"),
+        BackendKind::Outlook if !is_outlook_id(&id) => format!("{OUTLOOK_PREFIX}{id}"),
+        BackendKind::Google if !id.ends_with(CALENDAR_ID_SUFFIX) => {
+            format!("{id}{CALENDAR_ID_SUFFIX}")
+        }
+        _ => id,
     }
 }
 
-fn parse_calendar_ids(json_str: &str) -> Result<CalendarConfig> {
-    let raw: HashMap<String, String> =
-        serde_json::from_str(json_str).context("GOOGLE_CALENDAR_IDS is not valid JSON")?;
-    let default = raw
-        .get("default")
-        .cloned()
-        .map(normalize_calendar_id)
-        .context("GOOGLE_CALENDAR_IDS must include a \"default\" key")?;
-    let map = raw
-        .into_iter()
-        .filter(|(k, _)| k != "default")
-        .map(|(k, v)| {
-            let line = Line::from_name(&k)
-                .with_context(|| format!("Unknown line '{k}' in GOOGLE_CALENDAR_IDS"))?;
-            Ok((line, normalize_calendar_id(v)))
-        })
-        .collect::<Result<_>>()?;
-    Ok(CalendarConfig::PerLine { map, default })
+/// The service a calendar is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Google,
+    CalDav,
+    /// Outlook, through Microsoft Graph.
+    Outlook,
 }
 
-impl CalendarClient {
-    pub async fn from_env() -> Result<Self> {
-        let key_json = std::env::var("GOOGLE_SERVICE_ACCOUNT_KEY")
-            .context("GOOGLE_SERVICE_ACCOUNT_KEY env var not set")?;
-        let token_provider: Arc<dyn TokenProvider> =
-            Arc::new(CustomServiceAccount::from_json(&key_json)?);
+impl BackendKind {
+    /// The backend a calendar ID is on, by its form.
+    fn of(calendar_id: &str) -> Self {
+        if is_caldav_url(calendar_id) {
+            BackendKind::CalDav
+        } else if is_outlook_id(calendar_id) {
+            BackendKind::Outlook
+        } else {
+            BackendKind::Google
+        }
+    }
+}
+
+/// A calendar ID, keyed by line in GOOGLE_CALENDAR_IDS or the config file's
+/// `[calendar.ids]`: a bare calendar ID, or one with an effect filter or a
+/// backend of its own.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum CalendarTarget {
+    Id(String),
+    Detailed {
+        id: String,
+        #[serde(default)]
+        filter: Option<EffectFilter>,
+        #[serde(default)]
+        backend: Option<BackendKind>,
+    },
+}
+
+/// The calendar IDs key for the accessibility calendar.
+const ACCESSIBILITY_KEY: &str = "Accessibility";
 
-        let config = if let Ok(json_str) = std::env::var("GOOGLE_CALENDAR_IDS") {
-            parse_calendar_ids(&json_str)?
+impl CalendarClient {
+    pub async fn new(config: &Config) -> Result<Self> {
+        let calendars = config.calendar.calendars.clone().context(
+            "No calendars configured: set calendar.id or calendar.ids in the config file, \
+             or GOOGLE_CALENDAR_ID or GOOGLE_CALENDAR_IDS",
+        )?;
+
+        let kinds: HashSet<BackendKind> = calendars
+            .calendar_ids()
+            .into_iter()
+            .map(BackendKind::of)
+            .collect();
+        let google = if kinds.contains(&BackendKind::Google) {
+            Some(GoogleCalendar::new(config)?)
         } else {
-            let id = std::env::var("GOOGLE_CALENDAR_ID")
-                .context("Either GOOGLE_CALENDAR_IDS or GOOGLE_CALENDAR_ID env var must be set")?;
-            CalendarConfig::Single(normalize_calendar_id(id))
+            None
+        };
+        let caldav = if kinds.contains(&BackendKind::CalDav) {
+            Some(CalDavCalendar::new(
+                query::http_client()?,
+                &config.calendar.caldav,
+            ))
+        } else {
+            None
+        };
+        let graph = if kinds.contains(&BackendKind::Outlook) {
+            Some(GraphCalendar::new(
+                query::http_client()?,
+                &config.calendar.graph,
+            )?)
+        } else {
+            None
         };
 
-        let summarizer = BedrockSummarizer::from_env().await;
+        let summarizer = BedrockSummarizer::new(&config.summarizer).await;
 
+        Ok(Self {
+            google,
+            caldav,
+            graph,
+            config: calendars,
+            summarizer,
+            limits: SyncLimits::new(&config.calendar.concurrency),
+        })
+    }
+
+    /// What a sync that describes stops with `stops` shares across calendars.
+    fn context<'a>(&'a self, stops: &'a StopDirectory) -> SyncContext<'a> {
+        SyncContext::new(&self.config, self.summarizer.as_ref(), stops, &self.limits)
+    }
+
+    /// The client for the backend `calendar_id` is on.
+    fn backend(&self, calendar_id: &str) -> Result<Backend<'_>> {
+        match BackendKind::of(calendar_id) {
+            BackendKind::Google => self
+                .google
+                .as_ref()
+                .map(Backend::Google)
+                .with_context(|| format!("No Google Calendar client for {calendar_id}")),
+            BackendKind::CalDav => self
+                .caldav
+                .as_ref()
+                .map(Backend::CalDav)
+                .with_context(|| format!("No CalDAV client for {calendar_id}")),
+            BackendKind::Outlook => self
+                .graph
+                .as_ref()
+                .map(Backend::Graph)
+                .with_context(|| format!("No Microsoft Graph client for {calendar_id}")),
+        }
+    }
+}
+
+/// Hands each call to the client the calendar is on.
+impl CalendarBackend for Backend<'_> {
+    async fn list_alert_events(
+        &self,
+        calendar_id: &str,
+    ) -> Result<HashMap<EventKey, ExistingEvent>> {
+        match self {
+            Backend::Google(google) => google.list_alert_events(calendar_id).await,
+            Backend::CalDav(caldav) => caldav.list_alert_events(calendar_id).await,
+            Backend::Graph(graph) => graph.list_alert_events(calendar_id).await,
+        }
+    }
+
+    async fn create_event(&self, calendar_id: &str, event: EventContent<'_>) -> Result<()> {
+        match self {
+            Backend::Google(google) => google.create_event(calendar_id, event).await,
+            Backend::CalDav(caldav) => caldav.create_event(calendar_id, event).await,
+            Backend::Graph(graph) => graph.create_event(calendar_id, event).await,
+        }
+    }
+
+    async fn update_event(
+        &self,
+        calendar_id: &str,
+        event_id: &str,
+        event: EventContent<'_>,
+    ) -> Result<()> {
+        match self {
+            Backend::Google(google) => google.update_event(calendar_id, event_id, event).await,
+            Backend::CalDav(caldav) => caldav.update_event(calendar_id, event_id, event).await,
+            Backend::Graph(graph) => graph.update_event(calendar_id, event_id, event).await,
+        }
+    }
+
+    async fn delete_event(&self, calendar_id: &str, event_id: &str) -> Result<()> {
+        match self {
+            Backend::Google(google) => google.delete_event(calendar_id, event_id).await,
+            Backend::CalDav(caldav) => caldav.delete_event(calendar_id, event_id).await,
+            Backend::Graph(graph) => graph.delete_event(calendar_id, event_id).await,
+        }
+    }
+}
+
+impl GoogleCalendar {
+    pub fn new(config: &Config) -> Result<Self> {
+        let key_json = config.calendar.service_account_key()?;
+        let token_provider: Arc<dyn TokenProvider> =
+            Arc::new(CustomServiceAccount::from_json(&key_json)?);
         Ok(Self {
             token_provider,
-            config,
             client: query::http_client()?,
-            summarizer,
         })
     }
 
@@ -139,10 +687,20 @@ impl CalendarClient {
 
     async fn send_authenticated(&self, req: reqwest::RequestBuilder) -> Result<Response> {
         let token = self.access_token().await?;
-        query::send(req.bearer_auth(&token)).await
+        let response = req
+            .bearer_auth(&token)
+            .send()
+            .await
+            .context("Failed to query Google Calendar")?;
+        retry::check(response).await
     }
+}
 
-    async fn list_alert_events(&self, calendar_id: &str) -> Result<Vec<CalendarEvent>> {
+impl CalendarBackend for GoogleCalendar {
+    async fn list_alert_events(
+        &self,
+        calendar_id: &str,
+    ) -> Result<HashMap<EventKey, ExistingEvent>> {
         let mut events = Vec::new();
         let mut page_token: Option<String> = None;
         let time_min = chrono::Utc::now().to_rfc3339();
@@ -162,7 +720,11 @@ impl CalendarClient {
                 req = req.query(&[("pageToken", pt.as_str())]);
             }
 
-            let response: EventList = query::send(req).await?.json().await?;
+            let response = req
+                .send()
+                .await
+                .context("Failed to query Google Calendar")?;
+            let response: EventList = retry::check(response).await?.json().await?;
             events.extend(response.items);
 
             match response.next_page_token {
@@ -172,21 +734,36 @@ impl CalendarClient {
         }
         info!("Listed {} calendar events for {calendar_id}", events.len());
 
-        Ok(events)
+        Ok(events
+            .iter()
+            .filter_map(|event| {
+                let key = EventKey {
+                    alert_id: event.alert_id()?.to_owned(),
+                    period: event.period_id(),
+                };
+                let existing = ExistingEvent {
+                    event_id: event.id.clone(),
+                    ai_summary: event.ai_summary().map(str::to_owned),
+                    state_hash: event.alert_state_hash().map(str::to_owned),
+                    fields: event.fields(),
+                };
+                Some((key, existing))
+            })
+            .collect())
     }
 
-    async fn create_event(
-        &self,
-        calendar_id: &str,
-        alert: &Alert,
-        summary: &str,
-        ai_summary_raw: Option<&str>,
-    ) -> Result<()> {
+    async fn create_event(&self, calendar_id: &str, event: EventContent<'_>) -> Result<()> {
         let events_url = format!("{CAL_API}/{calendar_id}/events");
-        let body = event_body(alert, summary, ai_summary_raw)?;
+        let body = event_body(
+            event.occurrence.alert,
+            event.occurrence.period,
+            event.summary,
+            event.ai_summary,
+            event.stops,
+        )?;
         self.send_authenticated(self.client.post(&events_url).json(&body))
             .await?;
-        info!("Created calendar event for alert {}", alert.id);
+        info!("Created calendar event for {}", event.occurrence.key());
         Ok(())
     }
 
@@ -194,22 +771,31 @@ impl CalendarClient {
         &self,
         calendar_id: &str,
         event_id: &str,
-        alert: &Alert,
-        summary: &str,
-        ai_summary_raw: Option<&str>,
+        event: EventContent<'_>,
     ) -> Result<()> {
         let event_url = format!("{CAL_API}/{calendar_id}/events/{event_id}");
-        let body = event_body(alert, summary, ai_summary_raw)?;
+        let body = event_body(
+            event.occurrence.alert,
+            event.occurrence.period,
+            event.summary,
+            event.ai_summary,
+            event.stops,
+        )?;
         self.send_authenticated(self.client.put(&event_url).json(&body))
             .await?;
-        info!("Updated calendar event {event_id} for alert {}", alert.id);
+        info!(
+            "Updated calendar event {event_id} for {}",
+            event.occurrence.key()
+        );
         Ok(())
     }
 
     async fn delete_event(&self, calendar_id: &str, event_id: &str) -> Result<()> {
         let event_url = format!("{CAL_API}/{calendar_id}/events/{event_id}");
-        self.send_authenticated(self.client.delete(&event_url))
-            .await?;
+        let deleted = self
+            .send_authenticated(self.client.delete(&event_url))
+            .await;
+        retry::ignore_gone(deleted, event_id)?;
         info!("Deleted calendar event {event_id}");
         Ok(())
     }
@@ -218,34 +804,57 @@ impl CalendarClient {
 fn calendar_ids_for_alert<'a>(alert: &Alert, config: &'a CalendarConfig) -> Vec<&'a str> {
     match config {
         CalendarConfig::Single(id) => vec![id.as_str()],
-        CalendarConfig::PerLine { map, default } => {
+        CalendarConfig::PerLine {
+            accessibility: Some(id),
+            ..
+        } if is_accessibility_alert(alert) => vec![id.as_str()],
+        CalendarConfig::PerLine { default, .. } => {
             let mut ids: HashSet<&str> = HashSet::new();
             let mut found_any_route = false;
 
+            let insert_calendar = |ids: &mut HashSet<&'a str>, line: Line, id: Option<&'a str>| {
+                if let Some(id) = id {
+                    ids.insert(id);
+                } else {
+                    warn!(
+                        "Alert {}: line '{}' not in GOOGLE_CALENDAR_IDS, using default",
+                        alert.id,
+                        line.name()
+                    );
+                    ids.insert(default.as_str());
+                }
+            };
+
             for entity in &alert.attributes.informed_entity {
-                let Some(route) = &entity.route else {
-                    continue;
-                };
-                found_any_route = true;
-                match canonical_line(route) {
-                    Some(line) => {
-                        if let Some(id) = map.get(&line) {
-                            ids.insert(id.as_str());
-                        } else {
+                if let Some(route) = &entity.route {
+                    found_any_route = true;
+                    match entity_line(entity) {
+                        Some(line) => {
+                            for id in config.entity_calendars(alert, entity, line) {
+                                insert_calendar(&mut ids, line, id);
+                            }
+                        }
+                        None => {
                             warn!(
-                                "Alert {}: line '{}' not in GOOGLE_CALENDAR_IDS, using default",
-                                alert.id,
-                                line.name()
+                                "Alert {}: unknown route '{}', using default",
+                                alert.id, route
                             );
                             ids.insert(default.as_str());
                         }
                     }
-                    None => {
-                        warn!(
-                            "Alert {}: unknown route '{}', using default",
-                            alert.id, route
-                        );
-                        ids.insert(default.as_str());
+                } else if let Some(route_type) = entity.route_type {
+                    // A route-type-wide entity ("all subway") is on every line
+                    // of that mode.
+                    for &line in Line::for_route_type(route_type) {
+                        found_any_route = true;
+                        insert_calendar(&mut ids, line, config.line_calendar(line, None));
+                        for &branch in line.branches() {
+                            insert_calendar(
+                                &mut ids,
+                                line,
+                                config.line_calendar(line, Some(branch)),
+                            );
+                        }
                     }
                 }
             }
@@ -278,64 +887,180 @@ fn calendar_ids_by_alert<'a, 'c>(
         .collect()
 }
 
-pub async fn sync_alerts(alerts: &Alerts, cal: &CalendarClient) -> Result<()> {
-    let calendar_ids: HashSet<&str> = match &cal.config {
-        CalendarConfig::Single(id) => std::iter::once(id.as_str()).collect(),
-        CalendarConfig::PerLine { map, default } => map
-            .values()
-            .map(String::as_str)
-            .chain(std::iter::once(default.as_str()))
-            .collect(),
-    };
+/// The alerts bound for each calendar in `config`: those whose routes land
+/// there and that pass its filter, or `effects` when it has none of its own.
+pub fn alerts_by_calendar<'a, 'c>(
+    alerts: &[&'a Alert],
+    effects: &EffectFilter,
+    config: &'c CalendarConfig,
+) -> Vec<(&'c str, Vec<&'a Alert>)> {
+    let ids_by_alert = calendar_ids_by_alert(alerts, config);
 
-    let sync_alerts: Vec<&Alert> = alerts
-        .data
-        .iter()
-        .filter(|a| {
-            if !should_sync_alert(a) {
-                debug!(
-                    "Skipping station issue alert {}: {}",
-                    a.id, a.attributes.effect
-                );
-                false
-            } else {
-                true
-            }
+    config
+        .calendar_ids()
+        .into_iter()
+        .map(|calendar_id| {
+            let filter = config.filter(calendar_id).unwrap_or(effects);
+            let cal_alerts = alerts
+                .iter()
+                .copied()
+                .filter(|a| {
+                    filter.matches(a)
+                        && ids_by_alert
+                            .get(a.id.as_str())
+                            .is_some_and(|ids| ids.contains(&calendar_id))
+                })
+                .collect();
+            (calendar_id, cal_alerts)
         })
-        .collect();
+        .collect()
+}
 
-    let ids_by_alert = calendar_ids_by_alert(&sync_alerts, &cal.config);
+/// Fetches alerts and syncs them to every configured calendar, unless the
+/// feed is unchanged since the last sync: then there's nothing to do, and no
+/// Google or Bedrock calls are made. `force` syncs regardless.
+///
+/// The feed's state is only recorded once every calendar syncs without error,
+/// so a failed sync is retried in full.
+pub async fn sync_if_modified(
+    mbta: &MbtaClient,
+    config: &Config,
+    force: bool,
+) -> Result<SyncReport> {
+    let previous = if force {
+        Validators::default()
+    } else {
+        FeedState::load(&config.state_file, &config.sync_key(), Utc::now()).unwrap_or_default()
+    };
 
-    let tasks = calendar_ids.into_iter().map(|calendar_id| {
-        let cal_alerts: Vec<&Alert> = sync_alerts
-            .iter()
-            .copied()
-            .filter(|a| {
-                ids_by_alert
-                    .get(a.id.as_str())
-                    .is_some_and(|ids| ids.contains(&calendar_id))
-            })
-            .collect();
-        async move { sync_calendar(cal, calendar_id, &cal_alerts).await }
-    });
+    let Some((alerts, validators)) = alerts_if_modified(mbta, &config.query, &previous).await?
+    else {
+        info!("MBTA alerts unchanged since the last sync; skipping it");
+        return Ok(SyncReport::unchanged());
+    };
+
+    let cal = CalendarClient::new(config).await?;
+    let report = sync_alerts(mbta, &alerts, &config.effects, &config.stations, &cal).await?;
+
+    if report.has_errors() {
+        warn!("Not recording the feed's state, as some calendars failed to sync");
+    } else if let Err(e) =
+        FeedState::new(config.sync_key(), validators, Utc::now()).save(&config.state_file)
+    {
+        warn!("Failed to save feed state, the next sync will be a full one: {e:#}");
+    }
+    Ok(report)
+}
 
-    futures::future::try_join_all(tasks).await?;
+/// Fetches alerts and plans a sync of them to every configured calendar, even
+/// if the feed is unchanged. The feed's state isn't recorded, so the next real
+/// sync still happens.
+pub async fn dry_run(mbta: &MbtaClient, config: &Config) -> Result<Vec<CalendarPlan>> {
+    let alerts = crate::alerts(mbta, &config.query, &Cache::disabled()).await?;
+    let cal = CalendarClient::new(config).await?;
+    plan_alerts(mbta, &alerts, &config.effects, &config.stations, &cal).await
+}
 
-    Ok(())
+/// What [`sync_alerts`] would do to every configured calendar, in calendar ID
+/// order.
+pub async fn plan_alerts(
+    mbta: &MbtaClient,
+    alerts: &Alerts,
+    effects: &EffectFilter,
+    stations: &StationFilter,
+    cal: &CalendarClient,
+) -> Result<Vec<CalendarPlan>> {
+    let (plan_alerts, stops) = alerts_to_sync(
+        mbta,
+        alerts,
+        &cal.config.filters(effects),
+        stations,
+        &Cache::disabled(),
+    )
+    .await?;
+
+    let cx = &cal.context(&stops);
+    let tasks = alerts_by_calendar(&plan_alerts, effects, &cal.config)
+        .into_iter()
+        .map(|(calendar_id, cal_alerts)| async move {
+            let backend = cal.backend(calendar_id)?;
+            plan_calendar(&backend, calendar_id, &cal_alerts, cx).await
+        });
+
+    let mut plans = futures::future::try_join_all(tasks).await?;
+    plans.sort_by(|a, b| a.calendar_id.cmp(&b.calendar_id));
+    Ok(plans)
 }
 
-fn line_prefix_for_alert(
+/// Syncs `alerts` to every configured calendar. Calendars configured with an
+/// effect filter of their own use it in place of `effects`.
+///
+/// A calendar that fails to sync doesn't stop the others; its error is in its
+/// part of the report.
+pub async fn sync_alerts(
+    mbta: &MbtaClient,
+    alerts: &Alerts,
+    effects: &EffectFilter,
+    stations: &StationFilter,
+    cal: &CalendarClient,
+) -> Result<SyncReport> {
+    let (sync_alerts, stops) = alerts_to_sync(
+        mbta,
+        alerts,
+        &cal.config.filters(effects),
+        stations,
+        &Cache::disabled(),
+    )
+    .await?;
+
+    let cx = &cal.context(&stops);
+    let tasks = alerts_by_calendar(&sync_alerts, effects, &cal.config)
+        .into_iter()
+        .map(|(calendar_id, cal_alerts)| async move {
+            let synced = match cal.backend(calendar_id) {
+                Ok(backend) => sync_calendar(&backend, calendar_id, &cal_alerts, cx).await,
+                Err(e) => Err(e),
+            };
+            synced.unwrap_or_else(|e| {
+                warn!("Failed to sync calendar {calendar_id}: {e:#}");
+                CalendarReport::failed(calendar_id, &e)
+            })
+        });
+
+    let mut calendars = futures::future::join_all(tasks).await;
+    calendars.sort_by(|a, b| a.calendar_id.cmp(&b.calendar_id));
+    Ok(SyncReport {
+        feed_unchanged: false,
+        calendars,
+    })
+}
+
+/// Whether `alert`'s title on `calendar_id` names its line: not when the
+/// calendar is the line's own.
+pub fn line_prefix_for_alert(
     alert: &Alert,
     calendar_id: &str,
     config: &CalendarConfig,
 ) -> LinePrefixMode {
-    let CalendarConfig::PerLine { map, .. } = config else {
+    if matches!(config, CalendarConfig::Single(_)) {
         return LinePrefixMode::Include;
-    };
+    }
+    // On its line's calendar, a branch-only alert keeps the prefix: it's what
+    // says which branch.
+    if let Some(branch) = primary_branch(alert) {
+        return if config.line_calendar(branch.line(), Some(branch)) == Some(calendar_id)
+            && config.line_calendar(branch.line(), None) != Some(calendar_id)
+        {
+            LinePrefixMode::Omit
+        } else {
+            LinePrefixMode::Include
+        };
+    }
     for entity in &alert.attributes.informed_entity {
-        let Some(route) = &entity.route else { continue };
-        if let Some(line) = canonical_line(route)
-            && map.get(&line).map(String::as_str) == Some(calendar_id)
+        if let Some(line) = entity_line(entity)
+            && config
+                .entity_calendars(alert, entity, line)
+                .contains(&Some(calendar_id))
         {
             return LinePrefixMode::Omit;
         }
@@ -343,12 +1068,6 @@ fn line_prefix_for_alert(
     LinePrefixMode::Include
 }
 
-struct ExistingEvent {
-    event_id: String,
-    ai_summary: Option<String>,
-    state_hash: Option<String>,
-}
-
 /// Whether a Bedrock summarizer is configured for this run.
 ///
 /// Without one, no sync will ever populate `mbta_ai_summary`, so requiring it
@@ -360,51 +1079,194 @@ enum AiSummaries {
 }
 
 impl AiSummaries {
+    fn of<S>(summarizer: Option<&S>) -> Self {
+        if summarizer.is_some() {
+            Self::Enabled
+        } else {
+            Self::Disabled
+        }
+    }
+
     fn required(self) -> bool {
         self == Self::Enabled
     }
 }
 
+/// Which of an alert's active periods an event covers: the period's start,
+/// which stays put when the MBTA drops an earlier period from the alert, or,
+/// for a period without one, its index in `active_period`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PeriodId {
+    Start(DateTime<Utc>),
+    Index(usize),
+}
+
+/// How a [`PeriodId::Start`] is written: compact enough for resource names
+/// and UIDs, e.g. `20251024T204500Z`.
+const PERIOD_START_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+impl PeriodId {
+    pub fn of(alert: &Alert, index: usize) -> Self {
+        match alert.period(index).and_then(|p| p.start) {
+            Some(start) => Self::Start(start.with_timezone(&Utc)),
+            None => Self::Index(index),
+        }
+    }
+
+    /// The period a stored event records. Events written before per-period
+    /// sync record none; they always covered the first period.
+    pub fn stored(value: Option<&str>) -> Self {
+        value.and_then(|p| p.parse().ok()).unwrap_or(Self::Index(0))
+    }
+}
+
+impl fmt::Display for PeriodId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Start(start) => start.format(PERIOD_START_FORMAT).fmt(f),
+            Self::Index(index) => index.fmt(f),
+        }
+    }
+}
+
+impl FromStr for PeriodId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(start) = NaiveDateTime::parse_from_str(s, PERIOD_START_FORMAT) {
+            return Ok(Self::Start(start.and_utc()));
+        }
+        let index = s.parse().with_context(|| format!("Invalid period '{s}'"))?;
+        Ok(Self::Index(index))
+    }
+}
+
+impl Serialize for PeriodId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Identifies a synced event: the alert and which of its periods it covers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct EventKey {
+    pub alert_id: String,
+    pub period: PeriodId,
+}
+
+/// One calendar event's worth of an alert: a single entry of its
+/// `active_period`, by index.
+#[derive(Debug, Clone, Copy)]
+pub struct Occurrence<'a> {
+    pub alert: &'a Alert,
+    pub period: usize,
+}
+
+impl fmt::Display for EventKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} period {}", self.alert_id, self.period)
+    }
+}
+
+impl Occurrence<'_> {
+    pub fn period_id(&self) -> PeriodId {
+        PeriodId::of(self.alert, self.period)
+    }
+
+    pub fn key(&self) -> EventKey {
+        EventKey {
+            alert_id: self.alert.id.clone(),
+            period: self.period_id(),
+        }
+    }
+}
+
+fn period_has_ended(period: &ActivePeriod, now: DateTime<Utc>) -> bool {
+    period.end.is_some_and(|end| end <= now)
+}
+
+/// Expands an alert into one occurrence per active period that has not ended.
+///
+/// Ended periods are dropped because `list_alert_events` only returns events
+/// ending after now: planning them would recreate the same past event on every
+/// run. An alert with no active period still gets a single occurrence, which
+/// `event_times` dates today.
+pub fn alert_occurrences(alert: &Alert, now: DateTime<Utc>) -> Vec<Occurrence<'_>> {
+    if alert.attributes.active_period.is_empty() {
+        return vec![Occurrence { alert, period: 0 }];
+    }
+    alert
+        .attributes
+        .active_period
+        .iter()
+        .enumerate()
+        .filter(|(_, period)| !period_has_ended(period, now))
+        .map(|(period, _)| Occurrence { alert, period })
+        .collect()
+}
+
 struct SyncPlan<'a> {
-    to_create: Vec<&'a Alert>,
-    to_update: Vec<(String, &'a Alert)>, // (event_id, alert)
-    to_delete: Vec<String>,              // event_id
+    to_create: Vec<Occurrence<'a>>,
+    to_update: Vec<(String, Occurrence<'a>)>, // (event_id, occurrence)
+    to_delete: Vec<String>,                   // event_id
+    up_to_date: Vec<EventKey>,
+}
+
+impl<'a> SyncPlan<'a> {
+    /// The alerts of the occurrences to create or update, which need titles.
+    fn to_written(&self) -> impl Iterator<Item = &'a Alert> + '_ {
+        self.to_create
+            .iter()
+            .chain(self.to_update.iter().map(|(_, occurrence)| occurrence))
+            .map(|occurrence| occurrence.alert)
+    }
 }
 
 fn plan_calendar_sync<'a>(
-    existing_by_alert_id: &HashMap<String, ExistingEvent>,
+    existing_by_key: &HashMap<EventKey, ExistingEvent>,
     alerts: &[&'a Alert],
+    stops: &StopDirectory,
     ai_summaries: AiSummaries,
+    now: DateTime<Utc>,
 ) -> SyncPlan<'a> {
     let mut to_create = Vec::new();
     let mut to_update = Vec::new();
-    let mut seen: HashSet<String> = HashSet::new();
+    let mut up_to_date = Vec::new();
+    let mut seen: HashSet<EventKey> = HashSet::new();
 
-    for alert in alerts {
-        let current_hash = event_state_hash(alert);
-        match existing_by_alert_id.get(&alert.id) {
+    for occurrence in alerts
+        .iter()
+        .flat_map(|alert| alert_occurrences(alert, now))
+    {
+        let key = occurrence.key();
+        let current_hash = event_state_hash(occurrence.alert, occurrence.period, stops);
+        match existing_by_key.get(&key) {
             Some(ExistingEvent {
                 ai_summary,
                 state_hash: Some(cached_hash),
                 ..
             }) if *cached_hash == current_hash
-                && (!ai_summaries.required() || ai_summary.is_some()) =>
+                && (!ai_summaries.required()
+                    || ai_summary.is_some()
+                    || is_accessibility_alert(occurrence.alert)) =>
             {
                 // Event exists and is already up-to-date; no write needed.
+                up_to_date.push(key.clone());
             }
             Some(ExistingEvent { event_id, .. }) => {
-                to_update.push((event_id.clone(), *alert));
+                to_update.push((event_id.clone(), occurrence));
             }
             None => {
-                to_create.push(*alert);
+                to_create.push(occurrence);
             }
         }
-        seen.insert(alert.id.clone());
+        seen.insert(key);
     }
 
-    let to_delete = existing_by_alert_id
+    // Covers both alerts that went away and periods an alert no longer has.
+    let to_delete = existing_by_key
         .iter()
-        .filter(|(alert_id, _)| !seen.contains(*alert_id))
+        .filter(|(key, _)| !seen.contains(*key))
         .map(|(_, existing)| existing.event_id.clone())
         .collect();
 
@@ -412,59 +1274,319 @@ fn plan_calendar_sync<'a>(
         to_create,
         to_update,
         to_delete,
+        up_to_date,
     }
 }
 
-async fn sync_calendar(cal: &CalendarClient, calendar_id: &str, alerts: &[&Alert]) -> Result<()> {
-    let existing = cal.list_alert_events(calendar_id).await?;
-
-    let mut existing_by_alert_id: HashMap<String, ExistingEvent> = HashMap::new();
-    for event in &existing {
-        if let Some(alert_id) = event.alert_id() {
-            existing_by_alert_id.insert(
-                alert_id.to_owned(),
-                ExistingEvent {
-                    event_id: event.id.clone(),
-                    ai_summary: event.ai_summary().map(str::to_owned),
-                    state_hash: event.alert_state_hash().map(str::to_owned),
-                },
-            );
+/// Brings `calendar_id` on `backend` in line with `alerts`: creates events for
+/// new occurrences, rewrites changed ones, and deletes those whose alert or
+/// period is gone. Titles are generated, and then events written, as many at
+/// once as `cx`'s limits allow.
+///
+/// Each write is retried while it fails transiently; one that still fails is
+/// in the report's errors, and the rest are written regardless. Only failing
+/// to list the calendar's events fails the sync.
+pub async fn sync_calendar(
+    backend: &impl CalendarBackend,
+    calendar_id: &str,
+    alerts: &[&Alert],
+    cx: &SyncContext<'_, impl Summarize>,
+) -> Result<CalendarReport> {
+    let SyncContext { stops, limits, .. } = *cx;
+    let existing_by_key = with_retries(
+        &format!("list events on {calendar_id}"),
+        Retry::Idempotent,
+        || backend.list_alert_events(calendar_id),
+    )
+    .await?;
+    let plan = plan_calendar_sync(
+        &existing_by_key,
+        alerts,
+        stops,
+        AiSummaries::of(cx.summarizer),
+        Utc::now(),
+    );
+    let keys_by_id = keys_by_event_id(&existing_by_key);
+
+    let summaries = alert_summaries(plan.to_written(), calendar_id, cx).await;
+    let content = |occurrence| EventContent::with(occurrence, &summaries, stops);
+
+    let creates = plan.to_create.iter().map(|&occurrence| async move {
+        let key = occurrence.key();
+        let event = content(occurrence);
+        let created = limits
+            .write(
+                &format!("create the event for {key}"),
+                Retry::IfNotApplied,
+                || backend.create_event(calendar_id, event),
+            )
+            .await;
+        (key, created)
+    });
+    let updates = plan
+        .to_update
+        .iter()
+        .map(|(event_id, occurrence)| async move {
+            let key = occurrence.key();
+            let event = content(*occurrence);
+            let updated = limits
+                .write(
+                    &format!("update the event for {key}"),
+                    Retry::Idempotent,
+                    || backend.update_event(calendar_id, event_id, event),
+                )
+                .await;
+            (key, updated)
+        });
+    let deletes = plan.to_delete.iter().map(|event_id| {
+        let key = keys_by_id[event_id.as_str()].clone();
+        async move {
+            let deleted = limits
+                .write(
+                    &format!("delete the event for {key}"),
+                    Retry::Idempotent,
+                    || backend.delete_event(calendar_id, event_id),
+                )
+                .await;
+            (key, deleted)
         }
+    });
+    let (created, updated, deleted) = futures::join!(
+        futures::future::join_all(creates),
+        futures::future::join_all(updates),
+        futures::future::join_all(deletes),
+    );
+
+    let mut report = CalendarReport::new(calendar_id);
+    for key in plan.up_to_date {
+        report.skipped.add(key);
+    }
+    for (key, result) in created {
+        report.record(key, result, |report| &mut report.created);
+    }
+    for (key, result) in updated {
+        report.record(key, result, |report| &mut report.updated);
     }
+    for (key, result) in deleted {
+        report.record(key, result, |report| &mut report.deleted);
+    }
+
+    report.bedrock_fallbacks = bedrock_fallbacks(alerts, cx.summarizer, &summaries);
+    Ok(report)
+}
+
+/// Which event each backend event ID is.
+fn keys_by_event_id(
+    existing_by_key: &HashMap<EventKey, ExistingEvent>,
+) -> HashMap<&str, &EventKey> {
+    existing_by_key
+        .iter()
+        .map(|(key, existing)| (existing.event_id.as_str(), key))
+        .collect()
+}
+
+/// The alerts among `alerts` that got a heuristic title although Bedrock was
+/// there to write one.
+fn bedrock_fallbacks<S>(
+    alerts: &[&Alert],
+    summarizer: Option<&S>,
+    summaries: &HashMap<&str, AlertSummary>,
+) -> Vec<String> {
+    if summarizer.is_none() {
+        return Vec::new();
+    }
+    alerts
+        .iter()
+        .filter(|alert| !is_accessibility_alert(alert))
+        .filter(|alert| {
+            summaries
+                .get(alert.id.as_str())
+                .is_some_and(|summary| summary.raw.is_none())
+        })
+        .map(|alert| alert.id.clone())
+        .collect()
+}
 
-    let ai_summaries = if cal.summarizer.is_some() {
-        AiSummaries::Enabled
-    } else {
-        AiSummaries::Disabled
-    };
+/// What a sync would do to one calendar, without doing it.
+#[derive(Debug, Serialize)]
+pub struct CalendarPlan {
+    pub calendar_id: String,
+    pub create: Vec<PlannedCreate>,
+    pub update: Vec<PlannedUpdate>,
+    pub delete: Vec<EventKey>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlannedCreate {
+    #[serde(flatten)]
+    pub key: EventKey,
+    pub summary: String,
+    pub times: String,
+}
 
-    let plan = plan_calendar_sync(&existing_by_alert_id, alerts, ai_summaries);
+#[derive(Debug, Serialize)]
+pub struct PlannedUpdate {
+    #[serde(flatten)]
+    pub key: EventKey,
+    pub event_id: String,
+    /// Empty when only what the event records about itself is stale, e.g. a
+    /// state hash from before a field was hashed.
+    pub changes: Vec<FieldChange>,
+}
 
-    for alert in plan.to_create {
-        let line_prefix = line_prefix_for_alert(alert, calendar_id, &cal.config);
-        let summary = generate_or_fallback(cal.summarizer.as_ref(), alert, line_prefix).await;
-        cal.create_event(calendar_id, alert, &summary.display, summary.raw.as_deref())
-            .await?;
-    }
+/// One field of an event that an update would rewrite.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The fields that differ between what's on the calendar and what a sync
+/// would write there.
+fn field_changes(before: &EventFields, after: &EventFields) -> Vec<FieldChange> {
+    let times = |fields: &EventFields| fields.times.map(|t| t.to_string());
+    [
+        ("summary", before.summary.clone(), after.summary.clone()),
+        (
+            "description",
+            before.description.clone(),
+            after.description.clone(),
+        ),
+        ("times", times(before), times(after)),
+    ]
+    .into_iter()
+    .filter(|(_, before, after)| before != after)
+    .map(|(field, before, after)| FieldChange {
+        field,
+        before,
+        after,
+    })
+    .collect()
+}
+
+/// What [`sync_calendar`] would do to `calendar_id`, field by field. Events are
+/// listed and titles generated, but nothing is written.
+pub async fn plan_calendar(
+    backend: &impl CalendarBackend,
+    calendar_id: &str,
+    alerts: &[&Alert],
+    cx: &SyncContext<'_, impl Summarize>,
+) -> Result<CalendarPlan> {
+    let stops = cx.stops;
+    let existing_by_key = backend.list_alert_events(calendar_id).await?;
+    let plan = plan_calendar_sync(
+        &existing_by_key,
+        alerts,
+        stops,
+        AiSummaries::of(cx.summarizer),
+        Utc::now(),
+    );
 
-    for (event_id, alert) in &plan.to_update {
-        let line_prefix = line_prefix_for_alert(alert, calendar_id, &cal.config);
-        let summary = generate_or_fallback(cal.summarizer.as_ref(), alert, line_prefix).await;
-        cal.update_event(
-            calendar_id,
+    let summaries = alert_summaries(plan.to_written(), calendar_id, cx).await;
+
+    let mut create = Vec::new();
+    for occurrence in plan.to_create {
+        create.push(PlannedCreate {
+            key: occurrence.key(),
+            summary: summaries[occurrence.alert.id.as_str()].display.clone(),
+            times: EventTimes::of(occurrence).to_string(),
+        });
+    }
+
+    let mut update = Vec::new();
+    for (event_id, occurrence) in plan.to_update {
+        let after = EventFields::of(EventContent::with(occurrence, &summaries, stops));
+        let key = occurrence.key();
+        let changes = existing_by_key
+            .get(&key)
+            .map(|existing| field_changes(&existing.fields, &after))
+            .unwrap_or_default();
+        update.push(PlannedUpdate {
+            key,
             event_id,
-            alert,
-            &summary.display,
-            summary.raw.as_deref(),
-        )
-        .await?;
+            changes,
+        });
     }
 
-    for event_id in &plan.to_delete {
-        cal.delete_event(calendar_id, event_id).await?;
+    let keys_by_id = keys_by_event_id(&existing_by_key);
+    let mut delete: Vec<EventKey> = plan
+        .to_delete
+        .iter()
+        .map(|event_id| keys_by_id[event_id.as_str()].clone())
+        .collect();
+    delete.sort();
+
+    Ok(CalendarPlan {
+        calendar_id: calendar_id.to_owned(),
+        create,
+        update,
+        delete,
+    })
+}
+
+impl fmt::Display for CalendarPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} to create, {} to update, {} to delete",
+            self.calendar_id,
+            self.create.len(),
+            self.update.len(),
+            self.delete.len()
+        )?;
+        for create in &self.create {
+            writeln!(
+                f,
+                "  + {}: {} ({})",
+                create.key, create.summary, create.times
+            )?;
+        }
+        for update in &self.update {
+            writeln!(f, "  ~ {}", update.key)?;
+            if update.changes.is_empty() {
+                writeln!(f, "      (no visible changes)")?;
+            }
+            for change in &update.changes {
+                writeln!(
+                    f,
+                    "      {}: {:?} -> {:?}",
+                    change.field,
+                    change.before.as_deref().unwrap_or(""),
+                    change.after.as_deref().unwrap_or("")
+                )?;
+            }
+        }
+        for delete in &self.delete {
+            writeln!(f, "  - {delete}")?;
+        }
+        Ok(())
     }
+}
 
-    Ok(())
+/// Titles `alerts` on `calendar_id`, as many at once as `cx`'s limits allow.
+/// Every period of an alert shares one title, so each alert is titled once
+/// however often it's listed.
+async fn alert_summaries<'a>(
+    alerts: impl Iterator<Item = &'a Alert>,
+    calendar_id: &str,
+    cx: &SyncContext<'_, impl Summarize>,
+) -> HashMap<&'a str, AlertSummary> {
+    let unique: HashMap<&str, &Alert> = alerts.map(|alert| (alert.id.as_str(), alert)).collect();
+    let tasks = unique.into_iter().map(|(alert_id, alert)| async move {
+        let line_prefix = line_prefix_for_alert(alert, calendar_id, cx.config);
+        let summary = cx
+            .limits
+            .summarize(generate_or_fallback(
+                cx.summarizer,
+                alert,
+                line_prefix,
+                cx.stops,
+            ))
+            .await;
+        (alert_id, summary)
+    });
+    futures::future::join_all(tasks).await.into_iter().collect()
 }
 
 fn next_date(date: &str) -> Result<String> {
@@ -475,39 +1597,86 @@ fn next_date(date: &str) -> Result<String> {
         .to_string())
 }
 
-fn event_times(start: Option<&str>, end: Option<&str>) -> Result<(Value, Value)> {
-    match (start, end) {
-        (Some(s), Some(e)) => Ok((
-            json!({ "dateTime": s, "timeZone": "America/New_York" }),
-            json!({ "dateTime": e, "timeZone": "America/New_York" }),
-        )),
-        (Some(s), None) => {
-            // Open-ended alert: all-day event on the start date (end is exclusive in Google Calendar)
-            let date = s.get(..10).unwrap_or(s);
-            Ok((json!({ "date": date }), json!({ "date": next_date(date)? })))
+/// When an occurrence's event happens, whatever calendar it's rendered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTimes {
+    Timed(DateTime<FixedOffset>, DateTime<FixedOffset>),
+    /// All day on this date.
+    AllDay(NaiveDate),
+}
+
+/// In Boston time, which is what riders read it in.
+impl fmt::Display for EventTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = |t: &DateTime<FixedOffset>| {
+            t.with_timezone(&chrono_tz::America::New_York)
+                .format("%Y-%m-%d %H:%M")
+        };
+        match self {
+            Self::Timed(start, end) => write!(f, "{} to {}", local(start), local(end)),
+            Self::AllDay(date) => write!(f, "all day {date}"),
         }
-        _ => {
+    }
+}
+
+impl EventTimes {
+    pub fn new(start: Option<DateTime<FixedOffset>>, end: Option<DateTime<FixedOffset>>) -> Self {
+        match (start, end) {
+            (Some(s), Some(e)) => Self::Timed(s, e),
+            // Open-ended alert: all-day event on the start date
+            (Some(s), None) => Self::AllDay(s.date_naive()),
             // Alerts are Eastern-time events; using the UTC date would roll to
             // tomorrow after ~7-8pm ET.
-            let today = chrono::Utc::now()
-                .with_timezone(&chrono_tz::America::New_York)
-                .format("%Y-%m-%d")
-                .to_string();
-            let tomorrow = next_date(&today)?;
-            Ok((json!({ "date": today }), json!({ "date": tomorrow })))
+            _ => Self::AllDay(
+                chrono::Utc::now()
+                    .with_timezone(&chrono_tz::America::New_York)
+                    .date_naive(),
+            ),
+        }
+    }
+
+    /// The times of `occurrence`'s active period.
+    pub fn of(occurrence: Occurrence<'_>) -> Self {
+        let period = occurrence.alert.period(occurrence.period);
+        Self::new(period.and_then(|p| p.start), period.and_then(|p| p.end))
+    }
+}
+
+fn event_times(
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+) -> Result<(Value, Value)> {
+    match EventTimes::new(start, end) {
+        EventTimes::Timed(s, e) => Ok((
+            json!({ "dateTime": s.to_rfc3339(), "timeZone": "America/New_York" }),
+            json!({ "dateTime": e.to_rfc3339(), "timeZone": "America/New_York" }),
+        )),
+        EventTimes::AllDay(date) => {
+            // End is exclusive in Google Calendar
+            let date = date.format("%Y-%m-%d").to_string();
+            let next = next_date(&date)?;
+            Ok((json!({ "date": date }), json!({ "date": next })))
         }
     }
 }
 
 /// Builds the calendar event description from available alert fields.
 ///
-/// Always includes the alert header. Appends the full description and URL
+/// Always includes the alert header. Appends the full description, the
+/// affected stations and direction, the cause (when the MBTA gave one), and URL
 /// on separate sections when present.
-fn event_description(alert: &Alert) -> String {
+pub fn event_description(alert: &Alert, stops: &StopDirectory) -> String {
     let mut parts = vec![alert.attributes.header.trim().to_owned()];
     if let Some(desc) = &alert.attributes.description {
         parts.push(desc.trim().to_owned());
     }
+    let details = affected_details(alert, stops);
+    if !details.is_empty() {
+        parts.push(details.join("\n"));
+    }
+    if alert.attributes.cause != Cause::UnknownCause {
+        parts.push(format!("Cause: {}", alert.attributes.cause.label()));
+    }
     if let Some(url) = &alert.attributes.url {
         parts.push(url.clone());
     }
@@ -515,8 +1684,9 @@ fn event_description(alert: &Alert) -> String {
 }
 
 /// FNV-1a 64-bit hash over everything the rendered event depends on: header,
-/// description, url, active period bounds, effect, and routes. Deterministic
-/// across platforms and Rust versions.
+/// description, url, the bounds of the active period it covers, effect, cause,
+/// routes, stops and their names, direction, and an outage's facility.
+/// Deterministic across platforms and Rust versions.
 ///
 /// Effect and routes are in here because the event *title* renders both (via
 /// `effect_label` and `line_name`) and the routes also decide which calendar
@@ -526,26 +1696,17 @@ fn event_description(alert: &Alert) -> String {
 /// `line_name` renders the *first* entity's route: a reorder changes the title,
 /// so the hash has to see it. Normalizing here would trade a rare, harmless
 /// rewrite for a permanently stale line name.
-fn event_state_hash(alert: &Alert) -> String {
-    let mut hash: u64 = 0xcbf29ce484222325;
-    let feed = |hash: &mut u64, s: &str| {
-        for byte in s.bytes() {
-            *hash ^= byte as u64;
-            *hash = hash.wrapping_mul(0x100000001b3);
-        }
-        // separator to prevent "ab"+"c" == "a"+"bc"
-        *hash ^= 0xff;
-        *hash = hash.wrapping_mul(0x100000001b3);
-    };
-    feed(&mut hash, &alert.attributes.header);
-    feed(
-        &mut hash,
-        alert.attributes.description.as_deref().unwrap_or(""),
-    );
-    feed(&mut hash, alert.attributes.url.as_deref().unwrap_or(""));
-    feed(&mut hash, alert.period_start().unwrap_or(""));
-    feed(&mut hash, alert.period_end().unwrap_or(""));
-    feed(&mut hash, &alert.attributes.effect);
+pub fn event_state_hash(alert: &Alert, period: usize, stops: &StopDirectory) -> String {
+    let mut hash = Fnv1a::new();
+    hash.feed(&alert.attributes.header);
+    hash.feed(alert.attributes.description.as_deref().unwrap_or(""));
+    hash.feed(alert.attributes.url.as_deref().unwrap_or(""));
+    let period = alert.period(period);
+    let bound = |t: Option<DateTime<FixedOffset>>| t.map(|t| t.to_rfc3339()).unwrap_or_default();
+    hash.feed(&bound(period.and_then(|p| p.start)));
+    hash.feed(&bound(period.and_then(|p| p.end)));
+    hash.feed(alert.attributes.effect.as_str());
+    hash.feed(alert.attributes.cause.as_str());
 
     for route in alert
         .attributes
@@ -553,23 +1714,59 @@ fn event_state_hash(alert: &Alert) -> String {
         .iter()
         .filter_map(|entity| entity.route.as_deref())
     {
-        feed(&mut hash, route);
+        hash.feed(route);
+    }
+
+    // The description lists the stations and direction. The stations are
+    // named by the stop lookup, which falls back to bare IDs while it fails,
+    // so the lines as rendered are hashed too.
+    for stop in alert.stop_ids() {
+        hash.feed(stop);
+    }
+    hash.feed(
+        &alert
+            .direction_id()
+            .map(|d| d.to_string())
+            .unwrap_or_default(),
+    );
+    for detail in affected_details(alert, stops) {
+        hash.feed(&detail);
+    }
+
+    // An outage's title names the facility and its station from the lookup,
+    // which may have failed for the last sync and come back for this one.
+    if let Some(title) = accessibility_title(alert, stops) {
+        hash.feed(&title);
     }
 
-    hash.to_string()
+    hash.finish().to_string()
 }
 
-fn event_body(alert: &Alert, summary: &str, ai_summary_raw: Option<&str>) -> Result<Value> {
-    let (start, end) = event_times(alert.period_start(), alert.period_end())?;
+fn event_body(
+    alert: &Alert,
+    period: usize,
+    summary: &str,
+    ai_summary_raw: Option<&str>,
+    stops: &StopDirectory,
+) -> Result<Value> {
+    let active_period = alert.period(period);
+    let (start, end) = event_times(
+        active_period.and_then(|p| p.start),
+        active_period.and_then(|p| p.end),
+    )?;
 
     let mut private = serde_json::Map::new();
     private.insert("mbta_alert_source".to_owned(), json!("true"));
     private.insert("mbta_alert_id".to_owned(), json!(alert.id));
+    private.insert(
+        "mbta_alert_period".to_owned(),
+        json!(PeriodId::of(alert, period).to_string()),
+    );
     // Always recorded: without it the next sync has nothing to compare against
     // and rewrites the event unconditionally.
     private.insert(
         "mbta_alert_state_hash".to_owned(),
-        json!(event_state_hash(alert)),
+        json!(event_state_hash(alert, period, stops)),
     );
     if let Some(raw) = ai_summary_raw {
         private.insert("mbta_ai_summary".to_owned(), json!(raw));
@@ -577,7 +1774,7 @@ fn event_body(alert: &Alert, summary: &str, ai_summary_raw: Option<&str>) -> Res
 
     Ok(json!({
         "summary": summary,
-        "description": event_description(alert),
+        "description": event_description(alert, stops),
         "start": start,
         "end": end,
         "transparency": "transparent",
@@ -591,6 +1788,7 @@ fn event_body(alert: &Alert, summary: &str, ai_summary_raw: Option<&str>) -> Res
 mod test {
     use super::*;
     use crate::summary::event_summary;
+    use crate::types::{Effect, InformedEntity, RouteType};
 
     fn make_alert(route: &str, effect: &str, start: Option<&str>, end: Option<&str>) -> Alert {
         Alert::builder()
@@ -639,11 +1837,15 @@ mod test {
 
     // --- event_times ---
 
+    fn dt(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
     #[test]
     fn test_event_times_both_present() {
         let (start, end) = event_times(
-            Some("2024-01-15T10:00:00-05:00"),
-            Some("2024-01-15T22:00:00-05:00"),
+            Some(dt("2024-01-15T10:00:00-05:00")),
+            Some(dt("2024-01-15T22:00:00-05:00")),
         )
         .unwrap();
         assert_eq!(
@@ -658,14 +1860,14 @@ mod test {
 
     #[test]
     fn test_event_times_start_only_uses_date_format() {
-        let (start, end) = event_times(Some("2024-01-15T10:00:00-05:00"), None).unwrap();
+        let (start, end) = event_times(Some(dt("2024-01-15T10:00:00-05:00")), None).unwrap();
         assert_eq!(start, json!({ "date": "2024-01-15" }));
         assert_eq!(end, json!({ "date": "2024-01-16" }));
     }
 
     #[test]
     fn test_event_times_start_only_month_boundary() {
-        let (start, end) = event_times(Some("2024-03-31T08:00:00-04:00"), None).unwrap();
+        let (start, end) = event_times(Some(dt("2024-03-31T08:00:00-04:00")), None).unwrap();
         assert_eq!(start, json!({ "date": "2024-03-31" }));
         assert_eq!(end, json!({ "date": "2024-04-01" }));
     }
@@ -692,7 +1894,7 @@ mod test {
             Some("2024-06-01T23:00:00-04:00"),
         );
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(body["summary"], "[Red Line] Test header");
     }
 
@@ -706,8 +1908,11 @@ mod test {
         );
         alert.attributes.header = "Red Line Braintree Branch: Delays of about 20 minutes due to a signal problem at Braintree.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
-        assert_eq!(body["summary"], "[Red Line] Delay ~20 minutes");
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
+        assert_eq!(
+            body["summary"],
+            "[Red Line Braintree Branch] Delay ~20 minutes"
+        );
     }
 
     #[test]
@@ -720,7 +1925,7 @@ mod test {
         );
         alert.attributes.header = "Blue Line: Delays of up to 20 minutes due to signal problem near Wonderland. Trains may stand by at stations.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(body["summary"], "[Blue Line] Delay ~20 minutes");
     }
 
@@ -733,8 +1938,8 @@ mod test {
             None,
         );
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
-        assert_eq!(body["summary"], "[Green Line] Test header");
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
+        assert_eq!(body["summary"], "[Green Line B Branch] Test header");
     }
 
     #[test]
@@ -747,7 +1952,7 @@ mod test {
         );
         alert.attributes.header = "Due to severe weather, Subway, Bus, and Commuter Rail are operating on a reduced schedule. Ferry service is canceled.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(
             body["summary"],
             "[MBTA] Due to severe weather, Subway, Bus, and Commuter Rail are operating on a reduced schedule"
@@ -764,7 +1969,7 @@ mod test {
         );
         alert.attributes.header = "Red Line: Shuttle buses will replace service between Broadway and Ashmont this weekend.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(
             body["summary"],
             "[Red Line] Shuttle between Broadway and Ashmont"
@@ -781,7 +1986,7 @@ mod test {
         );
         alert.attributes.header = "Jackson Square: The stairway connecting the Jackson Sq lobby and the south end of the platform is closed until winter 2026. Use the stairway at the north end of the platform.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(
             body["summary"],
             "[Orange Line] Jackson Square: The stairway connecting the Jackson Sq lobby and the south end of the platform is closed until winter 2026"
@@ -798,7 +2003,7 @@ mod test {
         );
         alert.attributes.header = "Blue Line: Shuttle buses replacing service between Suffolk Downs and Maverick due to a power problem at Airport.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(
             body["summary"],
             "[Blue Line] Shuttle between Suffolk Downs and Maverick"
@@ -815,10 +2020,10 @@ mod test {
         );
         alert.attributes.header = "Red Line Ashmont Branch: Service between JFK/UMass and Ashmont will operate with two shuttle trains from April 10 - 30 to allow for critical track work.".to_owned();
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(
             body["summary"],
-            "[Red Line] Service change between JFK/UMass and Ashmont"
+            "[Red Line Ashmont Branch] Service change between JFK/UMass and Ashmont"
         );
     }
 
@@ -858,7 +2063,10 @@ mod test {
         let alert = make_alert("Red", "DELAY", None, None);
         let config = CalendarConfig::PerLine {
             map: [(Line::Orange, "cal-orange".to_owned())].into(),
+            branches: HashMap::new(),
+            accessibility: None,
             default: "cal-default".to_owned(),
+            filters: HashMap::new(),
         };
         assert!(matches!(
             line_prefix_for_alert(&alert, "cal-default", &config),
@@ -866,6 +2074,35 @@ mod test {
         ));
     }
 
+    #[test]
+    fn test_line_prefix_branch_alert_on_branch_calendar_omits() {
+        let alert = make_alert("Green-E", "SHUTTLE", None, None);
+        assert!(matches!(
+            line_prefix_for_alert(&alert, "cal-green-e", &green_e_config()),
+            LinePrefixMode::Omit
+        ));
+    }
+
+    #[test]
+    fn test_line_prefix_branch_alert_on_line_calendar_includes() {
+        // The Green calendar shows "[Green Line B Branch]" so riders can tell
+        // branch-only alerts apart from trunk-wide ones.
+        let alert = make_alert("Green-B", "SHUTTLE", None, None);
+        assert!(matches!(
+            line_prefix_for_alert(&alert, "cal-green", &green_e_config()),
+            LinePrefixMode::Include
+        ));
+    }
+
+    #[test]
+    fn test_line_prefix_trunk_alert_on_line_calendar_omits() {
+        let alert = make_alert_multi_route(&["Green-B", "Green-C"], "SUSPENSION");
+        assert!(matches!(
+            line_prefix_for_alert(&alert, "cal-green", &green_e_config()),
+            LinePrefixMode::Omit
+        ));
+    }
+
     #[test]
     fn test_line_prefix_red_alert_on_default_calendar_includes() {
         // Red is mapped to cal-red; a Red alert on the default calendar → Include
@@ -882,14 +2119,20 @@ mod test {
     fn test_event_description_header_only() {
         let mut alert = make_alert("Red", "DELAY", None, None);
         alert.attributes.description = None;
-        assert_eq!(event_description(&alert), "Test header");
+        assert_eq!(
+            event_description(&alert, &StopDirectory::default()),
+            "Test header"
+        );
     }
 
     #[test]
     fn test_event_description_header_and_description() {
         let alert = make_alert("Red", "DELAY", None, None);
         // make_alert sets description = Some("Test description")
-        assert_eq!(event_description(&alert), "Test header\n\nTest description");
+        assert_eq!(
+            event_description(&alert, &StopDirectory::default()),
+            "Test header\n\nTest description"
+        );
     }
 
     #[test]
@@ -898,7 +2141,7 @@ mod test {
         alert.attributes.description = None;
         alert.attributes.url = Some("https://mbta.com/RedLine".to_owned());
         assert_eq!(
-            event_description(&alert),
+            event_description(&alert, &StopDirectory::default()),
             "Test header\n\nhttps://mbta.com/RedLine"
         );
     }
@@ -908,18 +2151,98 @@ mod test {
         let mut alert = make_alert("Red", "SHUTTLE", None, None);
         alert.attributes.url = Some("https://mbta.com/RedLine".to_owned());
         assert_eq!(
-            event_description(&alert),
+            event_description(&alert, &StopDirectory::default()),
             "Test header\n\nTest description\n\nhttps://mbta.com/RedLine"
         );
     }
 
+    #[test]
+    fn test_event_description_includes_known_cause() {
+        let alert = Alert::builder()
+            .description("Test description")
+            .cause("TRACK_WORK")
+            .build();
+        assert_eq!(
+            event_description(&alert, &StopDirectory::default()),
+            "Test header\n\nTest description\n\nCause: Track work"
+        );
+    }
+
+    #[test]
+    fn test_event_state_hash_changes_with_cause() {
+        let unknown = Alert::builder().route("Red").build();
+        let track_work = Alert::builder().route("Red").cause("TRACK_WORK").build();
+        assert_ne!(
+            event_state_hash(&unknown, 0, &StopDirectory::default()),
+            event_state_hash(&track_work, 0, &StopDirectory::default())
+        );
+    }
+
+    #[test]
+    fn test_event_description_includes_affected_stations() {
+        let alert = Alert::builder()
+            .stop("Red", "place-pktrm")
+            .stop("Red", "place-dwnxg")
+            .build();
+        assert_eq!(
+            event_description(&alert, &StopDirectory::default()),
+            "Test header\n\nStations: place-pktrm, place-dwnxg"
+        );
+    }
+
+    #[test]
+    fn test_event_state_hash_changes_with_stops() {
+        let park = Alert::builder().stop("Red", "place-pktrm").build();
+        let dtx = Alert::builder().stop("Red", "place-dwnxg").build();
+        assert_ne!(
+            event_state_hash(&park, 0, &StopDirectory::default()),
+            event_state_hash(&dtx, 0, &StopDirectory::default())
+        );
+    }
+
+    #[test]
+    fn test_event_state_hash_changes_with_stop_names() -> Result<()> {
+        let alert = Alert::builder().stop("Red", "place-pktrm").build();
+        let mut stops = StopDirectory::default();
+        let unnamed = event_state_hash(&alert, 0, &stops);
+        stops.extend(serde_json::from_str(
+            r#"{"data": [{"id": "place-pktrm", "attributes": {"name": "Park Street"},
+                 "relationships": {"parent_station": {"data": null}}}]}"#,
+        )?);
+        assert_ne!(event_state_hash(&alert, 0, &stops), unnamed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_state_hash_changes_with_facility() -> Result<()> {
+        // Titled from the header while the facility lookup is down, then from
+        // the facility once it's back.
+        let alert = Alert::builder()
+            .effect("ELEVATOR_CLOSURE")
+            .entity(InformedEntity {
+                stop: Some("place-pktrm".to_owned()),
+                facility: Some("804".to_owned()),
+                ..Default::default()
+            })
+            .build();
+        let mut stops = StopDirectory::default();
+        let without = event_state_hash(&alert, 0, &stops);
+        stops.extend_facilities(serde_json::from_str(
+            r#"{"data": [{"id": "804", "attributes": {"type": "ELEVATOR",
+                 "short_name": "Red Line platform to Green Line platform"},
+                 "relationships": {"stop": {"data": {"id": "place-pktrm"}}}}]}"#,
+        )?);
+        assert_ne!(event_state_hash(&alert, 0, &stops), without);
+        Ok(())
+    }
+
     #[test]
     fn test_event_description_trims_whitespace() {
         let mut alert = make_alert("Red", "DELAY", None, None);
         alert.attributes.header = "  Header with spaces  ".to_owned();
         alert.attributes.description = Some("  Description with spaces  ".to_owned());
         assert_eq!(
-            event_description(&alert),
+            event_description(&alert, &StopDirectory::default()),
             "Header with spaces\n\nDescription with spaces"
         );
     }
@@ -933,7 +2256,7 @@ mod test {
             Some("2024-06-01T23:00:00-04:00"),
         );
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(body["description"], "Test header\n\nTest description");
     }
 
@@ -946,7 +2269,7 @@ mod test {
             Some("2024-06-01T23:00:00-04:00"),
         );
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(
             body["start"],
             json!({ "dateTime": "2024-06-01T09:00:00-04:00", "timeZone": "America/New_York" })
@@ -961,7 +2284,7 @@ mod test {
     fn test_event_body_dates_when_no_end() {
         let alert = make_alert("Red", "DELAY", Some("2024-06-01T09:00:00-04:00"), None);
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert_eq!(body["start"], json!({ "date": "2024-06-01" }));
         assert_eq!(body["end"], json!({ "date": "2024-06-02" }));
     }
@@ -975,7 +2298,7 @@ mod test {
             Some("2024-06-01T23:00:00-04:00"),
         );
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         let private = &body["extendedProperties"]["private"];
         assert_eq!(private["mbta_alert_source"], "true");
         assert_eq!(private["mbta_alert_id"], "alert-42");
@@ -990,7 +2313,14 @@ mod test {
             Some("2024-06-01T09:00:00-04:00"),
             Some("2024-06-01T23:00:00-04:00"),
         );
-        let body = event_body(&alert, "AI-generated title", Some("AI-generated title")).unwrap();
+        let body = event_body(
+            &alert,
+            0,
+            "AI-generated title",
+            Some("AI-generated title"),
+            &StopDirectory::default(),
+        )
+        .unwrap();
         let private = &body["extendedProperties"]["private"];
         assert_eq!(private["mbta_alert_source"], "true");
         assert_eq!(private["mbta_alert_id"], "alert-42");
@@ -1001,7 +2331,7 @@ mod test {
     fn test_event_body_no_period_falls_back_to_today() {
         let alert = make_alert_no_period("Orange", "SUSPENSION");
         let summary = event_summary(&alert, LinePrefixMode::Include);
-        let body = event_body(&alert, &summary, None).unwrap();
+        let body = event_body(&alert, 0, &summary, None, &StopDirectory::default()).unwrap();
         assert!(body["start"].get("date").is_some());
         assert!(body["end"].get("date").is_some());
     }
@@ -1011,23 +2341,28 @@ mod test {
     #[test]
     fn test_normalize_calendar_id_already_suffixed() {
         let id = "abc123@group.calendar.google.com".to_owned();
-        assert_eq!(normalize_calendar_id(id.clone()), id);
+        assert_eq!(normalize_calendar_id(id.clone(), BackendKind::Google), id);
     }
 
     #[test]
     fn test_normalize_calendar_id_bare_adds_suffix() {
         assert_eq!(
-            normalize_calendar_id("abc123".to_owned()),
+            normalize_calendar_id("abc123".to_owned(), BackendKind::Google),
             "abc123@group.calendar.google.com"
         );
     }
 
     // --- parse_calendar_ids ---
 
+    /// The GOOGLE_CALENDAR_IDS path: JSON into [`CalendarConfig::per_line`].
+    fn parse_calendar_ids(json: &str) -> Result<CalendarConfig> {
+        CalendarConfig::per_line(serde_json::from_str(json)?)
+    }
+
     #[test]
     fn test_parse_calendar_ids_per_line() -> Result<()> {
         let config = parse_calendar_ids(r#"{"default": "cal-default", "Red": "cal-red"}"#)?;
-        let CalendarConfig::PerLine { map, default } = config else {
+        let CalendarConfig::PerLine { map, default, .. } = config else {
             panic!("expected PerLine config");
         };
         assert_eq!(default, "cal-default@group.calendar.google.com");
@@ -1038,6 +2373,144 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_calendar_ids_branch_keys() -> Result<()> {
+        let config = parse_calendar_ids(r#"{"default": "d", "Green": "g", "Green-E": "e"}"#)?;
+        let CalendarConfig::PerLine { map, branches, .. } = config else {
+            panic!("expected PerLine config");
+        };
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            branches.get(&Branch::GreenE).map(String::as_str),
+            Some("e@group.calendar.google.com")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_with_filter() -> Result<()> {
+        let config = parse_calendar_ids(
+            r#"{"default": "d", "Red": {"id": "r", "filter": {"include_effects": ["ELEVATOR_CLOSURE"]}}}"#,
+        )?;
+        let filter = config
+            .filter("r@group.calendar.google.com")
+            .expect("Red calendar should have a filter");
+        assert_eq!(filter.include_effects, vec![Effect::ElevatorClosure]);
+        assert!(config.filter("d@group.calendar.google.com").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_invalid_filter_errors() {
+        assert!(
+            parse_calendar_ids(
+                r#"{"default": "d", "Red": {"id": "r", "filter": {"include_effects": ["NOPE"]}}}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_conflicting_filters_error() {
+        assert!(
+            parse_calendar_ids(
+                r#"{"default": "d",
+                    "Red": {"id": "x", "filter": {"min_severity": 3}},
+                    "Blue": {"id": "x", "filter": {"min_severity": 7}}}"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_accessibility_defaults_to_outages() -> Result<()> {
+        let config = parse_calendar_ids(r#"{"default": "d", "Accessibility": "a"}"#)?;
+        let CalendarConfig::PerLine { accessibility, .. } = &config else {
+            panic!("expected PerLine config");
+        };
+        assert_eq!(
+            accessibility.as_deref(),
+            Some("a@group.calendar.google.com")
+        );
+        assert_eq!(
+            config.filter("a@group.calendar.google.com"),
+            Some(&accessibility_filter())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar_ids_accessibility_alert_goes_only_to_accessibility() -> Result<()> {
+        let config = parse_calendar_ids(r#"{"default": "d", "Red": "r", "Accessibility": "a"}"#)?;
+        let alert = Alert::builder()
+            .effect("ELEVATOR_CLOSURE")
+            .stop("Red", "place-pktrm")
+            .build();
+        assert_eq!(
+            calendar_ids_for_alert(&alert, &config),
+            vec!["a@group.calendar.google.com"]
+        );
+        let delay = make_alert("Red", "DELAY", None, None);
+        assert_eq!(
+            calendar_ids_for_alert(&delay, &config),
+            vec!["r@group.calendar.google.com"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_new_line_keys() -> Result<()> {
+        let config = parse_calendar_ids(
+            r#"{"default": "d", "Mattapan": "m", "Silver": "s", "CommuterRail": "cr", "Ferry": "f", "Bus": "b"}"#,
+        )?;
+        let CalendarConfig::PerLine { map, .. } = config else {
+            panic!("expected PerLine config");
+        };
+        assert_eq!(map.len(), 5);
+        assert!(map.contains_key(&Line::CommuterRail));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_backends() -> Result<()> {
+        let config = parse_calendar_ids(
+            r#"{"default": "d", "Red": {"id": "AAMkAGI2", "backend": "outlook"},
+                "Orange": "https://dav.example.com/cal/orange"}"#,
+        )?;
+        let CalendarConfig::PerLine { map, .. } = &config else {
+            panic!("expected PerLine config");
+        };
+        assert_eq!(map[&Line::Red], "outlook:AAMkAGI2");
+        assert_eq!(map[&Line::Orange], "https://dav.example.com/cal/orange/");
+        assert_eq!(BackendKind::of(&map[&Line::Red]), BackendKind::Outlook);
+        assert_eq!(BackendKind::of(&map[&Line::Orange]), BackendKind::CalDav);
+        assert_eq!(
+            BackendKind::of("d@group.calendar.google.com"),
+            BackendKind::Google
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_calendar_ids_backend_must_match_id() {
+        for target in [
+            r#"{"id": "abc123", "backend": "caldav"}"#,
+            r#"{"id": "https://dav.example.com/cal/red", "backend": "google"}"#,
+            r#"{"id": "outlook:AAMkAGI2", "backend": "google"}"#,
+        ] {
+            let json = format!(r#"{{"default": "d", "Red": {target}}}"#);
+            assert!(parse_calendar_ids(&json).is_err(), "{target}");
+        }
+    }
+
+    #[test]
+    fn test_normalize_calendar_id_outlook_keeps_prefix() {
+        assert_eq!(
+            normalize_calendar_id("outlook:AAMkAGI2".to_owned(), BackendKind::Outlook),
+            "outlook:AAMkAGI2"
+        );
+    }
+
     #[test]
     fn test_parse_calendar_ids_missing_default_errors() {
         assert!(parse_calendar_ids(r#"{"Red": "cal-red"}"#).is_err());
@@ -1064,7 +2537,10 @@ mod test {
                 (Line::Green, "cal-green".to_owned()),
             ]
             .into(),
+            branches: HashMap::new(),
+            accessibility: None,
             default: "cal-default".to_owned(),
+            filters: HashMap::new(),
         }
     }
 
@@ -1076,56 +2552,183 @@ mod test {
         builder.build()
     }
 
-    fn make_alert_no_route(effect: &str) -> Alert {
-        Alert::builder()
-            .id("alert-no-route")
-            .effect(effect)
-            .null_route()
-            .build()
+    fn make_alert_no_route(effect: &str) -> Alert {
+        Alert::builder()
+            .id("alert-no-route")
+            .effect(effect)
+            .null_route()
+            .build()
+    }
+
+    #[test]
+    fn test_calendar_ids_single_config_always_returns_single() {
+        let config = CalendarConfig::Single("cal-all".to_owned());
+        let alert = make_alert("Red", "DELAY", None, None);
+        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-all"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_single_route() {
+        let config = per_line_config();
+        let alert = make_alert("Blue", "DELAY", None, None);
+        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-blue"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_green_branch_maps_to_green() {
+        let config = per_line_config();
+        let alert = make_alert("Green-D", "SHUTTLE", None, None);
+        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-green"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_multi_route_returns_both() {
+        let config = per_line_config();
+        let alert = make_alert_multi_route(&["Red", "Orange"], "DELAY");
+        let mut ids = calendar_ids_for_alert(&alert, &config);
+        ids.sort();
+        assert_eq!(ids, vec!["cal-orange", "cal-red"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_no_route_returns_default() {
+        let config = per_line_config();
+        let alert = make_alert_no_route("DELAY");
+        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-default"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_route_type_only_covers_every_line_of_mode() {
+        let config = per_line_config();
+        let alert = Alert::builder()
+            .entity(InformedEntity {
+                route_type: Some(RouteType::Subway),
+                ..Default::default()
+            })
+            .build();
+        let mut ids = calendar_ids_for_alert(&alert, &config);
+        ids.sort();
+        assert_eq!(ids, vec!["cal-blue", "cal-orange", "cal-red"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_unknown_route_returns_default() {
+        let config = per_line_config();
+        let alert = make_alert("99", "DELAY", None, None);
+        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-default"]);
+    }
+
+    #[test]
+    fn test_calendar_ids_per_line_commuter_rail() {
+        let config = CalendarConfig::PerLine {
+            map: [(Line::CommuterRail, "cal-cr".to_owned())].into(),
+            branches: HashMap::new(),
+            accessibility: None,
+            default: "cal-default".to_owned(),
+            filters: HashMap::new(),
+        };
+        let alert = make_alert("CR-Fitchburg", "DELAY", None, None);
+        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-cr"]);
+    }
+
+    fn red_branch_config() -> CalendarConfig {
+        CalendarConfig::PerLine {
+            map: [(Line::Red, "cal-red".to_owned())].into(),
+            branches: [
+                (Branch::Ashmont, "cal-ashmont".to_owned()),
+                (Branch::Braintree, "cal-braintree".to_owned()),
+            ]
+            .into(),
+            accessibility: None,
+            default: "cal-default".to_owned(),
+            filters: HashMap::new(),
+        }
+    }
+
+    fn sorted_calendar_ids<'a>(alert: &Alert, config: &'a CalendarConfig) -> Vec<&'a str> {
+        let mut ids = calendar_ids_for_alert(alert, config);
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_calendar_ids_red_branch_stop() {
+        let alert = Alert::builder().stop("Red", "place-brntn").build();
+        assert_eq!(
+            sorted_calendar_ids(&alert, &red_branch_config()),
+            vec!["cal-braintree"]
+        );
     }
 
     #[test]
-    fn test_calendar_ids_single_config_always_returns_single() {
-        let config = CalendarConfig::Single("cal-all".to_owned());
-        let alert = make_alert("Red", "DELAY", None, None);
-        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-all"]);
+    fn test_calendar_ids_red_trunk_reaches_both_branches() {
+        let alert = Alert::builder().stop("Red", "place-dwnxg").build();
+        assert_eq!(
+            sorted_calendar_ids(&alert, &red_branch_config()),
+            vec!["cal-ashmont", "cal-braintree"]
+        );
     }
 
     #[test]
-    fn test_calendar_ids_per_line_single_route() {
-        let config = per_line_config();
-        let alert = make_alert("Blue", "DELAY", None, None);
-        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-blue"]);
+    fn test_calendar_ids_red_branch_header() {
+        let alert = Alert::builder()
+            .header("Red Line Ashmont Branch: Delays of about 15 minutes.")
+            .route("Red")
+            .build();
+        assert_eq!(
+            sorted_calendar_ids(&alert, &red_branch_config()),
+            vec!["cal-ashmont"]
+        );
     }
 
     #[test]
-    fn test_calendar_ids_per_line_green_branch_maps_to_green() {
-        let config = per_line_config();
-        let alert = make_alert("Green-D", "SHUTTLE", None, None);
-        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-green"]);
+    fn test_event_summary_red_branch_title() {
+        let alert = Alert::builder()
+            .header(
+                "Red Line Braintree Branch: Delays of about 10 minutes due to a disabled train.",
+            )
+            .effect("DELAY")
+            .stop("Red", "place-qnctr")
+            .build();
+        let summary = event_summary(&alert, LinePrefixMode::Include);
+        assert_eq!(summary, "[Red Line Braintree Branch] Delay ~10 minutes");
+    }
+
+    fn green_e_config() -> CalendarConfig {
+        CalendarConfig::PerLine {
+            map: [(Line::Green, "cal-green".to_owned())].into(),
+            branches: [(Branch::GreenE, "cal-green-e".to_owned())].into(),
+            accessibility: None,
+            default: "cal-default".to_owned(),
+            filters: HashMap::new(),
+        }
     }
 
     #[test]
-    fn test_calendar_ids_per_line_multi_route_returns_both() {
-        let config = per_line_config();
-        let alert = make_alert_multi_route(&["Red", "Orange"], "DELAY");
-        let mut ids = calendar_ids_for_alert(&alert, &config);
-        ids.sort();
-        assert_eq!(ids, vec!["cal-orange", "cal-red"]);
+    fn test_calendar_ids_per_line_branch_calendar() {
+        let alert = make_alert("Green-E", "SHUTTLE", None, None);
+        assert_eq!(
+            calendar_ids_for_alert(&alert, &green_e_config()),
+            vec!["cal-green-e"]
+        );
     }
 
     #[test]
-    fn test_calendar_ids_per_line_no_route_returns_default() {
-        let config = per_line_config();
-        let alert = make_alert_no_route("DELAY");
-        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-default"]);
+    fn test_calendar_ids_per_line_branch_falls_back_to_line() {
+        let alert = make_alert("Green-B", "SHUTTLE", None, None);
+        assert_eq!(
+            calendar_ids_for_alert(&alert, &green_e_config()),
+            vec!["cal-green"]
+        );
     }
 
     #[test]
-    fn test_calendar_ids_per_line_unknown_route_returns_default() {
-        let config = per_line_config();
-        let alert = make_alert("CR-Fitchburg", "DELAY", None, None);
-        assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-default"]);
+    fn test_calendar_ids_per_line_trunk_alert_reaches_branch_and_line() {
+        let alert = make_alert_multi_route(&["Green-D", "Green-E"], "SUSPENSION");
+        let config = green_e_config();
+        let mut ids = calendar_ids_for_alert(&alert, &config);
+        ids.sort();
+        assert_eq!(ids, vec!["cal-green", "cal-green-e"]);
     }
 
     #[test]
@@ -1155,7 +2758,10 @@ mod test {
     fn test_calendar_ids_per_line_unmapped_line_returns_default() {
         let config = CalendarConfig::PerLine {
             map: [(Line::Red, "cal-red".to_owned())].into(),
+            branches: HashMap::new(),
+            accessibility: None,
             default: "cal-default".to_owned(),
+            filters: HashMap::new(),
         };
         let alert = make_alert("Blue", "DELAY", None, None);
         assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-default"]);
@@ -1169,7 +2775,10 @@ mod test {
                 (Line::Orange, "cal-shared".to_owned()),
             ]
             .into(),
+            branches: HashMap::new(),
+            accessibility: None,
             default: "cal-default".to_owned(),
+            filters: HashMap::new(),
         };
         let alert = make_alert_multi_route(&["Red", "Orange"], "DELAY");
         assert_eq!(calendar_ids_for_alert(&alert, &config), vec!["cal-shared"]);
@@ -1186,6 +2795,7 @@ mod test {
             extended_properties: Some(ExtendedProperties {
                 private: Some(private),
             }),
+            ..Default::default()
         };
         assert_eq!(event.alert_id(), Some("alert-123"));
     }
@@ -1198,6 +2808,7 @@ mod test {
             extended_properties: Some(ExtendedProperties {
                 private: Some(private),
             }),
+            ..Default::default()
         };
         assert_eq!(event.alert_id(), None);
     }
@@ -1207,6 +2818,7 @@ mod test {
         let event = CalendarEvent {
             id: "event-1".to_owned(),
             extended_properties: None,
+            ..Default::default()
         };
         assert_eq!(event.alert_id(), None);
     }
@@ -1216,24 +2828,129 @@ mod test {
         let event = CalendarEvent {
             id: "event-1".to_owned(),
             extended_properties: Some(ExtendedProperties { private: None }),
+            ..Default::default()
         };
         assert_eq!(event.alert_id(), None);
     }
 
+    #[test]
+    fn test_calendar_event_times() -> Result<()> {
+        let timed: CalendarEvent = serde_json::from_value(json!({
+            "id": "event-1",
+            "start": { "dateTime": "2025-10-24T20:45:00-04:00" },
+            "end": { "dateTime": "2025-10-27T02:30:00-04:00" },
+        }))?;
+        assert_eq!(
+            timed.times(),
+            Some(EventTimes::new(
+                Some(DateTime::parse_from_rfc3339("2025-10-24T20:45:00-04:00")?),
+                Some(DateTime::parse_from_rfc3339("2025-10-27T02:30:00-04:00")?),
+            ))
+        );
+
+        let all_day: CalendarEvent = serde_json::from_value(json!({
+            "id": "event-2",
+            "start": { "date": "2025-10-24" },
+            "end": { "date": "2025-10-25" },
+        }))?;
+        assert_eq!(
+            all_day.times(),
+            Some(EventTimes::AllDay("2025-10-24".parse()?))
+        );
+        Ok(())
+    }
+
+    // --- dry run ---
+
+    #[test]
+    fn test_field_changes_lists_only_differences() -> Result<()> {
+        let start = DateTime::parse_from_rfc3339("2025-10-24T20:45:00-04:00")?;
+        let end = DateTime::parse_from_rfc3339("2025-10-27T02:30:00-04:00")?;
+        let before = EventFields {
+            summary: Some("Red Line: Shuttles".to_owned()),
+            description: Some("Shuttle buses".to_owned()),
+            // The same instants, as a backend that lists in UTC gives them.
+            times: Some(EventTimes::Timed(
+                start.to_utc().into(),
+                end.to_utc().into(),
+            )),
+        };
+        let after = EventFields {
+            summary: Some("Red Line: Shuttles, Alewife to Harvard".to_owned()),
+            ..before.clone()
+        };
+        assert_eq!(field_changes(&before, &before), vec![]);
+        assert_eq!(
+            field_changes(&before, &after),
+            vec![FieldChange {
+                field: "summary",
+                before: Some("Red Line: Shuttles".to_owned()),
+                after: Some("Red Line: Shuttles, Alewife to Harvard".to_owned()),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar_plan_display() -> Result<()> {
+        let start = DateTime::parse_from_rfc3339("2025-10-24T20:45:00-04:00")?;
+        let end = DateTime::parse_from_rfc3339("2025-10-27T02:30:00-04:00")?;
+        let plan = CalendarPlan {
+            calendar_id: "red".to_owned(),
+            create: vec![PlannedCreate {
+                key: event_key("1", 0),
+                summary: "Red Line: Shuttles".to_owned(),
+                times: EventTimes::Timed(start, end).to_string(),
+            }],
+            update: vec![PlannedUpdate {
+                key: event_key("2", 1),
+                event_id: "event-2".to_owned(),
+                changes: vec![FieldChange {
+                    field: "summary",
+                    before: Some("Red Line: Delays".to_owned()),
+                    after: Some("Red Line: Delays of 20 minutes".to_owned()),
+                }],
+            }],
+            delete: vec![event_key("3", 0)],
+        };
+        assert_eq!(
+            plan.to_string(),
+            "red: 1 to create, 1 to update, 1 to delete\n\
+             \x20 + 1 period 0: Red Line: Shuttles (2025-10-24 20:45 to 2025-10-27 02:30)\n\
+             \x20 ~ 2 period 1\n\
+             \x20     summary: \"Red Line: Delays\" -> \"Red Line: Delays of 20 minutes\"\n\
+             \x20 - 3 period 0\n"
+        );
+        Ok(())
+    }
+
     // --- plan_calendar_sync ---
 
+    /// The key of an event for a period without a start.
+    fn event_key(alert_id: &str, period: usize) -> EventKey {
+        EventKey {
+            alert_id: alert_id.to_owned(),
+            period: PeriodId::Index(period),
+        }
+    }
+
+    fn occurrence_key(alert: &Alert, period: usize) -> EventKey {
+        Occurrence { alert, period }.key()
+    }
+
     fn make_existing(
-        alert_id: &str,
+        key: EventKey,
         event_id: &str,
         ai_summary: Option<&str>,
         hash: Option<&str>,
-    ) -> HashMap<String, ExistingEvent> {
+    ) -> HashMap<EventKey, ExistingEvent> {
         [(
-            alert_id.to_owned(),
+            key,
             ExistingEvent {
                 event_id: event_id.to_owned(),
                 ai_summary: ai_summary.map(str::to_owned),
                 state_hash: hash.map(str::to_owned),
+                fields: EventFields::default(),
             },
         )]
         .into()
@@ -1245,7 +2962,10 @@ mod test {
     fn test_event_state_hash_is_stable_for_identical_alerts() {
         let a = make_alert("Red", "DELAY", None, None);
         let b = make_alert("Red", "DELAY", None, None);
-        assert_eq!(event_state_hash(&a), event_state_hash(&b));
+        assert_eq!(
+            event_state_hash(&a, 0, &StopDirectory::default()),
+            event_state_hash(&b, 0, &StopDirectory::default())
+        );
     }
 
     #[test]
@@ -1254,7 +2974,10 @@ mod test {
         // unchanged header must still re-sync the event.
         let delay = make_alert("Red", "DELAY", None, None);
         let suspension = make_alert("Red", "SUSPENSION", None, None);
-        assert_ne!(event_state_hash(&delay), event_state_hash(&suspension));
+        assert_ne!(
+            event_state_hash(&delay, 0, &StopDirectory::default()),
+            event_state_hash(&suspension, 0, &StopDirectory::default())
+        );
     }
 
     #[test]
@@ -1263,7 +2986,10 @@ mod test {
         // the alert belongs to.
         let red = make_alert("Red", "DELAY", None, None);
         let blue = make_alert("Blue", "DELAY", None, None);
-        assert_ne!(event_state_hash(&red), event_state_hash(&blue));
+        assert_ne!(
+            event_state_hash(&red, 0, &StopDirectory::default()),
+            event_state_hash(&blue, 0, &StopDirectory::default())
+        );
     }
 
     #[test]
@@ -1276,24 +3002,54 @@ mod test {
 
         assert_ne!(crate::line_name(&red_first), crate::line_name(&blue_first));
         assert_ne!(
-            event_state_hash(&red_first),
-            event_state_hash(&blue_first),
+            event_state_hash(&red_first, 0, &StopDirectory::default()),
+            event_state_hash(&blue_first, 0, &StopDirectory::default()),
             "a title-changing reorder must not hash the same"
         );
     }
 
+    #[test]
+    fn test_plan_skip_accessibility_alert_without_ai_summary() {
+        // Accessibility titles never come from Bedrock, so a missing AI
+        // summary is no reason to rewrite the event.
+        let alert = make_alert("Red", "ELEVATOR_CLOSURE", None, None);
+        let current_hash = event_state_hash(&alert, 0, &StopDirectory::default());
+        let existing = make_existing(
+            occurrence_key(&alert, 0),
+            "event-1",
+            None,
+            Some(&current_hash),
+        );
+
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Enabled,
+            Utc::now(),
+        );
+
+        assert!(plan.to_update.is_empty(), "no updates expected");
+    }
+
     #[test]
     fn test_plan_skip_when_hash_and_summary_match() {
         let alert = make_alert("Red", "DELAY", None, None);
-        let current_hash = event_state_hash(&alert);
+        let current_hash = event_state_hash(&alert, 0, &StopDirectory::default());
         let existing = make_existing(
-            &alert.id,
+            occurrence_key(&alert, 0),
             "event-1",
             Some("AI summary"),
             Some(&current_hash),
         );
 
-        let plan = plan_calendar_sync(&existing, &[&alert], AiSummaries::Enabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Enabled,
+            Utc::now(),
+        );
 
         assert!(plan.to_create.is_empty(), "no creates expected");
         assert!(plan.to_update.is_empty(), "no updates expected");
@@ -1304,13 +3060,19 @@ mod test {
     fn test_plan_update_when_hash_changed() {
         let alert = make_alert("Red", "DELAY", None, None);
         let existing = make_existing(
-            &alert.id,
+            occurrence_key(&alert, 0),
             "event-1",
             Some("Old summary"),
             Some("stale-hash"),
         );
 
-        let plan = plan_calendar_sync(&existing, &[&alert], AiSummaries::Enabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Enabled,
+            Utc::now(),
+        );
 
         assert!(plan.to_create.is_empty());
         assert_eq!(plan.to_update.len(), 1);
@@ -1323,10 +3085,21 @@ mod test {
         // A summarizer is available but the event has no AI summary yet, so it
         // needs one write to populate it.
         let alert = make_alert("Red", "DELAY", None, None);
-        let current_hash = event_state_hash(&alert);
-        let existing = make_existing(&alert.id, "event-1", None, Some(&current_hash));
+        let current_hash = event_state_hash(&alert, 0, &StopDirectory::default());
+        let existing = make_existing(
+            occurrence_key(&alert, 0),
+            "event-1",
+            None,
+            Some(&current_hash),
+        );
 
-        let plan = plan_calendar_sync(&existing, &[&alert], AiSummaries::Enabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Enabled,
+            Utc::now(),
+        );
 
         assert!(plan.to_create.is_empty());
         assert_eq!(plan.to_update.len(), 1);
@@ -1337,10 +3110,21 @@ mod test {
         // With no summarizer configured, no run will ever produce an AI summary.
         // Demanding one rewrites every event on every sync, forever.
         let alert = make_alert("Red", "DELAY", None, None);
-        let current_hash = event_state_hash(&alert);
-        let existing = make_existing(&alert.id, "event-1", None, Some(&current_hash));
+        let current_hash = event_state_hash(&alert, 0, &StopDirectory::default());
+        let existing = make_existing(
+            occurrence_key(&alert, 0),
+            "event-1",
+            None,
+            Some(&current_hash),
+        );
 
-        let plan = plan_calendar_sync(&existing, &[&alert], AiSummaries::Disabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Disabled,
+            Utc::now(),
+        );
 
         assert!(plan.to_create.is_empty());
         assert!(plan.to_update.is_empty(), "no rewrite expected");
@@ -1350,9 +3134,20 @@ mod test {
     #[test]
     fn test_plan_update_without_ai_when_hash_differs() {
         let alert = make_alert("Red", "DELAY", None, None);
-        let existing = make_existing(&alert.id, "event-1", None, Some("stale-hash"));
+        let existing = make_existing(
+            occurrence_key(&alert, 0),
+            "event-1",
+            None,
+            Some("stale-hash"),
+        );
 
-        let plan = plan_calendar_sync(&existing, &[&alert], AiSummaries::Disabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Disabled,
+            Utc::now(),
+        );
 
         assert_eq!(plan.to_update.len(), 1);
     }
@@ -1363,10 +3158,13 @@ mod test {
         // so every event lands in to_update on every run.
         let alert = make_alert("Red", "DELAY", None, None);
 
-        let body = event_body(&alert, "Some summary", None).unwrap();
+        let body = event_body(&alert, 0, "Some summary", None, &StopDirectory::default()).unwrap();
         let private = &body["extendedProperties"]["private"];
 
-        assert_eq!(private["mbta_alert_state_hash"], event_state_hash(&alert));
+        assert_eq!(
+            private["mbta_alert_state_hash"],
+            event_state_hash(&alert, 0, &StopDirectory::default())
+        );
         assert!(private.get("mbta_ai_summary").is_none());
     }
 
@@ -1375,7 +3173,13 @@ mod test {
         let alert = make_alert("Red", "DELAY", None, None);
         let existing = HashMap::new();
 
-        let plan = plan_calendar_sync(&existing, &[&alert], AiSummaries::Enabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Enabled,
+            Utc::now(),
+        );
 
         assert_eq!(plan.to_create.len(), 1);
         assert!(plan.to_update.is_empty());
@@ -1384,9 +3188,20 @@ mod test {
 
     #[test]
     fn test_plan_delete_stale_event() {
-        let existing = make_existing("stale-alert", "event-99", Some("summary"), Some("hash"));
+        let existing = make_existing(
+            event_key("stale-alert", 0),
+            "event-99",
+            Some("summary"),
+            Some("hash"),
+        );
 
-        let plan = plan_calendar_sync(&existing, &[], AiSummaries::Enabled);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[],
+            &StopDirectory::default(),
+            AiSummaries::Enabled,
+            Utc::now(),
+        );
 
         assert!(plan.to_create.is_empty());
         assert!(plan.to_update.is_empty());
@@ -1402,30 +3217,33 @@ mod test {
         let mut alert_create = make_alert("Orange", "SHUTTLE", None, None);
         alert_create.id = "alert-create".to_owned();
 
-        let skip_hash = event_state_hash(&alert_skip);
-        let existing: HashMap<String, ExistingEvent> = [
+        let skip_hash = event_state_hash(&alert_skip, 0, &StopDirectory::default());
+        let existing: HashMap<EventKey, ExistingEvent> = [
             (
-                alert_skip.id.clone(),
+                event_key(&alert_skip.id, 0),
                 ExistingEvent {
                     event_id: "event-skip".to_owned(),
                     ai_summary: Some("summary".to_owned()),
                     state_hash: Some(skip_hash),
+                    fields: EventFields::default(),
                 },
             ),
             (
-                alert_update.id.clone(),
+                event_key(&alert_update.id, 0),
                 ExistingEvent {
                     event_id: "event-update".to_owned(),
                     ai_summary: Some("old".to_owned()),
                     state_hash: Some("stale".to_owned()),
+                    fields: EventFields::default(),
                 },
             ),
             (
-                "stale-alert".to_owned(),
+                event_key("stale-alert", 0),
                 ExistingEvent {
                     event_id: "event-stale".to_owned(),
                     ai_summary: Some("x".to_owned()),
                     state_hash: Some("h".to_owned()),
+                    fields: EventFields::default(),
                 },
             ),
         ]
@@ -1434,14 +3252,255 @@ mod test {
         let plan = plan_calendar_sync(
             &existing,
             &[&alert_skip, &alert_update, &alert_create],
+            &StopDirectory::default(),
             AiSummaries::Enabled,
+            Utc::now(),
         );
 
         assert_eq!(plan.to_create.len(), 1);
-        assert_eq!(plan.to_create[0].id, "alert-create");
+        assert_eq!(plan.to_create[0].alert.id, "alert-create");
         assert_eq!(plan.to_update.len(), 1);
         assert_eq!(plan.to_update[0].0, "event-update");
         assert_eq!(plan.to_delete.len(), 1);
         assert_eq!(plan.to_delete[0], "event-stale");
     }
+
+    // --- per-period sync ---
+
+    fn make_weekend_alert() -> Alert {
+        Alert::builder()
+            .id("alert-weekends")
+            .route("Red")
+            .effect("SHUTTLE")
+            .period(
+                Some("2024-06-01T03:00:00-04:00"),
+                Some("2024-06-03T02:59:00-04:00"),
+            )
+            .period(
+                Some("2024-06-08T03:00:00-04:00"),
+                Some("2024-06-10T02:59:00-04:00"),
+            )
+            .period(
+                Some("2024-06-15T03:00:00-04:00"),
+                Some("2024-06-17T02:59:00-04:00"),
+            )
+            .build()
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_calendar_event_period_id_defaults_to_first() {
+        // Events written before per-period sync carry no period.
+        let mut private = HashMap::new();
+        private.insert("mbta_alert_id".to_owned(), "alert-123".to_owned());
+        let event = CalendarEvent {
+            id: "event-1".to_owned(),
+            extended_properties: Some(ExtendedProperties {
+                private: Some(private),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(event.period_id(), PeriodId::Index(0));
+    }
+
+    #[test]
+    fn test_calendar_event_period_id_present() {
+        let event = |period: &str| CalendarEvent {
+            id: "event-1".to_owned(),
+            extended_properties: Some(ExtendedProperties {
+                private: Some([("mbta_alert_period".to_owned(), period.to_owned())].into()),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            event("20240607T230000Z").period_id(),
+            PeriodId::Start(at("2024-06-07T23:00:00Z"))
+        );
+        assert_eq!(event("3").period_id(), PeriodId::Index(3));
+    }
+
+    #[test]
+    fn test_alert_occurrences_one_per_period() {
+        let alert = make_weekend_alert();
+        let periods: Vec<usize> = alert_occurrences(&alert, at("2024-05-01T00:00:00Z"))
+            .iter()
+            .map(|o| o.period)
+            .collect();
+        assert_eq!(periods, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_alert_occurrences_skips_ended_periods() {
+        // Listing only returns events ending after now, so an ended period would
+        // otherwise be recreated on every run.
+        let alert = make_weekend_alert();
+        let periods: Vec<usize> = alert_occurrences(&alert, at("2024-06-09T12:00:00Z"))
+            .iter()
+            .map(|o| o.period)
+            .collect();
+        assert_eq!(periods, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_alert_occurrences_open_ended_period_never_ends() {
+        let alert = make_alert("Red", "DELAY", Some("2020-01-01T00:00:00-05:00"), None);
+        assert_eq!(alert_occurrences(&alert, Utc::now()).len(), 1);
+    }
+
+    #[test]
+    fn test_alert_occurrences_no_period_yields_one() {
+        let alert = make_alert_no_period("Red", "DELAY");
+        let occurrences = alert_occurrences(&alert, Utc::now());
+        assert_eq!(occurrences.len(), 1);
+        assert_eq!(occurrences[0].period, 0);
+    }
+
+    #[test]
+    fn test_plan_creates_one_event_per_period() {
+        let alert = make_weekend_alert();
+        let plan = plan_calendar_sync(
+            &HashMap::new(),
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Disabled,
+            at("2024-05-01T00:00:00Z"),
+        );
+        let periods: Vec<usize> = plan.to_create.iter().map(|o| o.period).collect();
+        assert_eq!(periods, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_plan_creates_only_missing_periods() {
+        let alert = make_weekend_alert();
+        let mut existing = HashMap::new();
+        for period in [0, 2] {
+            existing.insert(
+                occurrence_key(&alert, period),
+                ExistingEvent {
+                    event_id: format!("event-{period}"),
+                    ai_summary: None,
+                    state_hash: Some(event_state_hash(&alert, period, &StopDirectory::default())),
+                    fields: EventFields::default(),
+                },
+            );
+        }
+
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Disabled,
+            at("2024-05-01T00:00:00Z"),
+        );
+
+        assert_eq!(plan.to_create.len(), 1);
+        assert_eq!(plan.to_create[0].period, 1);
+        assert!(plan.to_update.is_empty());
+        assert!(plan.to_delete.is_empty());
+    }
+
+    #[test]
+    fn test_plan_leaves_later_periods_when_an_earlier_one_is_dropped() {
+        let alert = make_weekend_alert();
+        let stops = StopDirectory::default();
+        let existing: HashMap<EventKey, ExistingEvent> = (0..3)
+            .map(|period| {
+                let event = ExistingEvent {
+                    event_id: format!("event-{period}"),
+                    ai_summary: None,
+                    state_hash: Some(event_state_hash(&alert, period, &stops)),
+                    fields: EventFields::default(),
+                };
+                (occurrence_key(&alert, period), event)
+            })
+            .collect();
+
+        let mut later = make_weekend_alert();
+        later.attributes.active_period.remove(0);
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&later],
+            &stops,
+            AiSummaries::Disabled,
+            at("2024-05-01T00:00:00Z"),
+        );
+
+        assert!(plan.to_create.is_empty());
+        assert!(plan.to_update.is_empty());
+        assert_eq!(plan.to_delete, vec!["event-0"]);
+        assert_eq!(plan.up_to_date.len(), 2);
+    }
+
+    #[test]
+    fn test_period_id_round_trips() {
+        for id in [
+            PeriodId::Start(at("2024-06-07T23:00:00Z")),
+            PeriodId::Index(2),
+        ] {
+            assert_eq!(id.to_string().parse::<PeriodId>().unwrap(), id);
+        }
+        assert_eq!(
+            PeriodId::Start(at("2024-06-07T23:00:00Z")).to_string(),
+            "20240607T230000Z"
+        );
+        assert!("2024-06-07".parse::<PeriodId>().is_err());
+    }
+
+    #[test]
+    fn test_plan_deletes_period_the_alert_no_longer_has() {
+        let alert = make_alert("Red", "SHUTTLE", Some("2024-06-01T03:00:00-04:00"), None);
+        let mut existing = make_existing(
+            occurrence_key(&alert, 0),
+            "event-0",
+            None,
+            Some(&event_state_hash(&alert, 0, &StopDirectory::default())),
+        );
+        existing.insert(
+            event_key(&alert.id, 1),
+            ExistingEvent {
+                event_id: "event-1".to_owned(),
+                ai_summary: None,
+                state_hash: Some("h".to_owned()),
+                fields: EventFields::default(),
+            },
+        );
+
+        let plan = plan_calendar_sync(
+            &existing,
+            &[&alert],
+            &StopDirectory::default(),
+            AiSummaries::Disabled,
+            Utc::now(),
+        );
+
+        assert!(plan.to_create.is_empty());
+        assert!(plan.to_update.is_empty());
+        assert_eq!(plan.to_delete, vec!["event-1"]);
+    }
+
+    #[test]
+    fn test_event_state_hash_differs_per_period() {
+        let alert = make_weekend_alert();
+        assert_ne!(
+            event_state_hash(&alert, 0, &StopDirectory::default()),
+            event_state_hash(&alert, 1, &StopDirectory::default())
+        );
+    }
+
+    #[test]
+    fn test_event_body_uses_the_occurrence_period() {
+        let alert = make_weekend_alert();
+        let body = event_body(&alert, 1, "Shuttle", None, &StopDirectory::default()).unwrap();
+        assert_eq!(body["start"]["dateTime"], "2024-06-08T03:00:00-04:00");
+        assert_eq!(body["end"]["dateTime"], "2024-06-10T02:59:00-04:00");
+        let private = &body["extendedProperties"]["private"];
+        assert_eq!(private["mbta_alert_period"], "20240608T070000Z");
+        assert_eq!(
+            private["mbta_alert_state_hash"],
+            event_state_hash(&alert, 1, &StopDirectory::default())
+        );
+    }
 }
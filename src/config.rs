@@ -0,0 +1,825 @@
+//! Settings for the CLI and the Lambda: an optional TOML file, with
+//! environment variables layered on top, resolved and validated before
+//! anything is fetched.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+
+use crate::cache::Cache;
+use crate::calendar::{CalendarConfig, CalendarTarget};
+use crate::feed_state::FeedState;
+use crate::filter::{EffectFilter, StationFilter};
+use crate::hash::Fnv1a;
+use crate::mbta::{AlertQuery, RouteFilter};
+use crate::types::RouteType;
+
+/// Names the config file when `--config` isn't given.
+pub const CONFIG_VAR: &str = "MBTALERTS_CONFIG";
+
+/// The Bedrock client reads its model from here.
+const MODEL_ID_VAR: &str = "BEDROCK_MODEL_ID";
+
+/// The config file as written. Every section and field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    mbta: MbtaSection,
+    filter: EffectFilter,
+    calendar: CalendarSection,
+    summarizer: SummarizerSettings,
+    output: OutputSettings,
+    cache: CacheSettings,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct MbtaSection {
+    api_key: Option<Secret>,
+    state_file: Option<PathBuf>,
+    routes: Vec<String>,
+    route_types: Vec<String>,
+    stops: Vec<String>,
+}
+
+impl MbtaSection {
+    fn route_filter(&self) -> Result<RouteFilter> {
+        match (self.routes.is_empty(), self.route_types.is_empty()) {
+            (false, false) => bail!("Set at most one of mbta.routes and mbta.route_types"),
+            (false, true) => Ok(RouteFilter::Routes(self.routes.clone())),
+            (true, false) => Ok(RouteFilter::RouteTypes(
+                self.route_types
+                    .iter()
+                    .map(|t| t.parse())
+                    .collect::<Result<Vec<RouteType>>>()?,
+            )),
+            (true, true) => Ok(RouteFilter::default()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CalendarSection {
+    service_account_key_file: Option<PathBuf>,
+    id: Option<String>,
+    ids: HashMap<String, CalendarTarget>,
+    caldav: CalDavSettings,
+    graph: GraphSettings,
+    concurrency: ConcurrencySettings,
+}
+
+impl CalendarSection {
+    fn calendars(self) -> Result<Option<CalendarConfig>> {
+        match (self.id, self.ids.is_empty()) {
+            (Some(_), false) => {
+                bail!("Set at most one of calendar.id and calendar.ids in config file")
+            }
+            (Some(id), true) => Ok(Some(CalendarConfig::single(id))),
+            (None, false) => Ok(Some(
+                CalendarConfig::per_line(self.ids)
+                    .context("Invalid calendar.ids in config file")?,
+            )),
+            (None, true) => Ok(None),
+        }
+    }
+}
+
+/// A credential, kept out of debug logging.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Where events go and how to authenticate to Google Calendar, CalDAV and
+/// Microsoft Graph.
+#[derive(Debug, Default)]
+pub struct CalendarSettings {
+    service_account_key: Option<Secret>,
+    service_account_key_file: Option<PathBuf>,
+    /// `None` when no calendar is configured; only calendar sync needs one.
+    pub calendars: Option<CalendarConfig>,
+    pub caldav: CalDavSettings,
+    pub graph: GraphSettings,
+    pub concurrency: ConcurrencySettings,
+}
+
+impl CalendarSettings {
+    /// The service account key JSON, from GOOGLE_SERVICE_ACCOUNT_KEY or else
+    /// the configured key file.
+    pub fn service_account_key(&self) -> Result<String> {
+        match (&self.service_account_key, &self.service_account_key_file) {
+            (Some(key), _) => Ok(key.expose().to_owned()),
+            (None, Some(path)) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read service account key {}", path.display())),
+            (None, None) => bail!(
+                "No service account key: set calendar.service_account_key_file in the config \
+                 file, or GOOGLE_SERVICE_ACCOUNT_KEY"
+            ),
+        }
+    }
+}
+
+/// Credentials for the calendars whose IDs are CalDAV collection URLs. Without
+/// a username, requests go unauthenticated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalDavSettings {
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+}
+
+/// The Entra ID app registration Outlook calendars are synced as, and the
+/// mailbox they're in. The app needs the Calendars.ReadWrite application
+/// permission.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphSettings {
+    pub tenant_id: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<Secret>,
+    /// The user principal name or ID whose calendars these are.
+    pub user: Option<String>,
+}
+
+/// How many event titles a sync generates, and how many calendar writes it
+/// makes, at once, across all its calendars.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencySettings {
+    pub summaries: usize,
+    /// Kept low: Google Calendar limits how fast a user, here the service
+    /// account, may write.
+    pub writes: usize,
+}
+
+impl Default for ConcurrencySettings {
+    fn default() -> Self {
+        Self {
+            summaries: 4,
+            writes: 4,
+        }
+    }
+}
+
+impl ConcurrencySettings {
+    /// Each limit is a semaphore's permits, which can't be too many either.
+    fn validate(&self) -> Result<()> {
+        let limits = 1..=Semaphore::MAX_PERMITS;
+        if !limits.contains(&self.summaries) || !limits.contains(&self.writes) {
+            bail!(
+                "Concurrency limits must be from 1 to {}",
+                Semaphore::MAX_PERMITS
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SummarizerSettings {
+    /// Whether to ask Bedrock for event titles at all.
+    pub enabled: bool,
+    /// Overrides the Bedrock client's default model.
+    pub model_id: Option<String>,
+}
+
+impl Default for SummarizerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            model_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSettings {
+    /// Defaults to [`Cache::default_dir`].
+    pub dir: Option<PathBuf>,
+    /// How long a cached alerts response is reused.
+    pub ttl_minutes: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            ttl_minutes: 15,
+        }
+    }
+}
+
+impl CacheSettings {
+    pub fn dir(&self) -> PathBuf {
+        self.dir.clone().unwrap_or_else(Cache::default_dir)
+    }
+
+    pub fn cache(&self) -> Cache {
+        Cache::new(self.dir(), minutes(self.ttl_minutes))
+    }
+}
+
+pub fn minutes(minutes: u64) -> Duration {
+    Duration::from_secs(minutes * 60)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    /// Bold the line prefix in terminal output.
+    pub color: bool,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self { color: true }
+    }
+}
+
+#[derive(Debug)]
+pub struct Config {
+    /// Raises the API's rate limit from 20 requests a minute to 1000.
+    pub mbta_api_key: Option<Secret>,
+    /// Where calendar sync records the alerts feed it last saw.
+    pub state_file: PathBuf,
+    /// The alerts to fetch. The file and environment choose its routes; the
+    /// rest of the API's filters are left to the CLI.
+    pub query: AlertQuery,
+    pub stations: StationFilter,
+    pub effects: EffectFilter,
+    pub calendar: CalendarSettings,
+    pub summarizer: SummarizerSettings,
+    pub output: OutputSettings,
+    pub cache: CacheSettings,
+}
+
+impl Config {
+    /// Loads the config file at `path`, else the one MBTALERTS_CONFIG names,
+    /// else none, and applies the environment on top.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let env = |name: &str| std::env::var(name).ok();
+
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(|| env(CONFIG_VAR).map(PathBuf::from));
+        let Some(path) = path else {
+            return Self::resolve(ConfigFile::default(), env);
+        };
+
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut file: ConfigFile = toml::from_str(&text)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+        // Relative paths are beside the config, not wherever we're run from.
+        if let Some(dir) = path.parent() {
+            file.calendar.service_account_key_file = file
+                .calendar
+                .service_account_key_file
+                .map(|key| dir.join(key));
+            file.mbta.state_file = file.mbta.state_file.map(|state| dir.join(state));
+            file.cache.dir = file.cache.dir.map(|cache| dir.join(cache));
+        }
+
+        Self::resolve(file, env)
+    }
+
+    /// Applies the environment over `file`: a variable that's set wins over
+    /// the file's value.
+    fn resolve(file: ConfigFile, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let routes = match (env("MBTA_ROUTES"), env("MBTA_ROUTE_TYPES")) {
+            (Some(_), Some(_)) => bail!("Set at most one of MBTA_ROUTES and MBTA_ROUTE_TYPES"),
+            (Some(routes), None) => {
+                RouteFilter::parse_routes(&routes).context("Invalid MBTA_ROUTES")?
+            }
+            (None, Some(types)) => {
+                RouteFilter::parse_route_types(&types).context("Invalid MBTA_ROUTE_TYPES")?
+            }
+            (None, None) => file
+                .mbta
+                .route_filter()
+                .context("Invalid mbta routes in config file")?,
+        };
+
+        let mbta_api_key = env("MBTA_API_KEY").map(Secret).or(file.mbta.api_key);
+        let state_file = env("MBTA_STATE_FILE")
+            .map(PathBuf::from)
+            .or(file.mbta.state_file)
+            .unwrap_or_else(FeedState::default_path);
+
+        let stations = match env("MBTA_STOPS") {
+            Some(list) => StationFilter::parse(&list),
+            None => StationFilter::new(file.mbta.stops),
+        };
+
+        let effects = match env("MBTA_ALERT_FILTER") {
+            Some(json) => EffectFilter::from_json(&json).context("Invalid MBTA_ALERT_FILTER")?,
+            None => {
+                file.filter
+                    .validate()
+                    .context("Invalid filter in config file")?;
+                file.filter
+            }
+        };
+
+        let service_account_key = env("GOOGLE_SERVICE_ACCOUNT_KEY").map(Secret);
+        let service_account_key_file = file.calendar.service_account_key_file.clone();
+        let mut caldav = file.calendar.caldav.clone();
+        if let Some(username) = env("CALDAV_USERNAME") {
+            caldav.username = Some(username);
+        }
+        if let Some(password) = env("CALDAV_PASSWORD") {
+            caldav.password = Some(Secret(password));
+        }
+        let mut graph = file.calendar.graph.clone();
+        if let Some(tenant_id) = env("GRAPH_TENANT_ID") {
+            graph.tenant_id = Some(tenant_id);
+        }
+        if let Some(client_id) = env("GRAPH_CLIENT_ID") {
+            graph.client_id = Some(client_id);
+        }
+        if let Some(client_secret) = env("GRAPH_CLIENT_SECRET") {
+            graph.client_secret = Some(Secret(client_secret));
+        }
+        if let Some(user) = env("GRAPH_USER") {
+            graph.user = Some(user);
+        }
+        let mut concurrency = file.calendar.concurrency.clone();
+        if let Some(summaries) = env("SYNC_SUMMARY_CONCURRENCY") {
+            concurrency.summaries = summaries
+                .parse()
+                .context("Invalid SYNC_SUMMARY_CONCURRENCY")?;
+        }
+        if let Some(writes) = env("SYNC_WRITE_CONCURRENCY") {
+            concurrency.writes = writes.parse().context("Invalid SYNC_WRITE_CONCURRENCY")?;
+        }
+        concurrency.validate()?;
+        let calendars = if let Some(json) = env("GOOGLE_CALENDAR_IDS") {
+            let targets =
+                serde_json::from_str(&json).context("GOOGLE_CALENDAR_IDS is not valid JSON")?;
+            Some(CalendarConfig::per_line(targets).context("Invalid GOOGLE_CALENDAR_IDS")?)
+        } else if let Some(id) = env("GOOGLE_CALENDAR_ID") {
+            Some(CalendarConfig::single(id))
+        } else {
+            file.calendar.calendars()?
+        };
+
+        let mut summarizer = file.summarizer;
+        if let Some(model_id) = env(MODEL_ID_VAR) {
+            summarizer.model_id = Some(model_id);
+        }
+        if summarizer.model_id.as_deref().is_some_and(str::is_empty) {
+            bail!("summarizer.model_id is empty");
+        }
+
+        // https://no-color.org: any non-empty value turns color off.
+        let mut output = file.output;
+        if env("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            output.color = false;
+        }
+
+        Ok(Self {
+            mbta_api_key,
+            state_file,
+            query: AlertQuery::new(routes),
+            stations,
+            effects,
+            calendar: CalendarSettings {
+                service_account_key,
+                service_account_key_file,
+                calendars,
+                caldav,
+                graph,
+                concurrency,
+            },
+            summarizer,
+            output,
+            cache: file.cache,
+        })
+    }
+
+    /// What the feed's state is recorded for: the alerts query, and a hash of
+    /// the rest of what decides a sync's events, namely the calendars and the
+    /// filters. Changing any of them makes the next sync a full one.
+    pub fn sync_key(&self) -> String {
+        let mut hash = Fnv1a::new();
+        for line in self
+            .calendar
+            .calendars
+            .iter()
+            .flat_map(CalendarConfig::describe)
+        {
+            hash.feed(&line);
+        }
+        // Debug renderings only need to agree between runs of one build; a
+        // new build's differing costs a full sync, which it may well need.
+        hash.feed(&format!("{:?}", self.effects));
+        hash.feed(&format!("{:?}", self.stations));
+        format!("{} {:016x}", self.query.key(), hash.finish())
+    }
+
+    /// Hands a model ID from the config file to the Bedrock client, which
+    /// only reads it from BEDROCK_MODEL_ID.
+    ///
+    /// # Safety
+    ///
+    /// Sets an environment variable, so no other thread may be running.
+    pub unsafe fn export_model_id(&self) {
+        if let Some(model_id) = &self.summarizer.model_id {
+            // SAFETY: the caller guarantees no other threads are running.
+            unsafe { std::env::set_var(MODEL_ID_VAR, model_id) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Effect;
+
+    fn resolve(toml: &str, vars: &[(&str, &str)]) -> Result<Config> {
+        let file: ConfigFile = toml::from_str(toml)?;
+        Config::resolve(file, |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| (*v).to_owned())
+        })
+    }
+
+    #[test]
+    fn test_empty_config_uses_defaults() -> Result<()> {
+        let config = resolve("", &[])?;
+        assert_eq!(config.query.routes, RouteFilter::default());
+        assert!(config.stations.is_empty());
+        assert_eq!(config.effects, EffectFilter::default());
+        assert!(config.mbta_api_key.is_none());
+        assert_eq!(config.state_file, FeedState::default_path());
+        assert!(config.calendar.calendars.is_none());
+        assert_eq!(config.summarizer, SummarizerSettings::default());
+        assert!(config.output.color);
+        assert_eq!(config.cache.dir(), Cache::default_dir());
+        assert_eq!(config.cache.cache().max_age(), Some(minutes(15)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_config() -> Result<()> {
+        let config = resolve(
+            r#"
+            [mbta]
+            state_file = "state.json"
+            route_types = ["subway", "light-rail"]
+            stops = ["Park Street", "place-alfcl"]
+
+            [filter]
+            include_effects = ["SHUTTLE", "SUSPENSION"]
+            min_severity = 5
+
+            [calendar]
+            service_account_key_file = "key.json"
+
+            [calendar.ids]
+            default = "d"
+            Red = "r"
+            Accessibility = { id = "a", filter = { include_effects = ["ELEVATOR_CLOSURE"] } }
+
+            [summarizer]
+            enabled = false
+            model_id = "some-model"
+
+            [output]
+            color = false
+
+            [cache]
+            dir = "cache"
+            ttl_minutes = 5
+            "#,
+            &[],
+        )?;
+
+        assert_eq!(config.state_file, PathBuf::from("state.json"));
+        assert_eq!(
+            config.query.routes,
+            RouteFilter::RouteTypes(vec![RouteType::Subway, RouteType::LightRail])
+        );
+        assert_eq!(
+            config.stations,
+            StationFilter::parse("Park Street,place-alfcl")
+        );
+        assert_eq!(
+            config.effects.include_effects,
+            vec![Effect::Shuttle, Effect::Suspension]
+        );
+        let Some(CalendarConfig::PerLine { map, .. }) = &config.calendar.calendars else {
+            panic!("expected PerLine config");
+        };
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            config.calendar.service_account_key_file,
+            Some(PathBuf::from("key.json"))
+        );
+        assert!(!config.summarizer.enabled);
+        assert_eq!(config.summarizer.model_id.as_deref(), Some("some-model"));
+        assert!(!config.output.color);
+        assert_eq!(config.cache.dir(), PathBuf::from("cache"));
+        assert_eq!(config.cache.cache().max_age(), Some(minutes(5)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_overrides_file() -> Result<()> {
+        let config = resolve(
+            r#"
+            [mbta]
+            routes = ["Red"]
+            stops = ["Alewife"]
+
+            [calendar]
+            id = "from-file"
+
+            [summarizer]
+            model_id = "file-model"
+            "#,
+            &[
+                ("MBTA_ROUTE_TYPES", "ferry"),
+                ("MBTA_STOPS", "Park Street"),
+                ("MBTA_ALERT_FILTER", r#"{"exclude_effects": []}"#),
+                ("GOOGLE_CALENDAR_ID", "from-env"),
+                ("BEDROCK_MODEL_ID", "env-model"),
+                ("NO_COLOR", "1"),
+            ],
+        )?;
+
+        assert_eq!(
+            config.query.routes,
+            RouteFilter::RouteTypes(vec![RouteType::Ferry])
+        );
+        assert_eq!(config.stations, StationFilter::parse("Park Street"));
+        assert!(config.effects.exclude_effects.is_empty());
+        let Some(CalendarConfig::Single(id)) = &config.calendar.calendars else {
+            panic!("expected Single config");
+        };
+        assert_eq!(id, "from-env@group.calendar.google.com");
+        assert_eq!(config.summarizer.model_id.as_deref(), Some("env-model"));
+        assert!(!config.output.color);
+        Ok(())
+    }
+
+    #[test]
+    fn test_calendar_ids_env_wins_over_calendar_id_env() -> Result<()> {
+        let config = resolve(
+            "",
+            &[
+                ("GOOGLE_CALENDAR_IDS", r#"{"default": "d"}"#),
+                ("GOOGLE_CALENDAR_ID", "single"),
+            ],
+        )?;
+        assert!(matches!(
+            config.calendar.calendars,
+            Some(CalendarConfig::PerLine { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_account_key_env_wins_over_file() -> Result<()> {
+        let config = resolve(
+            "[calendar]\nservice_account_key_file = \"missing.json\"",
+            &[("GOOGLE_SERVICE_ACCOUNT_KEY", "{}")],
+        )?;
+        assert_eq!(config.calendar.service_account_key()?, "{}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_caldav_env_wins_over_file() -> Result<()> {
+        let config = resolve(
+            r#"
+            [calendar]
+            id = "https://dav.example.com/calendars/me/mbta"
+
+            [calendar.caldav]
+            username = "me"
+            password = "from-file"
+            "#,
+            &[("CALDAV_PASSWORD", "from-env")],
+        )?;
+        let Some(CalendarConfig::Single(id)) = &config.calendar.calendars else {
+            panic!("expected Single config");
+        };
+        assert_eq!(id, "https://dav.example.com/calendars/me/mbta/");
+        assert_eq!(config.calendar.caldav.username.as_deref(), Some("me"));
+        assert_eq!(
+            config.calendar.caldav.password.as_ref().map(Secret::expose),
+            Some("from-env")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_graph_settings_from_file_and_env() -> Result<()> {
+        let config = resolve(
+            r#"
+            [calendar.graph]
+            tenant_id = "contoso"
+            client_id = "app"
+            client_secret = "from-file"
+            "#,
+            &[
+                ("GRAPH_CLIENT_SECRET", "from-env"),
+                ("GRAPH_USER", "alerts@contoso.com"),
+            ],
+        )?;
+        let graph = &config.calendar.graph;
+        assert_eq!(graph.tenant_id.as_deref(), Some("contoso"));
+        assert_eq!(
+            graph.client_secret.as_ref().map(Secret::expose),
+            Some("from-env")
+        );
+        assert_eq!(graph.user.as_deref(), Some("alerts@contoso.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrency_from_file_and_env() -> Result<()> {
+        let config = resolve(
+            "[calendar.concurrency]
+summaries = 8
+writes = 2",
+            &[("SYNC_WRITE_CONCURRENCY", "1")],
+        )?;
+        assert_eq!(
+            config.calendar.concurrency,
+            ConcurrencySettings {
+                summaries: 8,
+                writes: 1,
+            }
+        );
+        assert_eq!(
+            resolve("", &[])?.calendar.concurrency,
+            ConcurrencySettings::default()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_of_range_concurrency_errors() {
+        assert!(
+            resolve(
+                "[calendar.concurrency]
+writes = 0",
+                &[]
+            )
+            .is_err()
+        );
+        assert!(resolve("", &[("SYNC_SUMMARY_CONCURRENCY", "0")]).is_err());
+        let too_many = (Semaphore::MAX_PERMITS + 1).to_string();
+        assert!(resolve("", &[("SYNC_WRITE_CONCURRENCY", &too_many)]).is_err());
+        assert!(resolve("", &[("SYNC_WRITE_CONCURRENCY", "many")]).is_err());
+    }
+
+    #[test]
+    fn test_missing_service_account_key_errors() -> Result<()> {
+        assert!(resolve("", &[])?.calendar.service_account_key().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() -> Result<()> {
+        let config = resolve(
+            "",
+            &[
+                ("GOOGLE_SERVICE_ACCOUNT_KEY", "secret-key"),
+                ("MBTA_API_KEY", "secret-api-key"),
+                ("CALDAV_PASSWORD", "secret-password"),
+                ("GRAPH_CLIENT_SECRET", "secret-client-secret"),
+            ],
+        )?;
+        // Only field names like `client_secret` may mention it.
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("secret-"), "{debug}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_mbta_api_key_env_wins_over_file() -> Result<()> {
+        let config = resolve("[mbta]\napi_key = \"from-file\"", &[])?;
+        assert_eq!(
+            config.mbta_api_key.as_ref().map(Secret::expose),
+            Some("from-file")
+        );
+        let config = resolve(
+            "[mbta]\napi_key = \"from-file\"",
+            &[("MBTA_API_KEY", "from-env")],
+        )?;
+        assert_eq!(
+            config.mbta_api_key.as_ref().map(Secret::expose),
+            Some("from-env")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_field_errors() {
+        assert!(resolve("[mbta]\nroute = [\"Red\"]", &[]).is_err());
+        assert!(resolve("[sumarizer]\nenabled = false", &[]).is_err());
+    }
+
+    #[test]
+    fn test_routes_and_route_types_conflict() {
+        assert!(resolve("[mbta]\nroutes = [\"Red\"]\nroute_types = [\"bus\"]", &[]).is_err());
+        assert!(
+            resolve(
+                "",
+                &[("MBTA_ROUTES", "Red"), ("MBTA_ROUTE_TYPES", "subway")]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_route_type_errors() {
+        assert!(resolve("[mbta]\nroute_types = [\"monorail\"]", &[]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_filter_errors() {
+        assert!(resolve("[filter]\ninclude_effects = [\"NOPE\"]", &[]).is_err());
+        assert!(resolve("", &[("MBTA_ALERT_FILTER", "not json")]).is_err());
+        assert!(resolve("[filter]\nmin_severity = 50", &[]).is_err());
+        assert!(resolve("", &[("MBTA_ALERT_FILTER", r#"{"min_severity": 50}"#)]).is_err());
+        assert!(
+            resolve(
+                "",
+                &[(
+                    "GOOGLE_CALENDAR_IDS",
+                    r#"{"default": "d", "Red": {"id": "r", "filter": {"min_severity": 50}}}"#
+                )]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_calendar_id_and_ids_conflict() {
+        assert!(
+            resolve(
+                "[calendar]\nid = \"a\"\n[calendar.ids]\ndefault = \"d\"",
+                &[]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_calendar_ids_error() {
+        assert!(resolve("[calendar.ids]\nRed = \"r\"", &[]).is_err());
+        assert!(resolve("", &[("GOOGLE_CALENDAR_IDS", r#"{"Gren": "g"}"#)]).is_err());
+    }
+
+    #[test]
+    fn test_empty_model_id_errors() {
+        assert!(resolve("[summarizer]\nmodel_id = \"\"", &[]).is_err());
+    }
+
+    #[test]
+    fn test_empty_no_color_keeps_color() -> Result<()> {
+        assert!(resolve("", &[("NO_COLOR", "")])?.output.color);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_key_changes_with_calendars_and_filters() -> Result<()> {
+        let ids = "[calendar.ids]\ndefault = \"d\"\nRed = \"r\"\nBlue = \"b\"\n";
+        let key = resolve(ids, &[])?.sync_key();
+        assert_eq!(resolve(ids, &[])?.sync_key(), key);
+
+        let moved = "[calendar.ids]\ndefault = \"d\"\nRed = \"b\"\nBlue = \"r\"\n";
+        assert_ne!(resolve(moved, &[])?.sync_key(), key);
+        let filtered = resolve(ids, &[("MBTA_ALERT_FILTER", r#"{"min_severity": 5}"#)])?;
+        assert_ne!(filtered.sync_key(), key);
+        let stations = resolve(ids, &[("MBTA_STOPS", "Park Street")])?;
+        assert_ne!(stations.sync_key(), key);
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! The alerts feed as of the last successful calendar sync, kept between runs
+//! so the next one can ask the API whether anything has changed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::APP_NAME;
+use crate::mbta::Validators;
+
+/// How long an unchanged feed is trusted for. Past this, a sync is a full
+/// one, which puts back whatever was changed in the calendars by hand.
+pub const MAX_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedState {
+    /// The [`Config::sync_key`](crate::config::Config::sync_key) of the sync
+    /// that recorded this; another config's validators say nothing about
+    /// what this one would write.
+    key: String,
+    saved_at: DateTime<Utc>,
+    validators: Validators,
+}
+
+impl FeedState {
+    pub fn new(key: String, validators: Validators, saved_at: DateTime<Utc>) -> Self {
+        Self {
+            key,
+            saved_at,
+            validators,
+        }
+    }
+
+    /// In the OS temp directory, beside the response cache.
+    pub fn default_path() -> PathBuf {
+        std::env::temp_dir().join(format!("{APP_NAME}-feed-state.json"))
+    }
+
+    /// The validators saved at `path` for `key`, unless they're older than
+    /// [`MAX_AGE`] at `now`. A missing, unreadable, mismatched or stale state
+    /// just means a full sync.
+    pub fn load(path: &Path, key: &str, now: DateTime<Utc>) -> Option<Validators> {
+        let json = fs::read_to_string(path).ok()?;
+        let state: Self = serde_json::from_str(&json)
+            .inspect_err(|e| warn!("Ignoring unreadable feed state {}: {e}", path.display()))
+            .ok()?;
+        if state.key != key {
+            debug!("Ignoring feed state for another config: {}", state.key);
+            return None;
+        }
+        if (now - state.saved_at).to_std().unwrap_or_default() > MAX_AGE {
+            debug!("Ignoring feed state from {}", state.saved_at);
+            return None;
+        }
+        Some(state.validators)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write feed state {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn validators() -> Validators {
+        Validators {
+            etag: Some("\"abc\"".to_owned()),
+            last_modified: Some("Tue, 14 Oct 2025 12:00:00 GMT".to_owned()),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2025-10-14T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_save_and_load() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "{APP_NAME}-test-feed-state-{}.json",
+            std::process::id()
+        ));
+        let key = "filter[activity]=ALL 0123456789abcdef";
+        FeedState::new(key.to_owned(), validators(), now()).save(&path)?;
+
+        let loaded = FeedState::load(&path, key, now() + chrono::Duration::hours(1));
+        let other = FeedState::load(&path, "filter[route]=Red 0123456789abcdef", now());
+        let stale = FeedState::load(&path, key, now() + chrono::Duration::hours(7));
+        fs::remove_file(&path)?;
+
+        assert_eq!(loaded, Some(validators()));
+        assert_eq!(other, None);
+        assert_eq!(stale, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let path = Path::new("/nonexistent/mbtalerts-feed-state.json");
+        assert_eq!(FeedState::load(path, "", now()), None);
+    }
+}
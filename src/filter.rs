@@ -0,0 +1,368 @@
+//! Narrowing the fetched alerts down to the ones a rider asked for.
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+
+use crate::stops::StopDirectory;
+use crate::types::{Alert, Effect, Lifecycle, Severity};
+
+/// The items of a comma-separated list, trimmed, skipping empty ones.
+pub fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Parses a comma-separated list of API enum values, accepting them in any
+/// case and with dashes for underscores (`elevator-closure`).
+pub fn parse_values<T: From<String>>(list: &str) -> Vec<T> {
+    split_list(list)
+        .map(|value| T::from(value.to_uppercase().replace('-', "_")))
+        .collect()
+}
+
+/// Station-level issues (closed stairways, parking, elevators, etc.) are
+/// noise for most riders, so they're excluded unless a filter says otherwise.
+/// The accessibility calendar opts back into its share of them. Facility
+/// alerts (parking, bike racks, docks) are here too, since the feed is
+/// queried for every activity to get elevator outages, which brings them in.
+const STATION_EFFECTS: &[Effect] = &[
+    Effect::StationIssue,
+    Effect::StopClosure,
+    Effect::StationClosure,
+    Effect::ParkingIssue,
+    Effect::ParkingClosure,
+    Effect::BikeIssue,
+    Effect::DockIssue,
+    Effect::DockClosure,
+    Effect::FacilityIssue,
+    Effect::ElevatorClosure,
+    Effect::EscalatorClosure,
+    Effect::AccessIssue,
+];
+
+/// Which alerts to keep by what they are: effect, severity and lifecycle.
+///
+/// Deserializes from the config file's `[filter]`, MBTA_ALERT_FILTER, and per
+/// calendar in the calendar IDs; omitted fields keep their defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectFilter {
+    /// Effects to keep. When non-empty, this replaces `exclude_effects`.
+    pub include_effects: Vec<Effect>,
+    /// Effects to drop. Defaults to station-level issues.
+    pub exclude_effects: Vec<Effect>,
+    /// Drop alerts less severe than this.
+    pub min_severity: Option<Severity>,
+    /// Lifecycles to keep; empty keeps all but `exclude_lifecycles`.
+    pub include_lifecycles: Vec<Lifecycle>,
+    pub exclude_lifecycles: Vec<Lifecycle>,
+}
+
+impl Default for EffectFilter {
+    fn default() -> Self {
+        Self {
+            include_effects: Vec::new(),
+            exclude_effects: STATION_EFFECTS.to_vec(),
+            min_severity: None,
+            include_lifecycles: Vec::new(),
+            exclude_lifecycles: Vec::new(),
+        }
+    }
+}
+
+impl EffectFilter {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let filter: Self = serde_json::from_str(json)?;
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    /// Rejects values the API doesn't use: a misspelled effect would
+    /// otherwise silently match nothing, as would a severity above 10.
+    pub fn validate(&self) -> Result<()> {
+        let effects = self.include_effects.iter().chain(&self.exclude_effects);
+        if let Some(effect) = effects.into_iter().find(|e| !e.is_known()) {
+            bail!("Unknown effect '{effect}'");
+        }
+        let lifecycles = self
+            .include_lifecycles
+            .iter()
+            .chain(&self.exclude_lifecycles);
+        if let Some(lifecycle) = lifecycles.into_iter().find(|l| !l.is_known()) {
+            bail!("Unknown lifecycle '{lifecycle}'");
+        }
+        if let Some(min) = self.min_severity
+            && !min.is_known()
+        {
+            bail!("Invalid min_severity {min}: expected 0-10");
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, alert: &Alert) -> bool {
+        let attributes = &alert.attributes;
+
+        let effect = if self.include_effects.is_empty() {
+            !self.exclude_effects.contains(&attributes.effect)
+        } else {
+            self.include_effects.contains(&attributes.effect)
+        };
+        let severity = self
+            .min_severity
+            .is_none_or(|min| attributes.severity >= min);
+        let lifecycle = (self.include_lifecycles.is_empty()
+            || self.include_lifecycles.contains(&attributes.lifecycle))
+            && !self.exclude_lifecycles.contains(&attributes.lifecycle);
+
+        effect && severity && lifecycle
+    }
+}
+
+/// The stations a rider cares about, each given as a stop ID (`place-pktrm`)
+/// or a station name ("Park Street"). Empty means every station.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StationFilter {
+    stations: Vec<String>,
+}
+
+impl StationFilter {
+    pub fn new(stations: Vec<String>) -> Self {
+        Self { stations }
+    }
+
+    /// Parses a comma-separated station list.
+    pub fn parse(list: &str) -> Self {
+        Self {
+            stations: split_list(list).map(str::to_owned).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    fn is_wanted(&self, stop_id: &str, stops: &StopDirectory) -> bool {
+        let name = stops.name(stop_id);
+        self.stations
+            .iter()
+            .any(|wanted| wanted == stop_id || wanted.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether `stop_id` or the station it belongs to is one of the stations.
+    fn is_wanted_stop(&self, stop_id: &str, stops: &StopDirectory) -> bool {
+        self.is_wanted(stop_id, stops) || self.is_wanted(stops.station_id(stop_id), stops)
+    }
+
+    /// Whether the alert touches one of the stations, by its own stop IDs or
+    /// the stations they belong to.
+    ///
+    /// An alert naming no stops covers its routes end to end, so it matches
+    /// when one of those routes serves one of the stations: a line-wide
+    /// suspension is as much the rider's problem as a closed station. So does
+    /// one naming no routes either, or a route whose stops weren't looked up.
+    pub fn matches(&self, alert: &Alert, stops: &StopDirectory) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let stop_ids = alert.stop_ids();
+        if !stop_ids.is_empty() {
+            return stop_ids
+                .into_iter()
+                .any(|stop| self.is_wanted_stop(stop, stops));
+        }
+        let routes = alert.route_ids();
+        routes.is_empty()
+            || routes.into_iter().any(|route| {
+                stops
+                    .route_stops(route)
+                    .is_none_or(|served| served.iter().any(|stop| self.is_wanted_stop(stop, stops)))
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Stops;
+
+    fn directory() -> StopDirectory {
+        let stops: Stops = serde_json::from_str(
+            r#"{
+                "data": [
+                    {"id": "70075", "attributes": {"name": "Park Street"},
+                     "relationships": {"parent_station": {"data": {"id": "place-pktrm", "type": "stop"}}}}
+                ],
+                "included": [
+                    {"id": "place-pktrm", "attributes": {"name": "Park Street"},
+                     "relationships": {"parent_station": {"data": null}}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut directory = StopDirectory::default();
+        directory.extend(stops);
+        directory
+    }
+
+    fn alert_with(effect: &str, severity: u8, lifecycle: &str) -> Alert {
+        Alert::builder()
+            .route("Red")
+            .effect(effect)
+            .severity(severity)
+            .lifecycle(lifecycle)
+            .build()
+    }
+
+    #[test]
+    fn test_default_effect_filter_skips_station_issues() {
+        let filter = EffectFilter::default();
+        for effect in [
+            "STATION_ISSUE",
+            "STOP_CLOSURE",
+            "STATION_CLOSURE",
+            "PARKING_ISSUE",
+            "PARKING_CLOSURE",
+            "BIKE_ISSUE",
+            "DOCK_ISSUE",
+            "DOCK_CLOSURE",
+            "FACILITY_ISSUE",
+            "ELEVATOR_CLOSURE",
+        ] {
+            assert!(!filter.matches(&alert_with(effect, 3, "NEW")), "{effect}");
+        }
+        assert!(filter.matches(&alert_with("SHUTTLE", 3, "NEW")));
+    }
+
+    #[test]
+    fn test_include_effects_replaces_exclusions() -> Result<()> {
+        let filter = EffectFilter::from_json(
+            r#"{"include_effects": ["ELEVATOR_CLOSURE", "STATION_ISSUE"]}"#,
+        )?;
+        assert!(filter.matches(&alert_with("STATION_ISSUE", 3, "NEW")));
+        assert!(filter.matches(&alert_with("ELEVATOR_CLOSURE", 3, "NEW")));
+        assert!(!filter.matches(&alert_with("DELAY", 3, "NEW")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_severity() -> Result<()> {
+        let filter = EffectFilter::from_json(r#"{"min_severity": 5}"#)?;
+        assert!(filter.matches(&alert_with("DELAY", 5, "NEW")));
+        assert!(!filter.matches(&alert_with("DELAY", 4, "NEW")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lifecycles() -> Result<()> {
+        let filter = EffectFilter::from_json(
+            r#"{"include_lifecycles": ["NEW", "ONGOING"], "exclude_lifecycles": ["ONGOING"]}"#,
+        )?;
+        assert!(filter.matches(&alert_with("DELAY", 3, "NEW")));
+        assert!(!filter.matches(&alert_with("DELAY", 3, "ONGOING")));
+        assert!(!filter.matches(&alert_with("DELAY", 3, "UPCOMING")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_json_rejects_unknown_values_and_fields() {
+        assert!(EffectFilter::from_json(r#"{"include_effects": ["ELEVATR_CLOSURE"]}"#).is_err());
+        assert!(EffectFilter::from_json(r#"{"exclude_lifecycles": ["OLD"]}"#).is_err());
+        assert!(EffectFilter::from_json(r#"{"effects": ["DELAY"]}"#).is_err());
+        assert!(EffectFilter::from_json(r#"{"min_severity": 11}"#).is_err());
+        assert!(EffectFilter::from_json(r#"{"min_severity": 10}"#).is_ok());
+    }
+
+    #[test]
+    fn test_parse_values_is_lenient_about_spelling() {
+        assert_eq!(
+            parse_values::<Effect>("elevator-closure, Delay"),
+            vec![Effect::ElevatorClosure, Effect::Delay]
+        );
+    }
+
+    fn park_street_platform_alert() -> Alert {
+        Alert::builder().stop("Red", "70075").build()
+    }
+
+    #[test]
+    fn test_parse_trims_and_skips_empty() {
+        assert_eq!(
+            StationFilter::parse(" Park Street, place-alfcl ,,"),
+            StationFilter {
+                stations: vec!["Park Street".to_owned(), "place-alfcl".to_owned()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        assert!(StationFilter::default().matches(&park_street_platform_alert(), &directory()));
+    }
+
+    #[test]
+    fn test_matches_parent_station_id() {
+        let filter = StationFilter::parse("place-pktrm");
+        assert!(filter.matches(&park_street_platform_alert(), &directory()));
+    }
+
+    #[test]
+    fn test_matches_station_name_case_insensitively() {
+        let filter = StationFilter::parse("park street");
+        assert!(filter.matches(&park_street_platform_alert(), &directory()));
+    }
+
+    #[test]
+    fn test_other_stations_do_not_match() {
+        let filter = StationFilter::parse("Alewife,place-dwnxg");
+        assert!(!filter.matches(&park_street_platform_alert(), &directory()));
+    }
+
+    #[test]
+    fn test_unresolved_stops_match_by_id_only() {
+        let filter = StationFilter::parse("70075");
+        assert!(filter.matches(&park_street_platform_alert(), &StopDirectory::default()));
+        let filter = StationFilter::parse("Park Street");
+        assert!(!filter.matches(&park_street_platform_alert(), &StopDirectory::default()));
+    }
+
+    /// [`directory`], with the Red Line's stops up to Alewife and the Blue
+    /// Line's to Wonderland.
+    fn directory_with_routes() -> StopDirectory {
+        let route = |stops: &str| -> Stops {
+            serde_json::from_str(&format!(r#"{{"data": [{stops}], "included": []}}"#)).unwrap()
+        };
+        let mut directory = directory();
+        directory.extend_route(
+            "Red",
+            route(
+                r#"{"id": "place-alfcl", "attributes": {"name": "Alewife"},
+                    "relationships": {"parent_station": {"data": null}}},
+                   {"id": "place-pktrm", "attributes": {"name": "Park Street"},
+                    "relationships": {"parent_station": {"data": null}}}"#,
+            ),
+        );
+        directory.extend_route(
+            "Blue",
+            route(
+                r#"{"id": "place-wondl", "attributes": {"name": "Wonderland"},
+                    "relationships": {"parent_station": {"data": null}}}"#,
+            ),
+        );
+        directory
+    }
+
+    #[test]
+    fn test_alert_without_stops_matches_on_a_route_serving_the_stations() {
+        let filter = StationFilter::parse("Alewife");
+        let directory = directory_with_routes();
+        assert!(filter.matches(&Alert::builder().route("Red").build(), &directory));
+        // A route whose stops weren't looked up may serve them.
+        assert!(filter.matches(&Alert::builder().route("Orange").build(), &directory));
+    }
+
+    #[test]
+    fn test_line_wide_alert_on_another_line_does_not_match() {
+        let filter = StationFilter::parse("Alewife");
+        let blue = Alert::builder().route("Blue").build();
+        assert!(!filter.matches(&blue, &directory_with_routes()));
+    }
+}
@@ -0,0 +1,704 @@
+//! Outlook calendars through Microsoft Graph, for calendar IDs configured with
+//! `"backend": "outlook"` or written as `outlook:<id>`. The app signs in as
+//! itself with a client secret and writes to the configured user's calendars.
+//!
+//! What Google keeps in private extended properties goes in single-value
+//! extended properties here, named in a property set of the app's own.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use log::{debug, info};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::calendar::{
+    CalendarBackend, EventContent, EventFields, EventKey, EventTimes, ExistingEvent, PeriodId,
+    event_description, event_state_hash,
+};
+use crate::config::{GraphSettings, Secret};
+use crate::retry;
+
+const LOGIN_URL: &str = "https://login.microsoftonline.com";
+const GRAPH_API: &str = "https://graph.microsoft.com/v1.0";
+const SCOPE: &str = "https://graph.microsoft.com/.default";
+
+/// Marks a calendar ID as an Outlook one: Graph's IDs are opaque strings that
+/// could as well be bare Google IDs.
+pub const OUTLOOK_PREFIX: &str = "outlook:";
+
+/// Whether `calendar_id` names an Outlook calendar rather than a Google one.
+pub fn is_outlook_id(calendar_id: &str) -> bool {
+    calendar_id.starts_with(OUTLOOK_PREFIX)
+}
+
+/// The MAPI property set this app's extended properties are named in.
+const PROPERTY_SET: &str = "{4f4c8b7e-2d6a-4a0e-9c57-3b1e6d2a9f10}";
+
+const ALERT_ID: &str = "mbta_alert_id";
+const PERIOD: &str = "mbta_alert_period";
+const STATE_HASH: &str = "mbta_alert_state_hash";
+const AI_SUMMARY: &str = "mbta_ai_summary";
+
+/// Tokens are fetched again this long before they expire, so one doesn't
+/// lapse mid-request.
+const TOKEN_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+/// The ID Graph knows the extended property `name` by.
+fn property_id(name: &str) -> String {
+    format!("String {PROPERTY_SET} Name {name}")
+}
+
+pub struct GraphCalendar {
+    client: &'static Client,
+    /// [`LOGIN_URL`] and [`GRAPH_API`], but for a stand-in in tests.
+    login_url: String,
+    api_url: String,
+    tenant_id: String,
+    client_id: String,
+    client_secret: Secret,
+    user: String,
+    /// The last access token and when it expires.
+    token: Mutex<Option<(String, DateTime<Utc>)>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// Seconds.
+    expires_in: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventPage {
+    #[serde(default)]
+    value: Vec<GraphEvent>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphEvent {
+    id: String,
+    subject: Option<String>,
+    body: Option<GraphBody>,
+    start: Option<GraphTime>,
+    end: Option<GraphTime>,
+    #[serde(rename = "isAllDay", default)]
+    is_all_day: bool,
+    #[serde(rename = "singleValueExtendedProperties", default)]
+    properties: Vec<ExtendedProperty>,
+}
+
+/// A time as Graph gives it: listed in UTC, since that's what's asked for.
+#[derive(Debug, Deserialize)]
+struct GraphTime {
+    #[serde(rename = "dateTime")]
+    date_time: String,
+}
+
+/// An event's body, as text since that's what's asked for.
+#[derive(Debug, Deserialize)]
+struct GraphBody {
+    content: String,
+}
+
+impl GraphTime {
+    fn parse(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.date_time, "%Y-%m-%dT%H:%M:%S%.f").ok()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtendedProperty {
+    id: String,
+    value: String,
+}
+
+impl GraphEvent {
+    /// Graph may give back a property ID in a different case than it was
+    /// written in.
+    fn property(&self, name: &str) -> Option<&str> {
+        let id = property_id(name);
+        self.properties
+            .iter()
+            .find(|p| p.id.eq_ignore_ascii_case(&id))
+            .map(|p| p.value.as_str())
+    }
+
+    fn has_ended(&self, now: DateTime<Utc>) -> bool {
+        self.end
+            .as_ref()
+            .and_then(GraphTime::parse)
+            .is_some_and(|end| end.and_utc() <= now)
+    }
+
+    fn times(&self) -> Option<EventTimes> {
+        let start = self.start.as_ref()?.parse()?;
+        if self.is_all_day {
+            return Some(EventTimes::AllDay(start.date()));
+        }
+        let end = self.end.as_ref()?.parse()?;
+        Some(EventTimes::Timed(
+            start.and_utc().fixed_offset(),
+            end.and_utc().fixed_offset(),
+        ))
+    }
+
+    fn fields(&self) -> EventFields {
+        EventFields {
+            summary: self.subject.clone(),
+            // Outlook hands text bodies back with CRLF line breaks.
+            description: self
+                .body
+                .as_ref()
+                .map(|body| body.content.replace("\r\n", "\n")),
+            times: self.times(),
+        }
+    }
+}
+
+/// Asks for this app's events that haven't ended by `now`, so the listing
+/// doesn't grow with every alert ever synced. Graph compares the end's
+/// `dateTime` as it's stored, in UTC.
+fn listing_filter(now: DateTime<Utc>) -> String {
+    format!(
+        "singleValueExtendedProperties/Any(ep: ep/id eq '{}' and ep/value ne null) \
+         and end/dateTime ge '{}'",
+        property_id(ALERT_ID),
+        now.format("%Y-%m-%dT%H:%M:%S")
+    )
+}
+
+/// This app's events among `events` that haven't ended by `now`. The listing
+/// asks Graph for only those, but this doesn't rely on it.
+fn existing_events(events: &[GraphEvent], now: DateTime<Utc>) -> HashMap<EventKey, ExistingEvent> {
+    events
+        .iter()
+        .filter(|event| !event.has_ended(now))
+        .filter_map(|event| {
+            let key = EventKey {
+                alert_id: event.property(ALERT_ID)?.to_owned(),
+                period: PeriodId::stored(event.property(PERIOD)),
+            };
+            let existing = ExistingEvent {
+                event_id: event.id.clone(),
+                ai_summary: event.property(AI_SUMMARY).map(str::to_owned),
+                state_hash: event.property(STATE_HASH).map(str::to_owned),
+                fields: event.fields(),
+            };
+            Some((key, existing))
+        })
+        .collect()
+}
+
+fn graph_time(time: NaiveDateTime) -> Value {
+    json!({ "dateTime": time.format("%Y-%m-%dT%H:%M:%S").to_string(), "timeZone": "UTC" })
+}
+
+fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).expect("midnight exists")
+}
+
+fn event_body(event: EventContent<'_>) -> Value {
+    let alert = event.occurrence.alert;
+    let period = event.occurrence.period;
+    let (start, end, all_day) = match EventTimes::of(event.occurrence) {
+        EventTimes::Timed(s, e) => (s.naive_utc(), e.naive_utc(), false),
+        // End is exclusive, as in Google Calendar
+        EventTimes::AllDay(date) => (
+            midnight(date),
+            midnight(date.succ_opt().unwrap_or(date)),
+            true,
+        ),
+    };
+
+    let mut properties = vec![
+        (ALERT_ID, alert.id.clone()),
+        (PERIOD, event.occurrence.period_id().to_string()),
+        // Always recorded: without it the next sync has nothing to compare
+        // against and rewrites the event unconditionally.
+        (STATE_HASH, event_state_hash(alert, period, event.stops)),
+    ];
+    if let Some(raw) = event.ai_summary {
+        properties.push((AI_SUMMARY, raw.to_owned()));
+    }
+
+    json!({
+        "subject": event.summary,
+        "body": { "contentType": "text", "content": event_description(alert, event.stops) },
+        "start": graph_time(start),
+        "end": graph_time(end),
+        "isAllDay": all_day,
+        "showAs": "free",
+        "isReminderOn": false,
+        "singleValueExtendedProperties": properties
+            .into_iter()
+            .map(|(name, value)| json!({ "id": property_id(name), "value": value }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+impl GraphCalendar {
+    pub fn new(client: &'static Client, settings: &GraphSettings) -> Result<Self> {
+        let missing = |setting: &str, var: &str| {
+            format!(
+                "No Microsoft Graph {setting}: set calendar.graph.{setting} in the config file, or {var}"
+            )
+        };
+        Ok(Self {
+            client,
+            login_url: LOGIN_URL.to_owned(),
+            api_url: GRAPH_API.to_owned(),
+            tenant_id: settings
+                .tenant_id
+                .clone()
+                .with_context(|| missing("tenant_id", "GRAPH_TENANT_ID"))?,
+            client_id: settings
+                .client_id
+                .clone()
+                .with_context(|| missing("client_id", "GRAPH_CLIENT_ID"))?,
+            client_secret: settings
+                .client_secret
+                .clone()
+                .with_context(|| missing("client_secret", "GRAPH_CLIENT_SECRET"))?,
+            user: settings
+                .user
+                .clone()
+                .with_context(|| missing("user", "GRAPH_USER"))?,
+            token: Mutex::new(None),
+        })
+    }
+
+    /// The cached access token, or a new one once it's close to expiring.
+    async fn access_token(&self) -> Result<String> {
+        let now = Utc::now();
+        if let Some((token, expires)) = &*self.token.lock().unwrap()
+            && now + TOKEN_MARGIN < *expires
+        {
+            return Ok(token.clone());
+        }
+
+        let url = format!("{}/{}/oauth2/v2.0/token", self.login_url, self.tenant_id);
+        let req = self.client.post(&url).form(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.expose()),
+            ("scope", SCOPE),
+        ]);
+        let response = req.send().await.map_err(anyhow::Error::from);
+        let response: TokenResponse = match response {
+            Ok(response) => retry::check(response).await,
+            Err(e) => Err(e),
+        }
+        .context("Failed to get a Microsoft Graph token")?
+        .json()
+        .await?;
+
+        *self.token.lock().unwrap() = Some((
+            response.access_token.clone(),
+            now + TimeDelta::seconds(response.expires_in),
+        ));
+        Ok(response.access_token)
+    }
+
+    fn events_url(&self, calendar_id: &str) -> String {
+        let id = calendar_id
+            .strip_prefix(OUTLOOK_PREFIX)
+            .unwrap_or(calendar_id);
+        format!("{}/users/{}/calendars/{id}/events", self.api_url, self.user)
+    }
+
+    fn event_url(&self, event_id: &str) -> String {
+        format!("{}/users/{}/events/{event_id}", self.api_url, self.user)
+    }
+
+    async fn send_authenticated(&self, req: RequestBuilder) -> Result<Response> {
+        let token = self.access_token().await?;
+        let response = req
+            .bearer_auth(&token)
+            .send()
+            .await
+            .context("Failed to query Microsoft Graph")?;
+        retry::check(response).await
+    }
+}
+
+impl CalendarBackend for GraphCalendar {
+    async fn list_alert_events(
+        &self,
+        calendar_id: &str,
+    ) -> Result<HashMap<EventKey, ExistingEvent>> {
+        let now = Utc::now();
+        let filter = listing_filter(now);
+        let expand = format!(
+            "singleValueExtendedProperties($filter={})",
+            [ALERT_ID, PERIOD, STATE_HASH, AI_SUMMARY]
+                .map(|name| format!("id eq '{}'", property_id(name)))
+                .join(" or ")
+        );
+
+        debug!("Listing calendar events for {calendar_id}");
+        let mut events = Vec::new();
+        let mut req = self.client.get(self.events_url(calendar_id)).query(&[
+            ("$filter", filter.as_str()),
+            ("$expand", expand.as_str()),
+            ("$select", "subject,body,start,end,isAllDay"),
+            ("$top", "100"),
+        ]);
+        loop {
+            // Refreshed per page, like Google's listing: a long pagination can
+            // outlive a token fetched once before the loop.
+            let page: EventPage = self
+                .send_authenticated(req.header(
+                    "Prefer",
+                    "outlook.timezone=\"UTC\", outlook.body-content-type=\"text\"",
+                ))
+                .await?
+                .json()
+                .await?;
+            events.extend(page.value);
+
+            match page.next_link {
+                // The link carries the query along with the page's position.
+                Some(next) => req = self.client.get(next),
+                None => break,
+            }
+        }
+
+        let events = existing_events(&events, now);
+        info!("Listed {} calendar events for {calendar_id}", events.len());
+        Ok(events)
+    }
+
+    async fn create_event(&self, calendar_id: &str, event: EventContent<'_>) -> Result<()> {
+        let req = self
+            .client
+            .post(self.events_url(calendar_id))
+            .json(&event_body(event));
+        self.send_authenticated(req).await?;
+        info!("Created calendar event for {}", event.occurrence.key());
+        Ok(())
+    }
+
+    async fn update_event(
+        &self,
+        _calendar_id: &str,
+        event_id: &str,
+        event: EventContent<'_>,
+    ) -> Result<()> {
+        let req = self
+            .client
+            .patch(self.event_url(event_id))
+            .json(&event_body(event));
+        self.send_authenticated(req).await?;
+        info!(
+            "Updated calendar event {event_id} for {}",
+            event.occurrence.key()
+        );
+        Ok(())
+    }
+
+    async fn delete_event(&self, _calendar_id: &str, event_id: &str) -> Result<()> {
+        let req = self.client.delete(self.event_url(event_id));
+        retry::ignore_gone(self.send_authenticated(req).await, event_id)?;
+        info!("Deleted calendar event {event_id}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use axum::Router;
+    use axum::http::{Method, StatusCode, Uri, header};
+    use axum::response::IntoResponse;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::calendar::{CalendarConfig, Occurrence, SyncContext, SyncLimits, sync_calendar};
+    use crate::report::CalendarReport;
+    use crate::stops::StopDirectory;
+    use crate::types::Alert;
+
+    const EVENTS: &str = "/users/alerts@example.com/calendars/red/events";
+
+    /// What a Microsoft Graph stand-in lists, how it answers, and what it was
+    /// asked.
+    #[derive(Debug, Default)]
+    struct Graph {
+        events: Vec<Value>,
+        /// Statuses to answer writes with, in turn, before succeeding.
+        failures: Vec<StatusCode>,
+        requests: Vec<(Method, String)>,
+    }
+
+    /// Just enough of Microsoft Graph for a sync: hands out tokens, lists
+    /// `events`, and takes any write.
+    async fn stand_in(
+        axum::extract::State(graph): axum::extract::State<Arc<Mutex<Graph>>>,
+        method: Method,
+        uri: Uri,
+    ) -> axum::response::Response {
+        if uri.path().ends_with("/oauth2/v2.0/token") {
+            return axum::Json(json!({ "access_token": "token", "expires_in": 3600 }))
+                .into_response();
+        }
+        let mut graph = graph.lock().unwrap();
+        graph.requests.push((method.clone(), uri.path().to_owned()));
+        if method == Method::GET {
+            return axum::Json(json!({ "value": graph.events })).into_response();
+        }
+        if let Some(status) = graph.failures.pop() {
+            return (status, [(header::RETRY_AFTER, "0")]).into_response();
+        }
+        match method {
+            Method::POST => StatusCode::CREATED.into_response(),
+            _ => StatusCode::NO_CONTENT.into_response(),
+        }
+    }
+
+    /// A client of a stand-in for Graph, and the stand-in's state.
+    async fn serve_stand_in() -> (GraphCalendar, Arc<Mutex<Graph>>) {
+        let graph = Arc::new(Mutex::new(Graph::default()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let router = Router::new().fallback(stand_in).with_state(graph.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let calendar = GraphCalendar {
+            client: Box::leak(Box::new(Client::new())),
+            login_url: base.clone(),
+            api_url: base,
+            tenant_id: "contoso".to_owned(),
+            client_id: "app".to_owned(),
+            client_secret: toml::from_str::<GraphSettings>("client_secret = \"secret\"")
+                .unwrap()
+                .client_secret
+                .unwrap(),
+            user: "alerts@example.com".to_owned(),
+            token: Mutex::new(None),
+        };
+        (calendar, graph)
+    }
+
+    async fn sync(calendar: &GraphCalendar, alerts: &[&Alert]) -> Result<CalendarReport> {
+        let id = "outlook:red";
+        let config = CalendarConfig::single(id.to_owned());
+        let stops = StopDirectory::default();
+        let limits = SyncLimits::default();
+        let cx = SyncContext::new(&config, None, &stops, &limits);
+        sync_calendar(calendar, id, alerts, &cx).await
+    }
+
+    fn shuttle() -> Alert {
+        Alert::builder()
+            .id("1")
+            .header("Shuttle buses replace Red Line service.")
+            .route("Red")
+            .period(
+                Some("2099-06-01T10:00:00-04:00"),
+                Some("2099-06-01T15:00:00-04:00"),
+            )
+            .build()
+    }
+
+    fn body(alert: &Alert, ai_summary: Option<&str>) -> Value {
+        event_body(EventContent {
+            occurrence: Occurrence { alert, period: 0 },
+            summary: "Red Line: Shuttles",
+            ai_summary,
+            stops: &StopDirectory::default(),
+        })
+    }
+
+    fn property<'a>(body: &'a Value, name: &str) -> Option<&'a str> {
+        body["singleValueExtendedProperties"]
+            .as_array()?
+            .iter()
+            .find(|p| p["id"] == property_id(name))?["value"]
+            .as_str()
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_create_is_retried() -> Result<()> {
+        let (calendar, graph) = serve_stand_in().await;
+        graph.lock().unwrap().failures = vec![StatusCode::TOO_MANY_REQUESTS];
+
+        let report = sync(&calendar, &[&shuttle()]).await?;
+        assert_eq!(report.created.count(), 1);
+        assert!(report.errors.is_empty());
+        let posts = |graph: &Graph| {
+            graph
+                .requests
+                .iter()
+                .filter(|(method, path)| *method == Method::POST && path == EVENTS)
+                .count()
+        };
+        assert_eq!(posts(&graph.lock().unwrap()), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_create_is_not_repeated() -> Result<()> {
+        let (calendar, graph) = serve_stand_in().await;
+        // Graph may have written the event before failing, so it's left to
+        // the next sync, which lists what's there first.
+        graph.lock().unwrap().failures = vec![StatusCode::INTERNAL_SERVER_ERROR];
+
+        let report = sync(&calendar, &[&shuttle()]).await?;
+        assert_eq!(report.failed.count(), 1);
+        assert!(report.errors[0].contains("500"), "{}", report.errors[0]);
+        assert_eq!(graph.lock().unwrap().requests.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_event_already_deleted_counts_as_deleted() -> Result<()> {
+        let (calendar, graph) = serve_stand_in().await;
+        {
+            let mut graph = graph.lock().unwrap();
+            graph.events = vec![json!({
+                "id": "AAMk-cleared",
+                "subject": "Red Line: Delays",
+                "end": { "dateTime": "2099-06-01T19:00:00.0000000" },
+                "singleValueExtendedProperties": [
+                    { "id": property_id(ALERT_ID), "value": "2" },
+                    { "id": property_id(PERIOD), "value": "0" },
+                ],
+            })];
+            // An earlier try at the delete went through.
+            graph.failures = vec![StatusCode::GONE];
+        }
+
+        let report = sync(&calendar, &[]).await?;
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.deleted.count(), 1);
+        assert_eq!(report.deleted.events[0].alert_id, "2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_listing_filter_skips_ended_events() {
+        let now = "2025-10-14T16:00:00Z".parse().unwrap();
+        let filter = listing_filter(now);
+        assert!(
+            filter.starts_with(&format!(
+                "singleValueExtendedProperties/Any(ep: ep/id eq '{}'",
+                property_id(ALERT_ID)
+            )),
+            "{filter}"
+        );
+        assert!(
+            filter.ends_with(" and end/dateTime ge '2025-10-14T16:00:00'"),
+            "{filter}"
+        );
+    }
+
+    #[test]
+    fn test_is_outlook_id() {
+        assert!(is_outlook_id("outlook:AAMkAGI2"));
+        assert!(!is_outlook_id("AAMkAGI2"));
+        assert!(!is_outlook_id("https://dav.example.com/cal/"));
+    }
+
+    #[test]
+    fn test_event_body_is_in_utc_and_records_its_key() {
+        let alert = Alert::builder()
+            .id("123")
+            .route("Red")
+            .period(
+                Some("2025-10-24T20:45:00-04:00"),
+                Some("2025-10-27T02:30:00-04:00"),
+            )
+            .build();
+        let body = body(&alert, Some("Shuttles"));
+
+        assert_eq!(body["subject"], "Red Line: Shuttles");
+        assert_eq!(body["isAllDay"], false);
+        assert_eq!(body["start"]["dateTime"], "2025-10-25T00:45:00");
+        assert_eq!(body["end"]["dateTime"], "2025-10-27T06:30:00");
+        assert_eq!(body["start"]["timeZone"], "UTC");
+        assert_eq!(property(&body, ALERT_ID), Some("123"));
+        assert_eq!(property(&body, PERIOD), Some("20251025T004500Z"));
+        assert_eq!(
+            property(&body, STATE_HASH),
+            Some(event_state_hash(&alert, 0, &StopDirectory::default()).as_str())
+        );
+        assert_eq!(property(&body, AI_SUMMARY), Some("Shuttles"));
+    }
+
+    #[test]
+    fn test_open_ended_event_body_is_all_day() {
+        let alert = Alert::builder()
+            .id("123")
+            .route("Red")
+            .period(Some("2025-10-24T20:45:00-04:00"), None)
+            .build();
+        let body = body(&alert, None);
+
+        assert_eq!(body["isAllDay"], true);
+        assert_eq!(body["start"]["dateTime"], "2025-10-24T00:00:00");
+        assert_eq!(body["end"]["dateTime"], "2025-10-25T00:00:00");
+        assert_eq!(property(&body, AI_SUMMARY), None);
+    }
+
+    #[test]
+    fn test_existing_events_reads_fields_and_skips_ended_and_foreign_events() -> Result<()> {
+        let page: EventPage = serde_json::from_value(json!({
+            "value": [
+                {
+                    "id": "current",
+                    "subject": "Red Line: Shuttles",
+                    "body": { "contentType": "text", "content": "Shuttle buses\r\n\r\nCause: Maintenance" },
+                    "start": { "dateTime": "2025-10-25T00:45:00.0000000", "timeZone": "UTC" },
+                    "end": { "dateTime": "2025-10-27T06:30:00.0000000", "timeZone": "UTC" },
+                    "singleValueExtendedProperties": [
+                        { "id": property_id(ALERT_ID).to_lowercase(), "value": "123" },
+                        { "id": property_id(PERIOD), "value": "20251025T004500Z" },
+                        { "id": property_id(STATE_HASH), "value": "42" }
+                    ]
+                },
+                {
+                    "id": "ended",
+                    "end": { "dateTime": "2025-10-20T06:30:00.0000000", "timeZone": "UTC" },
+                    "singleValueExtendedProperties": [
+                        { "id": property_id(ALERT_ID), "value": "456" }
+                    ]
+                },
+                {
+                    "id": "foreign",
+                    "end": { "dateTime": "2025-10-27T06:30:00.0000000", "timeZone": "UTC" }
+                }
+            ]
+        }))?;
+        let now = DateTime::parse_from_rfc3339("2025-10-24T12:00:00Z")?.to_utc();
+
+        let events = existing_events(&page.value, now);
+        assert_eq!(events.len(), 1);
+        let key = EventKey {
+            alert_id: "123".to_owned(),
+            period: PeriodId::Start(DateTime::parse_from_rfc3339("2025-10-25T00:45:00Z")?.to_utc()),
+        };
+        assert_eq!(
+            events[&key],
+            ExistingEvent {
+                event_id: "current".to_owned(),
+                ai_summary: None,
+                state_hash: Some("42".to_owned()),
+                fields: EventFields {
+                    summary: Some("Red Line: Shuttles".to_owned()),
+                    description: Some("Shuttle buses\n\nCause: Maintenance".to_owned()),
+                    times: Some(EventTimes::Timed(
+                        DateTime::parse_from_rfc3339("2025-10-24T20:45:00-04:00")?,
+                        DateTime::parse_from_rfc3339("2025-10-27T02:30:00-04:00")?,
+                    )),
+                },
+            }
+        );
+        Ok(())
+    }
+}
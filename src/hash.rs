@@ -0,0 +1,64 @@
+//! FNV-1a 64-bit hashing, for hashes that are kept between runs: unlike
+//! `DefaultHasher`'s, they're the same across platforms and Rust versions.
+
+const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const PRIME: u64 = 0x100000001b3;
+
+/// A hash over a sequence of strings.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(OFFSET_BASIS)
+    }
+}
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.0 ^= byte as u64;
+        self.0 = self.0.wrapping_mul(PRIME);
+    }
+
+    /// Hashes `s`, then a separator, so that "ab" then "c" hashes apart from
+    /// "a" then "bc".
+    pub fn feed(&mut self, s: &str) {
+        for byte in s.bytes() {
+            self.byte(byte);
+        }
+        self.byte(0xff);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hash(parts: &[&str]) -> u64 {
+        let mut hash = Fnv1a::new();
+        for part in parts {
+            hash.feed(part);
+        }
+        hash.finish()
+    }
+
+    #[test]
+    fn test_hash_is_stable() {
+        // Saved hashes are compared with new ones, so this mustn't change.
+        assert_eq!(hash(&[]), OFFSET_BASIS);
+        assert_eq!(hash(&["a"]), 0x089bc907b544c769);
+    }
+
+    #[test]
+    fn test_parts_are_separated() {
+        assert_ne!(hash(&["ab", "c"]), hash(&["a", "bc"]));
+    }
+}
@@ -0,0 +1,516 @@
+//! Alerts as iCalendar (.ics) feeds, one per line and branch, for subscribing
+//! from Apple Calendar or Outlook without a Google service account.
+//!
+//! Events carry what a synced Google Calendar event does: the same title,
+//! description and times, routed to the same line's calendar.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, Utc};
+use log::info;
+
+use crate::accessibility::accessibility_filter;
+use crate::cache::Cache;
+use crate::calendar::{
+    CalendarConfig, EventContent, EventFields, EventTimes, Occurrence, alert_occurrences,
+    alerts_by_calendar, event_description, event_state_hash, line_prefix_for_alert,
+};
+use crate::filter::{EffectFilter, StationFilter};
+use crate::mbta::MbtaClient;
+use crate::stops::StopDirectory;
+use crate::summary::TitleCache;
+use crate::types::{Alert, Alerts};
+use crate::{APP_NAME, Branch, Line, alerts_to_sync};
+
+/// An event's revision, for consumers that diff them.
+pub const STATE_HASH_PROPERTY: &str = "X-MBTA-STATE-HASH";
+/// What a CalDAV event records for the next sync to read back: the alert and
+/// period it covers, and Bedrock's title.
+pub const ALERT_ID_PROPERTY: &str = "X-MBTA-ALERT-ID";
+pub const PERIOD_PROPERTY: &str = "X-MBTA-ALERT-PERIOD";
+pub const AI_SUMMARY_PROPERTY: &str = "X-MBTA-AI-SUMMARY";
+
+/// The feed for elevator, escalator and access outages.
+const ACCESSIBILITY_FEED: &str = "accessibility";
+/// The feed for alerts on no line we know.
+const OTHER_FEED: &str = "other";
+
+/// A line or branch key as a feed name: `CommuterRail` is `commuter-rail`,
+/// `Green-E` is `green-e`.
+fn feed_name(key: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in key.chars().enumerate() {
+        if c.is_uppercase() && i > 0 && !name.ends_with('-') {
+            name.push('-');
+        }
+        name.extend(c.to_lowercase());
+    }
+    name
+}
+
+/// One feed per line and branch, plus accessibility and everything else,
+/// routed the way per-line Google calendars are.
+pub fn feed_config() -> CalendarConfig {
+    CalendarConfig::PerLine {
+        map: Line::ALL
+            .into_iter()
+            .map(|line| (line, feed_name(line.name())))
+            .collect(),
+        branches: Branch::ALL
+            .into_iter()
+            .map(|branch| (branch, feed_name(branch.name())))
+            .collect(),
+        accessibility: Some(ACCESSIBILITY_FEED.to_owned()),
+        default: OTHER_FEED.to_owned(),
+        filters: HashMap::from([(ACCESSIBILITY_FEED.to_owned(), accessibility_filter())]),
+    }
+}
+
+/// What a feed is called in a calendar app: "Red Line Ashmont Branch alerts".
+fn feed_title(name: &str) -> String {
+    let lines = Line::ALL
+        .into_iter()
+        .map(|line| (line.name(), line.full_name()));
+    let branches = Branch::ALL
+        .into_iter()
+        .map(|branch| (branch.name(), branch.full_name()));
+    let title = lines
+        .chain(branches)
+        .find(|(key, _)| feed_name(key) == name)
+        .map_or(
+            match name {
+                ACCESSIBILITY_FEED => "Accessibility",
+                _ => "MBTA",
+            },
+            |(_, full_name)| full_name,
+        );
+    format!("{title} alerts")
+}
+
+/// A rendered feed, named for its line: `red`, `green-e`, `accessibility`.
+#[derive(Debug)]
+pub struct IcsFeed {
+    pub name: String,
+    pub ics: String,
+}
+
+impl IcsFeed {
+    /// Writes the feed to `<dir>/<name>.ics`.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(format!("{}.ics", self.name));
+        fs::write(&path, &self.ics).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Renders `alerts` as every feed in [`feed_config`], in name order. Each
+/// feed is rendered, even an empty one, so a subscription to a quiet line
+/// finds an empty calendar rather than nothing.
+pub async fn ics_feeds(
+    mbta: &MbtaClient,
+    alerts: &Alerts,
+    effects: &EffectFilter,
+    stations: &StationFilter,
+    titles: &TitleCache<'_>,
+    cache: &Cache,
+) -> Result<Vec<IcsFeed>> {
+    let config = feed_config();
+    let (alerts, stops) =
+        alerts_to_sync(mbta, alerts, &config.filters(effects), stations, cache).await?;
+
+    let mut by_feed = alerts_by_calendar(&alerts, effects, &config);
+    by_feed.sort_by_key(|(name, _)| *name);
+
+    let now = Utc::now();
+    let mut feeds = Vec::with_capacity(by_feed.len());
+    for (name, feed_alerts) in by_feed {
+        let mut events = Vec::new();
+        for alert in feed_alerts {
+            let prefix = line_prefix_for_alert(alert, name, &config);
+            let title = titles.summary(alert, prefix, &stops).await.display;
+            for occurrence in alert_occurrences(alert, now) {
+                events.push(vevent(occurrence, &title, &stops));
+            }
+        }
+        feeds.push(IcsFeed {
+            name: name.to_owned(),
+            ics: vcalendar(&feed_title(name), &events),
+        });
+    }
+    info!("Rendered {} iCalendar feeds", feeds.len());
+    Ok(feeds)
+}
+
+/// Escapes a TEXT value (RFC 5545 §3.3.11).
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Reverses [`escape`].
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Folds a content line to 75 octets a line (RFC 5545 §3.1), never splitting
+/// a character.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+fn utc(time: DateTime<impl chrono::TimeZone>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// One occurrence as a VEVENT's content lines.
+///
+/// The UID is the alert ID and period, like a synced event's key, so an
+/// update replaces the event rather than adding another. SEQUENCE must be an
+/// integer that only grows, which the state hash isn't, so it counts the
+/// minutes from the alert's creation to its last update; the hash rides along
+/// as `X-MBTA-STATE-HASH` for consumers that diff revisions.
+fn vevent(occurrence: Occurrence<'_>, summary: &str, stops: &StopDirectory) -> Vec<String> {
+    let alert: &Alert = occurrence.alert;
+    let attributes = &alert.attributes;
+    let sequence = (attributes.updated_at - attributes.created_at)
+        .num_minutes()
+        .clamp(0, i32::MAX.into());
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_owned(),
+        format!("UID:{}-{}@{APP_NAME}", alert.id, occurrence.period_id()),
+        format!("DTSTAMP:{}", utc(attributes.updated_at)),
+        format!("SEQUENCE:{sequence}"),
+        format!(
+            "{STATE_HASH_PROPERTY}:{}",
+            event_state_hash(alert, occurrence.period, stops)
+        ),
+    ];
+    match EventTimes::of(occurrence) {
+        EventTimes::Timed(start, end) => {
+            lines.push(format!("DTSTART:{}", utc(start)));
+            lines.push(format!("DTEND:{}", utc(end)));
+        }
+        EventTimes::AllDay(date) => {
+            // DTEND is exclusive, as in Google Calendar.
+            let next = date + Days::new(1);
+            lines.push(format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")));
+            lines.push(format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")));
+        }
+    }
+    lines.push(format!("SUMMARY:{}", escape(summary)));
+    lines.push(format!(
+        "DESCRIPTION:{}",
+        escape(&event_description(alert, stops))
+    ));
+    if let Some(url) = &attributes.url {
+        lines.push(format!("URL:{url}"));
+    }
+    lines.push("TRANSP:TRANSPARENT".to_owned());
+    lines.push("END:VEVENT".to_owned());
+    lines
+}
+
+/// A whole calendar around `events`, with CRLF line endings.
+fn vcalendar(title: &str, events: &[Vec<String>]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        format!("PRODID:-//{APP_NAME}//MBTA alerts//EN"),
+        "CALSCALE:GREGORIAN".to_owned(),
+        "METHOD:PUBLISH".to_owned(),
+        format!("X-WR-CALNAME:{}", escape(title)),
+        "X-WR-TIMEZONE:America/New_York".to_owned(),
+    ];
+    lines.extend(events.iter().flatten().cloned());
+    lines.push("END:VCALENDAR".to_owned());
+    content(&lines)
+}
+
+/// Content lines as iCalendar text: folded, each ending in CRLF.
+fn content(lines: &[String]) -> String {
+    let mut ics = lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+/// One synced event as a CalDAV calendar object resource (RFC 4791 §4.1): a
+/// VCALENDAR holding just its VEVENT, which also records the alert, period and
+/// AI title for [`event_properties`] to read back.
+pub fn calendar_object(event: EventContent<'_>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        format!("PRODID:-//{APP_NAME}//MBTA alerts//EN"),
+    ];
+    let mut vevent = vevent(event.occurrence, event.summary, event.stops);
+    let end = vevent.pop();
+    vevent.push(format!(
+        "{ALERT_ID_PROPERTY}:{}",
+        escape(&event.occurrence.alert.id)
+    ));
+    vevent.push(format!(
+        "{PERIOD_PROPERTY}:{}",
+        event.occurrence.period_id()
+    ));
+    if let Some(ai_summary) = event.ai_summary {
+        vevent.push(format!("{AI_SUMMARY_PROPERTY}:{}", escape(ai_summary)));
+    }
+    vevent.extend(end);
+    lines.extend(vevent);
+    lines.push("END:VCALENDAR".to_owned());
+    content(&lines)
+}
+
+/// The properties of the first VEVENT in `ics` by name, TEXT values
+/// unescaped. Parameters are dropped, and a repeated property keeps its first
+/// value.
+pub fn event_properties(ics: &str) -> HashMap<String, String> {
+    let unfolded = ics
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut properties = HashMap::new();
+    let mut in_event = false;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.split(';').next().unwrap_or(name).to_ascii_uppercase();
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => in_event = true,
+            ("END", "VEVENT") => break,
+            _ if in_event => {
+                properties.entry(name).or_insert_with(|| unescape(value));
+            }
+            _ => {}
+        }
+    }
+    properties
+}
+
+/// What an event's `properties` say it shows. Only times in UTC or all-day
+/// dates, the ones [`calendar_object`] writes, are read back.
+pub fn event_fields(properties: &HashMap<String, String>) -> EventFields {
+    let utc = |name: &str| {
+        let time = properties.get(name)?;
+        let time = NaiveDateTime::parse_from_str(time, "%Y%m%dT%H%M%SZ").ok()?;
+        Some(time.and_utc().fixed_offset())
+    };
+    let times = match (utc("DTSTART"), utc("DTEND")) {
+        (Some(start), Some(end)) => Some(EventTimes::Timed(start, end)),
+        _ => properties
+            .get("DTSTART")
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+            .map(EventTimes::AllDay),
+    };
+    EventFields {
+        summary: properties.get("SUMMARY").cloned(),
+        description: properties.get("DESCRIPTION").cloned(),
+        times,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_feed_name() {
+        assert_eq!(feed_name("Red"), "red");
+        assert_eq!(feed_name("CommuterRail"), "commuter-rail");
+        assert_eq!(feed_name("Green-E"), "green-e");
+        assert_eq!(feed_name("Red-Ashmont"), "red-ashmont");
+    }
+
+    #[test]
+    fn test_feed_title() {
+        assert_eq!(feed_title("green-e"), "Green Line E Branch alerts");
+        assert_eq!(feed_title("commuter-rail"), "Commuter Rail alerts");
+        assert_eq!(feed_title("accessibility"), "Accessibility alerts");
+        assert_eq!(feed_title("other"), "MBTA alerts");
+    }
+
+    #[test]
+    fn test_feeds_follow_calendar_routing() {
+        let branch = Alert::builder().id("1").route("Green-E").build();
+        let elevator = Alert::builder()
+            .id("2")
+            .route("Red")
+            .effect("ELEVATOR_CLOSURE")
+            .build();
+        let config = feed_config();
+        let by_feed: HashMap<&str, Vec<&str>> =
+            alerts_by_calendar(&[&branch, &elevator], &EffectFilter::default(), &config)
+                .into_iter()
+                .map(|(name, alerts)| (name, alerts.iter().map(|a| a.id.as_str()).collect()))
+                .collect();
+
+        assert_eq!(by_feed["green-e"], vec!["1"]);
+        assert_eq!(by_feed["accessibility"], vec!["2"]);
+        assert!(by_feed["red"].is_empty());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a, b; c\\d\ne"), "a\\, b\\; c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_fold_long_lines() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert!(parts.iter().all(|part| part.len() <= 75));
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ')));
+        assert_eq!(parts.concat().replace(' ', ""), line.replace(' ', ""));
+    }
+
+    #[test]
+    fn test_vevent() {
+        let alert = Alert::builder()
+            .id("123")
+            .header("Shuttle buses replace Red Line service.")
+            .route("Red")
+            .url("https://mbta.com/alerts/red")
+            .period(
+                Some("2025-10-24T20:45:00-04:00"),
+                Some("2025-10-27T02:30:00-04:00"),
+            )
+            .period(Some("2025-11-01T20:45:00-04:00"), None)
+            .build();
+        let stops = StopDirectory::default();
+        let event = |period| {
+            vevent(
+                Occurrence {
+                    alert: &alert,
+                    period,
+                },
+                "Red Line: Shuttles",
+                &stops,
+            )
+        };
+
+        let timed = event(0);
+        assert!(timed.contains(&"UID:123-20251025T004500Z@mbtalerts".to_owned()));
+        assert!(timed.contains(&"DTSTART:20251025T004500Z".to_owned()));
+        assert!(timed.contains(&"DTEND:20251027T063000Z".to_owned()));
+        assert!(timed.contains(&"SUMMARY:Red Line: Shuttles".to_owned()));
+        assert!(timed.contains(&"SEQUENCE:0".to_owned()));
+        assert!(timed.contains(&format!(
+            "X-MBTA-STATE-HASH:{}",
+            event_state_hash(&alert, 0, &StopDirectory::default())
+        )));
+
+        let all_day = event(1);
+        assert!(all_day.contains(&"UID:123-20251102T004500Z@mbtalerts".to_owned()));
+        assert!(all_day.contains(&"DTSTART;VALUE=DATE:20251101".to_owned()));
+        assert!(all_day.contains(&"DTEND;VALUE=DATE:20251102".to_owned()));
+    }
+
+    #[test]
+    fn test_unescape_reverses_escape() {
+        let text = "a, b; c\\d\ne";
+        assert_eq!(unescape(&escape(text)), text);
+    }
+
+    #[test]
+    fn test_calendar_object_round_trips_sync_properties() {
+        let alert = Alert::builder()
+            .id("123")
+            .header("Shuttle buses replace Red Line service.")
+            .route("Red")
+            .period(Some("2025-10-24T20:45:00-04:00"), None)
+            .build();
+        let stops = StopDirectory::default();
+        let ai_summary = "Shuttles, Alewife to Harvard; ".repeat(4);
+        let ics = calendar_object(EventContent {
+            occurrence: Occurrence {
+                alert: &alert,
+                period: 0,
+            },
+            summary: "Red Line: Shuttles",
+            ai_summary: Some(&ai_summary),
+            stops: &stops,
+        });
+        assert!(!ics.contains("METHOD"));
+
+        let properties = event_properties(&ics);
+        assert_eq!(properties[ALERT_ID_PROPERTY], "123");
+        assert_eq!(properties[PERIOD_PROPERTY], "20251025T004500Z");
+        assert_eq!(properties[AI_SUMMARY_PROPERTY], ai_summary);
+        assert_eq!(
+            properties[STATE_HASH_PROPERTY],
+            event_state_hash(&alert, 0, &StopDirectory::default())
+        );
+        assert_eq!(properties["DTSTART"], "20251024");
+    }
+
+    #[test]
+    fn test_event_fields_read_back_what_was_written() {
+        let stops = StopDirectory::default();
+        // Timed, then all day.
+        for end in [Some("2025-10-27T02:30:00-04:00"), None] {
+            let alert = Alert::builder()
+                .id("123")
+                .header("Shuttle buses replace Red Line service; expect delays.")
+                .route("Red")
+                .period(Some("2025-10-24T20:45:00-04:00"), end)
+                .build();
+            let event = EventContent {
+                occurrence: Occurrence {
+                    alert: &alert,
+                    period: 0,
+                },
+                summary: "Red Line: Shuttles, Alewife to Harvard",
+                ai_summary: None,
+                stops: &stops,
+            };
+            let properties = event_properties(&calendar_object(event));
+            assert_eq!(event_fields(&properties), EventFields::of(event));
+        }
+    }
+
+    #[test]
+    fn test_vcalendar_uses_crlf() {
+        let ics = vcalendar("Red Line alerts", &[]);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains("X-WR-CALNAME:Red Line alerts\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+}
@@ -1,20 +1,56 @@
-use jluszcz_rust_utils::cache::CacheMode;
 use jluszcz_rust_utils::lambda;
 use lambda_runtime::LambdaEvent;
+use log::info;
 use mbtalerts::APP_NAME;
-use mbtalerts::calendar::{CalendarClient, sync_alerts};
-use serde_json::{Value, json};
+use mbtalerts::calendar::{dry_run, sync_if_modified};
+use mbtalerts::config::Config;
+use mbtalerts::mbta::MbtaClient;
+use serde::Deserialize;
+use serde_json::Value;
 
-#[tokio::main]
-async fn main() -> Result<(), lambda_runtime::Error> {
-    lambda::run(APP_NAME, module_path!(), false, handler).await
+/// What an invocation asks for. A scheduled event carries none of these, so
+/// it syncs.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SyncRequest {
+    /// Returns what the sync would do to each calendar instead of doing it.
+    dry_run: bool,
 }
 
-async fn handler(_event: LambdaEvent<Value>) -> Result<Value, lambda_runtime::Error> {
-    let alerts = mbtalerts::alerts(CacheMode::Disabled).await?;
+fn main() -> Result<(), lambda_runtime::Error> {
+    // Loaded once per cold start, so a bad config fails the init rather than
+    // every invocation.
+    let config: &'static Config = Box::leak(Box::new(Config::load(None)?));
+    // SAFETY: the runtime, and with it every other thread, starts below.
+    unsafe { config.export_model_id() };
 
-    let calendar = CalendarClient::from_env().await?;
-    sync_alerts(&alerts, &calendar).await?;
+    tokio::runtime::Runtime::new()?.block_on(lambda::run(
+        APP_NAME,
+        module_path!(),
+        false,
+        |event| handler(event, config),
+    ))
+}
+
+async fn handler(
+    event: LambdaEvent<SyncRequest>,
+    config: &Config,
+) -> Result<Value, lambda_runtime::Error> {
+    let mbta = MbtaClient::new(config)?;
+    if event.payload.dry_run {
+        let plans = dry_run(&mbta, config).await?;
+        for plan in &plans {
+            info!("{plan}");
+        }
+        return Ok(serde_json::to_value(plans)?);
+    }
+    let report = sync_if_modified(&mbta, config, false).await?;
+    info!("{report}");
 
-    Ok(json!({}))
+    // Failing the invocation is what alarms and retries go by, so the report
+    // goes with the failure.
+    if report.has_errors() {
+        return Err(serde_json::to_string(&report)?.into());
+    }
+    Ok(serde_json::to_value(report)?)
 }
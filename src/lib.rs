@@ -1,13 +1,31 @@
 use anyhow::Result;
-use jluszcz_rust_utils::cache::{CacheMode, dated_cache_path, try_cached_query};
-use log::{trace, warn};
+use log::{debug, trace, warn};
 
-use crate::mbta::query_subway_alerts;
-use crate::types::{Alert, Alerts};
+use crate::cache::{Cache, REFERENCE_MAX_AGE};
+use crate::filter::{EffectFilter, StationFilter};
+use crate::mbta::{AlertQuery, Fetched, MbtaClient, Validators};
+use crate::stops::StopDirectory;
+use crate::types::{Alert, Alerts, InformedEntity, RouteType};
 
+pub mod accessibility;
+pub mod agenda;
 pub mod ai;
+pub mod cache;
+pub mod caldav;
 pub mod calendar;
+pub mod config;
+pub mod feed_state;
+pub mod filter;
+pub mod graph;
+pub mod hash;
+pub mod ics;
 pub mod mbta;
+pub mod output;
+pub mod report;
+pub mod retry;
+pub mod serve;
+pub mod stops;
+pub mod stream;
 pub mod summary;
 pub mod types;
 
@@ -19,10 +37,26 @@ pub enum Line {
     Orange,
     Blue,
     Green,
+    Mattapan,
+    Silver,
+    CommuterRail,
+    Ferry,
+    /// Every bus route other than the Silver Line.
+    Bus,
 }
 
 impl Line {
-    pub const ALL: [Line; 4] = [Line::Red, Line::Orange, Line::Blue, Line::Green];
+    pub const ALL: [Line; 9] = [
+        Line::Red,
+        Line::Orange,
+        Line::Blue,
+        Line::Green,
+        Line::Mattapan,
+        Line::Silver,
+        Line::CommuterRail,
+        Line::Ferry,
+        Line::Bus,
+    ];
 
     /// Canonical short name, used as the key in GOOGLE_CALENDAR_IDS.
     pub fn name(self) -> &'static str {
@@ -31,6 +65,11 @@ impl Line {
             Line::Orange => "Orange",
             Line::Blue => "Blue",
             Line::Green => "Green",
+            Line::Mattapan => "Mattapan",
+            Line::Silver => "Silver",
+            Line::CommuterRail => "CommuterRail",
+            Line::Ferry => "Ferry",
+            Line::Bus => "Bus",
         }
     }
 
@@ -45,52 +84,373 @@ impl Line {
             Line::Orange => "Orange Line",
             Line::Blue => "Blue Line",
             Line::Green => "Green Line",
+            Line::Mattapan => "Mattapan Trolley",
+            Line::Silver => "Silver Line",
+            Line::CommuterRail => "Commuter Rail",
+            Line::Ferry => "Ferry",
+            Line::Bus => "Bus",
+        }
+    }
+
+    /// What a GTFS `direction_id` means on this line. Rapid transit names
+    /// compass directions; everything else runs outbound from and inbound to
+    /// downtown.
+    pub fn direction_name(self, direction_id: u8) -> &'static str {
+        match (self, direction_id) {
+            (Line::Red | Line::Orange, 0) => "Southbound",
+            (Line::Red | Line::Orange, _) => "Northbound",
+            (Line::Blue | Line::Green, 0) => "Westbound",
+            (Line::Blue | Line::Green, _) => "Eastbound",
+            (_, 0) => "Outbound",
+            (_, _) => "Inbound",
+        }
+    }
+
+    /// The lines an entity naming only a route type ("all subway") covers.
+    pub fn for_route_type(route_type: RouteType) -> &'static [Line] {
+        match route_type {
+            RouteType::LightRail => &[Line::Green, Line::Mattapan],
+            RouteType::Subway => &[Line::Red, Line::Orange, Line::Blue],
+            RouteType::CommuterRail => &[Line::CommuterRail],
+            RouteType::Bus => &[Line::Bus, Line::Silver],
+            RouteType::Ferry => &[Line::Ferry],
+            RouteType::Unknown(_) => &[],
+        }
+    }
+
+    /// The branches riders can follow on their own; empty for unbranched lines.
+    pub fn branches(self) -> &'static [Branch] {
+        match self {
+            Line::Red => &[Branch::Ashmont, Branch::Braintree],
+            Line::Green => &[
+                Branch::GreenB,
+                Branch::GreenC,
+                Branch::GreenD,
+                Branch::GreenE,
+            ],
+            _ => &[],
+        }
+    }
+}
+
+/// A branch of a line, for riders who only care about theirs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Branch {
+    GreenB,
+    GreenC,
+    GreenD,
+    GreenE,
+    Ashmont,
+    Braintree,
+}
+
+impl Branch {
+    pub const ALL: [Branch; 6] = [
+        Branch::GreenB,
+        Branch::GreenC,
+        Branch::GreenD,
+        Branch::GreenE,
+        Branch::Ashmont,
+        Branch::Braintree,
+    ];
+
+    /// Canonical short name, used as the key in GOOGLE_CALENDAR_IDS. Green
+    /// Line branches are named by their MBTA route ID; the Red Line's share one
+    /// route, so theirs follow the same `Line-Branch` shape.
+    pub fn name(self) -> &'static str {
+        match self {
+            Branch::GreenB => "Green-B",
+            Branch::GreenC => "Green-C",
+            Branch::GreenD => "Green-D",
+            Branch::GreenE => "Green-E",
+            Branch::Ashmont => "Red-Ashmont",
+            Branch::Braintree => "Red-Braintree",
+        }
+    }
+
+    /// Inverse of [`Branch::name`]: parses a GOOGLE_CALENDAR_IDS key.
+    pub fn from_name(name: &str) -> Option<Branch> {
+        Self::ALL.into_iter().find(|branch| branch.name() == name)
+    }
+
+    pub fn full_name(self) -> &'static str {
+        match self {
+            Branch::GreenB => "Green Line B Branch",
+            Branch::GreenC => "Green Line C Branch",
+            Branch::GreenD => "Green Line D Branch",
+            Branch::GreenE => "Green Line E Branch",
+            Branch::Ashmont => "Red Line Ashmont Branch",
+            Branch::Braintree => "Red Line Braintree Branch",
+        }
+    }
+
+    pub fn line(self) -> Line {
+        match self {
+            Branch::GreenB | Branch::GreenC | Branch::GreenD | Branch::GreenE => Line::Green,
+            Branch::Ashmont | Branch::Braintree => Line::Red,
+        }
+    }
+
+    /// Parent stations served by this branch alone. Only the Red Line needs
+    /// these: its branches share the `Red` route, so stops are all that tell
+    /// them apart.
+    fn stations(self) -> &'static [&'static str] {
+        match self {
+            Branch::Ashmont => &["place-shmnl", "place-fldcr", "place-smmnl", "place-asmnl"],
+            Branch::Braintree => &[
+                "place-nqncy",
+                "place-wlsta",
+                "place-qnctr",
+                "place-qamnl",
+                "place-brntn",
+            ],
+            _ => &[],
         }
     }
 }
 
+/// MBTA route IDs of the Silver Line: SL1, SL2, SL3, SLW, SL5 and SL4.
+const SILVER_LINE_ROUTES: &[&str] = &["741", "742", "743", "746", "749", "751"];
+
+/// The line a route ID belongs to, for the routes whose ID alone says so. Bus
+/// route IDs are bare numbers that only the route type can place; see
+/// [`entity_line`].
 pub fn canonical_line(route: &str) -> Option<Line> {
     match route {
         "Red" => Some(Line::Red),
         "Orange" => Some(Line::Orange),
         "Blue" => Some(Line::Blue),
+        "Mattapan" => Some(Line::Mattapan),
         r if r.starts_with("Green") => Some(Line::Green),
+        r if SILVER_LINE_ROUTES.contains(&r) => Some(Line::Silver),
+        r if r.starts_with("CR-") => Some(Line::CommuterRail),
+        r if r.starts_with("Boat-") => Some(Line::Ferry),
         _ => None,
     }
 }
 
-pub fn line_name(alert: &Alert) -> &'static str {
+/// The line an informed entity's route belongs to, using its route type for
+/// the bus routes [`canonical_line`] can't place from the ID.
+pub fn entity_line(entity: &InformedEntity) -> Option<Line> {
+    let route = entity.route.as_deref()?;
+    canonical_line(route)
+        .or_else(|| (entity.route_type == Some(RouteType::Bus)).then_some(Line::Bus))
+}
+
+/// The branch an informed entity is on: a Green Line branch's route, or a
+/// Red Line stop south of JFK/UMass.
+pub fn entity_branch(entity: &InformedEntity) -> Option<Branch> {
+    let route = entity.route.as_deref()?;
+    Branch::from_name(route).or_else(|| {
+        let stop = entity.stop.as_deref()?;
+        canonical_line(route)?
+            .branches()
+            .iter()
+            .copied()
+            .find(|branch| branch.stations().contains(&stop))
+    })
+}
+
+/// The branch named by the header's prefix, as in "Red Line Braintree Branch:
+/// Delays ...".
+fn header_branch(alert: &Alert) -> Option<Branch> {
+    let (prefix, _) = alert.attributes.header.split_once(": ")?;
+    Branch::ALL
+        .into_iter()
+        .find(|branch| prefix.contains(branch.full_name()))
+}
+
+/// The branch of `line` the alert is confined to: the one its header names,
+/// or else the one every entity on the line is on. Trunk alerts list each
+/// branch they reach, so they have none.
+fn line_branch(alert: &Alert, line: Line) -> Option<Branch> {
+    if let Some(branch) = header_branch(alert).filter(|branch| branch.line() == line) {
+        return Some(branch);
+    }
+
+    let mut branch = None;
     for entity in &alert.attributes.informed_entity {
-        if let Some(route) = &entity.route {
-            return match canonical_line(route) {
-                Some(line) => line.full_name(),
-                None => {
-                    warn!("Unknown route '{route}', falling back to MBTA");
-                    "MBTA"
-                }
-            };
+        if entity_line(entity) != Some(line) {
+            continue;
+        }
+        match entity_branch(entity) {
+            Some(entity_branch) if branch.is_some_and(|b| b != entity_branch) => return None,
+            Some(entity_branch) => branch = Some(entity_branch),
+            // A station both branches share says nothing either way.
+            None if entity.stop.is_some() => {}
+            None => return None,
         }
     }
-    "MBTA"
+    branch
 }
 
-const STATION_EFFECTS_TO_SKIP: &[&str] = &[
-    "STATION_ISSUE",
-    "STOP_CLOSURE",
-    "STATION_CLOSURE",
-    "PARKING_ISSUE",
-];
-
-/// Station-level issues (closed stairways, parking, etc.) are noise for both
-/// the terminal output and calendar sync.
-pub fn should_sync_alert(alert: &Alert) -> bool {
-    !STATION_EFFECTS_TO_SKIP.contains(&alert.attributes.effect.as_str())
+/// The branches an informed entity of `alert` reaches: its own, else the one
+/// the alert is confined to, else every branch of its line. Empty for
+/// unbranched lines.
+pub fn entity_branches(alert: &Alert, entity: &InformedEntity) -> Vec<Branch> {
+    let Some(line) = entity_line(entity) else {
+        return Vec::new();
+    };
+    match entity_branch(entity).or_else(|| line_branch(alert, line)) {
+        Some(branch) => vec![branch],
+        None => line.branches().to_vec(),
+    }
 }
 
-pub async fn alerts(cache_mode: CacheMode) -> Result<Alerts> {
-    let cache_path = dated_cache_path("alerts");
+/// The line the alert is about: the first informed entity whose route we
+/// recognize, preferring any other line to [`Line::Bus`].
+///
+/// Alerts routinely list connecting bus routes alongside the rail line they
+/// are about, so neither an unrecognized route nor a bus decides the answer
+/// just because it came first.
+pub fn primary_line(alert: &Alert) -> Option<Line> {
+    let mut fallback = None;
+    for entity in &alert.attributes.informed_entity {
+        let Some(route) = &entity.route else { continue };
+        match entity_line(entity) {
+            Some(Line::Bus) => fallback = fallback.or(Some(Line::Bus)),
+            Some(line) => return Some(line),
+            None => warn!("Unknown route '{route}' in alert {}", alert.id),
+        }
+    }
+    fallback
+}
 
-    let response = try_cached_query(cache_mode, &cache_path, query_subway_alerts).await?;
+/// The branch of the alert's [`primary_line`] it is confined to, if any.
+pub fn primary_branch(alert: &Alert) -> Option<Branch> {
+    primary_line(alert).and_then(|line| line_branch(alert, line))
+}
+
+/// The most specific name for what the alert affects: its branch if it is
+/// confined to one, otherwise its line.
+pub fn line_name(alert: &Alert) -> &'static str {
+    match primary_line(alert) {
+        Some(line) => line_branch(alert, line).map_or(line.full_name(), Branch::full_name),
+        None => "MBTA",
+    }
+}
+
+/// The direction the alert is limited to, named for its line ("Southbound").
+/// Unrecognized routes use the outbound/inbound convention.
+pub fn direction_name(alert: &Alert) -> Option<&'static str> {
+    let direction_id = alert.direction_id()?;
+    Some(match primary_line(alert) {
+        Some(line) => line.direction_name(direction_id),
+        None if direction_id == 0 => "Outbound",
+        None => "Inbound",
+    })
+}
+
+/// Longest station list shown before the rest are summarized as a count.
+const MAX_LISTED_STATIONS: usize = 8;
+
+/// Which stations and which direction an alert covers, one line each, for the
+/// terminal and calendar descriptions. Empty when the alert names neither.
+pub fn affected_details(alert: &Alert, stops: &StopDirectory) -> Vec<String> {
+    let mut details = Vec::new();
+
+    let stations = stops.stations(alert);
+    if !stations.is_empty() {
+        let mut names: Vec<&str> = stations
+            .iter()
+            .take(MAX_LISTED_STATIONS)
+            .map(|id| stops.name(id))
+            .collect();
+        let more;
+        if stations.len() > MAX_LISTED_STATIONS {
+            more = format!("and {} more", stations.len() - MAX_LISTED_STATIONS);
+            names.push(&more);
+        }
+        details.push(format!("Stations: {}", names.join(", ")));
+    }
+
+    if let Some(direction) = direction_name(alert) {
+        details.push(format!("Direction: {direction}"));
+    }
+
+    details
+}
+
+/// Whether the alert belongs in the terminal output or a calendar: it's the
+/// kind of alert `effects` keeps, and it touches one of the rider's `stations`.
+pub fn should_sync_alert(
+    alert: &Alert,
+    effects: &EffectFilter,
+    stations: &StationFilter,
+    stops: &StopDirectory,
+) -> bool {
+    effects.matches(alert) && stations.matches(alert, stops)
+}
+
+/// The alerts [`should_sync_alert`] keeps under any of `effects`, and the
+/// directory of their stops. Calendar sync passes each calendar's filter and
+/// narrows further per calendar.
+///
+/// Stops are looked up before the station filter runs, since riders name
+/// stations that alerts often only reference by platform. Without a station
+/// filter, station names only enrich the output, so a failed lookup falls
+/// back to stop IDs rather than failing the run; see [`stops_or_fallback`].
+pub async fn alerts_to_sync<'a>(
+    mbta: &MbtaClient,
+    alerts: &'a Alerts,
+    effects: &[&EffectFilter],
+    stations: &StationFilter,
+    cache: &Cache,
+) -> Result<(Vec<&'a Alert>, StopDirectory)> {
+    let candidates: Vec<&Alert> = alerts
+        .data
+        .iter()
+        .filter(|a| {
+            let keep = effects.iter().any(|filter| filter.matches(a));
+            if !keep {
+                debug!(
+                    "Skipping {} alert {} (severity {}, {})",
+                    a.attributes.effect, a.id, a.attributes.severity, a.attributes.lifecycle
+                );
+            }
+            keep
+        })
+        .collect();
+
+    let lookup = stop_directory(mbta, &candidates, stations, cache).await;
+    let stops = stops_or_fallback(lookup, stations)?;
+
+    let alerts = candidates
+        .into_iter()
+        .filter(|a| {
+            let keep = stations.matches(a, &stops);
+            if !keep {
+                debug!("Skipping alert {}: none of the configured stations", a.id);
+            }
+            keep
+        })
+        .collect();
+
+    Ok((alerts, stops))
+}
+
+/// The stops `lookup` found, or, if it failed, none: alerts are then described
+/// by stop ID. Unless `stations` filters them: it needs the directory to match
+/// stations by name, and matching nothing would have a sync delete every one
+/// of their events.
+fn stops_or_fallback(
+    lookup: Result<StopDirectory>,
+    stations: &StationFilter,
+) -> Result<StopDirectory> {
+    match lookup {
+        Ok(stops) => Ok(stops),
+        Err(e) if stations.is_empty() => {
+            warn!("Failed to look up stops, describing alerts by stop ID: {e:#}");
+            Ok(StopDirectory::default())
+        }
+        Err(e) => Err(e.context("Failed to look up stops, which the station filter needs")),
+    }
+}
+
+pub async fn alerts(mbta: &MbtaClient, query: &AlertQuery, cache: &Cache) -> Result<Alerts> {
+    let response = cache
+        .query("alerts", &query.key(), || mbta.query_alerts(query))
+        .await?;
     trace!("{response}");
 
     let alerts: Alerts = serde_json::from_str(&response)?;
@@ -98,9 +458,114 @@ pub async fn alerts(cache_mode: CacheMode) -> Result<Alerts> {
     Ok(alerts)
 }
 
+/// The alerts for `query` and their validators, or `None` when they're
+/// unchanged since the response `previous` came from. Never cached: the
+/// point is to ask the API.
+pub async fn alerts_if_modified(
+    mbta: &MbtaClient,
+    query: &AlertQuery,
+    previous: &Validators,
+) -> Result<Option<(Alerts, Validators)>> {
+    match mbta.query_alerts_if_modified(query, previous).await? {
+        Fetched::Modified { body, validators } => {
+            trace!("{body}");
+            Ok(Some((serde_json::from_str(&body)?, validators)))
+        }
+        Fetched::NotModified => Ok(None),
+    }
+}
+
+/// Stops per `/stops` request, keeping the `filter[id]` URL a sane length.
+const STOPS_PER_QUERY: usize = 100;
+
+/// Looks up every stop the given alerts mention, the stops on the routes of
+/// those that name none when `stations` needs them, and the facilities at the
+/// stations of alerts that name any.
+pub async fn stop_directory(
+    mbta: &MbtaClient,
+    alerts: &[&Alert],
+    stations: &StationFilter,
+    cache: &Cache,
+) -> Result<StopDirectory> {
+    let cache = cache.at_least(REFERENCE_MAX_AGE);
+
+    let mut ids: Vec<&str> = alerts.iter().flat_map(|alert| alert.stop_ids()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut directory = StopDirectory::default();
+    for chunk in ids.chunks(STOPS_PER_QUERY) {
+        let ids = chunk.join(",");
+        let response = cache
+            .query("stops", &ids, || mbta.query_stops(&ids))
+            .await?;
+        trace!("{response}");
+
+        directory.extend(serde_json::from_str(&response)?);
+    }
+
+    // An alert naming no stops matches the station filter by its routes.
+    if !stations.is_empty() {
+        let mut routes: Vec<&str> = alerts
+            .iter()
+            .filter(|alert| alert.stop_ids().is_empty())
+            .flat_map(|alert| alert.route_ids())
+            .collect();
+        routes.sort_unstable();
+        routes.dedup();
+        for route in routes {
+            let response = cache
+                .query("route-stops", route, || mbta.query_route_stops(route))
+                .await?;
+            trace!("{response}");
+
+            directory.extend_route(route, serde_json::from_str(&response)?);
+        }
+    }
+
+    // Outages are still titled from their alerts without facilities, so a
+    // failed lookup costs detail, not the stop names.
+    if let Err(e) = look_up_facilities(mbta, alerts, &cache, &mut directory).await {
+        warn!("Failed to look up facilities, titling outages without them: {e:#}");
+    }
+
+    Ok(directory)
+}
+
+/// Adds the facilities `alerts` mention to `directory`, which must already
+/// have their stops.
+async fn look_up_facilities(
+    mbta: &MbtaClient,
+    alerts: &[&Alert],
+    cache: &Cache,
+    directory: &mut StopDirectory,
+) -> Result<()> {
+    // Facilities are looked up by station, the only filter `/facilities` offers.
+    let mut stations: Vec<String> = alerts
+        .iter()
+        .filter(|alert| !alert.facility_ids().is_empty())
+        .flat_map(|alert| directory.stations(alert))
+        .map(str::to_owned)
+        .collect();
+    stations.sort_unstable();
+    stations.dedup();
+
+    for chunk in stations.chunks(STOPS_PER_QUERY) {
+        let ids = chunk.join(",");
+        let response = cache
+            .query("facilities", &ids, || mbta.query_facilities(&ids))
+            .await?;
+        trace!("{response}");
+
+        directory.extend_facilities(serde_json::from_str(&response)?);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::types::Effect;
 
     const EXAMPLE_ALERTS_RESPONSE: &str = include_str!("../tests/fixtures/alerts.json");
 
@@ -136,7 +601,16 @@ mod test {
         let alerts: Alerts = serde_json::from_str(EXAMPLE_ALERTS_RESPONSE)?;
         let mut checked = 0;
 
-        for alert in alerts.data.iter().filter(|a| should_sync_alert(a)) {
+        let (effects, stations, stops) = (
+            EffectFilter::default(),
+            StationFilter::default(),
+            StopDirectory::default(),
+        );
+        for alert in alerts
+            .data
+            .iter()
+            .filter(|a| should_sync_alert(a, &effects, &stations, &stops))
+        {
             let with_prefix = event_summary(alert, LinePrefixMode::Include);
             let without_prefix = event_summary(alert, LinePrefixMode::Omit);
 
@@ -183,7 +657,7 @@ mod test {
 
     #[test]
     fn test_line_from_name_unknown() {
-        assert_eq!(Line::from_name("Silver"), None);
+        assert_eq!(Line::from_name("Purple"), None);
     }
 
     #[test]
@@ -217,8 +691,46 @@ mod test {
     }
 
     #[test]
-    fn test_canonical_line_unknown() {
-        assert_eq!(canonical_line("CR-Fitchburg"), None);
+    fn test_canonical_line_mattapan() {
+        assert_eq!(canonical_line("Mattapan"), Some(Line::Mattapan));
+    }
+
+    #[test]
+    fn test_canonical_line_silver() {
+        assert_eq!(canonical_line("741"), Some(Line::Silver));
+    }
+
+    #[test]
+    fn test_canonical_line_commuter_rail() {
+        assert_eq!(canonical_line("CR-Fitchburg"), Some(Line::CommuterRail));
+    }
+
+    #[test]
+    fn test_canonical_line_ferry() {
+        assert_eq!(canonical_line("Boat-F1"), Some(Line::Ferry));
+    }
+
+    #[test]
+    fn test_canonical_line_bus_route_id_alone_is_unknown() {
+        assert_eq!(canonical_line("99"), None);
+    }
+
+    fn bus_entity(route: &str) -> InformedEntity {
+        InformedEntity {
+            route: Some(route.to_owned()),
+            route_type: Some(RouteType::Bus),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entity_line_bus_by_route_type() {
+        assert_eq!(entity_line(&bus_entity("99")), Some(Line::Bus));
+    }
+
+    #[test]
+    fn test_entity_line_silver_line_is_not_bus() {
+        assert_eq!(entity_line(&bus_entity("742")), Some(Line::Silver));
     }
 
     #[test]
@@ -238,22 +750,121 @@ mod test {
 
     #[test]
     fn test_line_name_green_b() {
-        assert_eq!(line_name(&make_alert("Green-B")), "Green Line");
+        assert_eq!(line_name(&make_alert("Green-B")), "Green Line B Branch");
     }
 
     #[test]
     fn test_line_name_green_c() {
-        assert_eq!(line_name(&make_alert("Green-C")), "Green Line");
+        assert_eq!(line_name(&make_alert("Green-C")), "Green Line C Branch");
     }
 
     #[test]
     fn test_line_name_green_d() {
-        assert_eq!(line_name(&make_alert("Green-D")), "Green Line");
+        assert_eq!(line_name(&make_alert("Green-D")), "Green Line D Branch");
     }
 
     #[test]
     fn test_line_name_green_e() {
-        assert_eq!(line_name(&make_alert("Green-E")), "Green Line");
+        assert_eq!(line_name(&make_alert("Green-E")), "Green Line E Branch");
+    }
+
+    #[test]
+    fn test_line_name_green_trunk_has_no_branch() {
+        let alert = Alert::builder().route("Green-B").route("Green-C").build();
+        assert_eq!(line_name(&alert), "Green Line");
+        assert_eq!(primary_branch(&alert), None);
+    }
+
+    #[test]
+    fn test_line_name_branch_ignores_other_lines() {
+        let alert = Alert::builder()
+            .route("Green-E")
+            .entity(bus_entity("39"))
+            .build();
+        assert_eq!(line_name(&alert), "Green Line E Branch");
+        assert_eq!(primary_branch(&alert), Some(Branch::GreenE));
+    }
+
+    #[test]
+    fn test_branch_from_name_round_trips() {
+        for branch in Branch::ALL {
+            assert_eq!(Branch::from_name(branch.name()), Some(branch));
+            assert!(branch.line().branches().contains(&branch));
+        }
+    }
+
+    fn red_stop_alert(stops: &[&str]) -> Alert {
+        stops
+            .iter()
+            .fold(Alert::builder(), |builder, stop| builder.stop("Red", stop))
+            .build()
+    }
+
+    #[test]
+    fn test_line_name_red_branch_from_stops() {
+        let alert = red_stop_alert(&["place-qnctr", "place-brntn"]);
+        assert_eq!(line_name(&alert), "Red Line Braintree Branch");
+        assert_eq!(primary_branch(&alert), Some(Branch::Braintree));
+    }
+
+    #[test]
+    fn test_line_name_red_branch_ignores_trunk_stations() {
+        let alert = red_stop_alert(&["place-jfk", "place-smmnl", "place-asmnl"]);
+        assert_eq!(primary_branch(&alert), Some(Branch::Ashmont));
+    }
+
+    #[test]
+    fn test_line_name_red_both_branches_is_line() {
+        let alert = red_stop_alert(&["place-asmnl", "place-brntn"]);
+        assert_eq!(line_name(&alert), "Red Line");
+    }
+
+    #[test]
+    fn test_line_name_red_trunk_only_is_line() {
+        let alert = red_stop_alert(&["place-jfk", "place-andrw"]);
+        assert_eq!(primary_branch(&alert), None);
+    }
+
+    #[test]
+    fn test_line_name_red_branch_from_header() {
+        let alert = Alert::builder()
+            .header("Red Line Braintree Branch: Delays of about 10 minutes.")
+            .route("Red")
+            .build();
+        assert_eq!(line_name(&alert), "Red Line Braintree Branch");
+    }
+
+    #[test]
+    fn test_header_branch_must_match_line() {
+        let alert = Alert::builder()
+            .header("Red Line Braintree Branch: Shuttle buses replace service.")
+            .route("Orange")
+            .build();
+        assert_eq!(line_name(&alert), "Orange Line");
+    }
+
+    #[test]
+    fn test_line_name_mattapan_at_shared_ashmont_is_not_red() {
+        let alert = Alert::builder().stop("Mattapan", "place-asmnl").build();
+        assert_eq!(line_name(&alert), "Mattapan Trolley");
+        assert_eq!(primary_branch(&alert), None);
+    }
+
+    #[test]
+    fn test_entity_branches_trunk_station_reaches_both() {
+        let alert = red_stop_alert(&["place-jfk"]);
+        let entity = &alert.attributes.informed_entity[0];
+        assert_eq!(
+            entity_branches(&alert, entity),
+            vec![Branch::Ashmont, Branch::Braintree]
+        );
+    }
+
+    #[test]
+    fn test_entity_branches_trunk_station_of_branch_alert() {
+        let alert = red_stop_alert(&["place-jfk", "place-nqncy"]);
+        let entity = &alert.attributes.informed_entity[0];
+        assert_eq!(entity_branches(&alert, entity), vec![Branch::Braintree]);
     }
 
     #[test]
@@ -261,9 +872,34 @@ mod test {
         assert_eq!(line_name(&make_alert("Blue")), "Blue Line");
     }
 
+    #[test]
+    fn test_line_name_commuter_rail() {
+        assert_eq!(line_name(&make_alert("CR-Fitchburg")), "Commuter Rail");
+    }
+
+    #[test]
+    fn test_line_name_mattapan() {
+        assert_eq!(line_name(&make_alert("Mattapan")), "Mattapan Trolley");
+    }
+
     #[test]
     fn test_line_name_unknown_route() {
-        assert_eq!(line_name(&make_alert("CR-Fitchburg")), "MBTA");
+        assert_eq!(line_name(&make_alert("99")), "MBTA");
+    }
+
+    #[test]
+    fn test_line_name_prefers_rail_over_leading_bus() {
+        let alert = Alert::builder()
+            .entity(bus_entity("99"))
+            .route("Orange")
+            .build();
+        assert_eq!(line_name(&alert), "Orange Line");
+    }
+
+    #[test]
+    fn test_line_name_bus_only() {
+        let alert = Alert::builder().entity(bus_entity("99")).build();
+        assert_eq!(line_name(&alert), "Bus");
     }
 
     #[test]
@@ -276,34 +912,165 @@ mod test {
         assert_eq!(line_name(&make_alert_null_route()), "MBTA");
     }
 
+    #[test]
+    fn test_line_name_skips_unrecognized_leading_route() {
+        let alert = Alert::builder().route("SL1").route("Red").build();
+        assert_eq!(line_name(&alert), "Red Line");
+    }
+
+    fn make_alert_with_direction(route: &str, direction_id: u8) -> Alert {
+        Alert::builder()
+            .entity(InformedEntity {
+                route: Some(route.to_owned()),
+                direction_id: Some(direction_id),
+                ..Default::default()
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_direction_name_uses_line_convention() {
+        assert_eq!(
+            direction_name(&make_alert_with_direction("Red", 0)),
+            Some("Southbound")
+        );
+        assert_eq!(
+            direction_name(&make_alert_with_direction("Green-E", 1)),
+            Some("Eastbound")
+        );
+    }
+
+    #[test]
+    fn test_direction_name_unrecognized_route_uses_bus_convention() {
+        assert_eq!(
+            direction_name(&make_alert_with_direction("426", 0)),
+            Some("Outbound")
+        );
+    }
+
+    #[test]
+    fn test_direction_name_none_without_direction() {
+        assert_eq!(direction_name(&make_alert("Red")), None);
+    }
+
+    #[test]
+    fn test_affected_details_lists_stations_and_direction() {
+        let northbound = |stop: &str| InformedEntity {
+            route: Some("Red".to_owned()),
+            stop: Some(stop.to_owned()),
+            direction_id: Some(1),
+            ..Default::default()
+        };
+        let alert = Alert::builder()
+            .entity(northbound("place-pktrm"))
+            .entity(northbound("place-dwnxg"))
+            .build();
+        assert_eq!(
+            affected_details(&alert, &StopDirectory::default()),
+            vec![
+                "Stations: place-pktrm, place-dwnxg".to_owned(),
+                "Direction: Northbound".to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_stop_lookup_falls_back_without_a_station_filter() {
+        let failed = || Err(anyhow::anyhow!("503 Service Unavailable"));
+        let stops = stops_or_fallback(failed(), &StationFilter::default());
+        assert!(stops.is_ok_and(|stops| stops.name("70061") == "70061"));
+
+        // Matching "Park Street" needs the directory; without it, nothing
+        // would match and a sync would delete the station's events.
+        let by_name = StationFilter::parse("Park Street");
+        assert!(stops_or_fallback(failed(), &by_name).is_err());
+    }
+
+    #[test]
+    fn test_affected_details_truncates_long_station_lists() {
+        let mut builder = Alert::builder();
+        for i in 0..10 {
+            builder = builder.stop("Red", &format!("place-s{i}"));
+        }
+        let details = affected_details(&builder.build(), &StopDirectory::default());
+        assert_eq!(details.len(), 1);
+        assert!(
+            details[0].ends_with(", place-s7, and 2 more"),
+            "{}",
+            details[0]
+        );
+    }
+
+    #[test]
+    fn test_affected_details_empty_without_stops_or_direction() {
+        assert!(affected_details(&make_alert("Red"), &StopDirectory::default()).is_empty());
+    }
+
     fn make_alert_with_effect(effect: &str) -> Alert {
         Alert::builder().route("Red").effect(effect).build()
     }
 
+    fn should_sync(alert: &Alert) -> bool {
+        should_sync_alert(
+            alert,
+            &EffectFilter::default(),
+            &StationFilter::default(),
+            &StopDirectory::default(),
+        )
+    }
+
     #[test]
     fn test_should_sync_station_issue_is_skipped() {
-        assert!(!should_sync_alert(&make_alert_with_effect("STATION_ISSUE")));
+        assert!(!should_sync(&make_alert_with_effect("STATION_ISSUE")));
     }
 
     #[test]
     fn test_should_sync_stop_closure_is_skipped() {
-        assert!(!should_sync_alert(&make_alert_with_effect("STOP_CLOSURE")));
+        assert!(!should_sync(&make_alert_with_effect("STOP_CLOSURE")));
     }
 
     #[test]
     fn test_should_sync_station_closure_is_skipped() {
-        assert!(!should_sync_alert(&make_alert_with_effect(
-            "STATION_CLOSURE"
-        )));
+        assert!(!should_sync(&make_alert_with_effect("STATION_CLOSURE")));
     }
 
     #[test]
     fn test_should_sync_parking_issue_is_skipped() {
-        assert!(!should_sync_alert(&make_alert_with_effect("PARKING_ISSUE")));
+        assert!(!should_sync(&make_alert_with_effect("PARKING_ISSUE")));
+    }
+
+    #[test]
+    fn test_should_sync_respects_effect_filter() {
+        let alert = make_alert_with_effect("STATION_ISSUE");
+        let effects = EffectFilter {
+            include_effects: vec![Effect::StationIssue],
+            ..Default::default()
+        };
+        let (stations, stops) = (StationFilter::default(), StopDirectory::default());
+        assert!(!should_sync(&alert));
+        assert!(should_sync_alert(&alert, &effects, &stations, &stops));
     }
 
     #[test]
     fn test_should_sync_shuttle_is_synced() {
-        assert!(should_sync_alert(&make_alert_with_effect("SHUTTLE")));
+        assert!(should_sync(&make_alert_with_effect("SHUTTLE")));
+    }
+
+    #[test]
+    fn test_should_sync_respects_station_filter() {
+        let alert = Alert::builder().stop("Red", "place-pktrm").build();
+        let (effects, stops) = (EffectFilter::default(), StopDirectory::default());
+        assert!(should_sync_alert(
+            &alert,
+            &effects,
+            &StationFilter::parse("place-pktrm"),
+            &stops
+        ));
+        assert!(!should_sync_alert(
+            &alert,
+            &effects,
+            &StationFilter::parse("place-alfcl"),
+            &stops
+        ));
     }
 }
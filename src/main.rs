@@ -1,83 +1,321 @@
-use chrono::DateTime;
-use clap::Parser;
-use jluszcz_rust_utils::cache::CacheMode;
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, FixedOffset, Utc};
+use chrono_tz::America::New_York;
+use chrono_tz::Tz;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use jluszcz_rust_utils::cli::VerbosityArgs;
 use jluszcz_rust_utils::{Verbosity, set_up_logger, tls};
-use log::debug;
+use log::{debug, warn};
+use mbtalerts::accessibility::is_accessibility_alert;
+use mbtalerts::agenda::agenda;
 use mbtalerts::ai::BedrockSummarizer;
-use mbtalerts::calendar::{CalendarClient, sync_alerts};
-use mbtalerts::summary::{
-    LinePrefixMode, first_sentence, generate_or_fallback, uses_first_sentence_summary,
-};
-use mbtalerts::types::{Alert, Alerts};
-use mbtalerts::{APP_NAME, should_sync_alert};
+use mbtalerts::cache::Cache;
+use mbtalerts::calendar::{CalendarClient, dry_run, sync_alerts, sync_if_modified};
+use mbtalerts::config::{Config, minutes};
+use mbtalerts::filter::{StationFilter, parse_values};
+use mbtalerts::ics::ics_feeds;
+use mbtalerts::mbta::{AlertQuery, AlertTime, MbtaClient, RouteFilter};
+use mbtalerts::output::{self, AlertRecord, alert_body, alert_records};
+use mbtalerts::serve::serve;
+use mbtalerts::stops::StopDirectory;
+use mbtalerts::stream::{AlertChange, AlertStream};
+use mbtalerts::summary::{LinePrefixMode, TitleCache, generate_or_fallback};
+use mbtalerts::types::{Alert, Alerts, Severity};
+use mbtalerts::{APP_NAME, affected_details, alerts_to_sync};
 
 const SEPARATOR: &str = "----------------------------------------";
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Titles, times and details for reading in a terminal.
+    Text,
+    /// A JSON array.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    Csv,
+    /// A table.
+    Markdown,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Serve the iCalendar feeds at /calendar/<line>.ics and the alerts at /alerts.json, refreshing them from the MBTA API.
+    Serve {
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: SocketAddr,
+
+        /// Minutes between checks for changed alerts.
+        #[arg(long, value_name = "MINUTES", default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+}
+
+/// The modes that sync calendars. They delete the events of alerts missing
+/// from what they fetch, so they can't be given the API's server-side filters.
+const SYNCS: [&str; 2] = ["sync_calendar", "watch"];
+
 #[derive(Debug, Parser)]
 #[command(version, author, infer_long_args = true)]
 struct RawArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[command(flatten)]
     verbosity: VerbosityArgs,
 
+    /// TOML config file. Defaults to MBTALERTS_CONFIG; environment variables override its values.
+    #[arg(short = 'c', long)]
+    config: Option<PathBuf>,
+
     /// Query remote services instead of using cached values.
     #[arg(short = 'n', long)]
     no_cache: bool,
 
-    /// Sync alerts to Google Calendar (requires GOOGLE_SERVICE_ACCOUNT_KEY and either GOOGLE_CALENDAR_ID or GOOGLE_CALENDAR_IDS env vars).
+    /// Re-fetch alerts only if the cached response is older than this many minutes, instead of the configured TTL.
+    #[arg(short = 'r', long, value_name = "MINUTES", conflicts_with = "no_cache")]
+    refresh: Option<u64>,
+
+    /// List the cached responses and how old they are, then exit.
+    #[arg(long, conflicts_with = "no_cache")]
+    cache_info: bool,
+
+    /// Sync alerts to Google Calendar (requires a service account key and calendar IDs, from the config file or GOOGLE_* env vars).
     #[arg(short = 's', long)]
     sync_calendar: bool,
+
+    /// With --sync-calendar, print what it would create, update and delete on each calendar, and how each update changes its event, without writing anything.
+    #[arg(long, requires = "sync_calendar", conflicts_with = "watch")]
+    dry_run: bool,
+
+    /// Follow the MBTA alert stream, printing alerts as they're added, updated and cleared, or syncing each change with --sync-calendar.
+    #[arg(short = 'w', long)]
+    watch: bool,
+
+    /// Write an iCalendar feed per line and branch to this directory, e.g. red.ics and green-e.ics, instead of printing alerts.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["sync_calendar", "watch", "upcoming", "when"])]
+    ics: Option<PathBuf>,
+
+    /// How to print alerts.
+    #[arg(short = 'f', long, value_enum, default_value_t = OutputFormat::Text, conflicts_with_all = ["sync_calendar", "watch", "upcoming", "when", "ics"])]
+    format: OutputFormat,
+
+    /// Print a day-by-day agenda of the alerts over the next this-many days, grouped by line, instead of every current alert.
+    #[arg(short = 'u', long, value_name = "DAYS", value_parser = clap::value_parser!(u64).range(1..=90), conflicts_with_all = ["watch", "sync_calendar", "when"])]
+    upcoming: Option<u64>,
+
+    /// Print the agenda for the day of this time, limited to the alerts active then: "now", RFC 3339, or a Boston time like "2025-10-25T09:00".
+    #[arg(long, value_name = "TIME", conflicts_with_all = ["watch", "sync_calendar", "active_at"])]
+    when: Option<AlertTime>,
+
+    /// Comma-separated route IDs to fetch alerts for, e.g. "Red,CR-Fitchburg". Overrides the configured routes, which default to the subway.
+    #[arg(long, conflicts_with = "route_types")]
+    routes: Option<String>,
+
+    /// Comma-separated route types to fetch alerts for: light-rail, subway, commuter-rail, bus, ferry (or 0-4). Overrides the configured routes.
+    #[arg(long)]
+    route_types: Option<String>,
+
+    /// Comma-separated stations to limit alerts to, by name or stop ID, e.g. "Park Street,place-dwnxg". Overrides the configured stations.
+    #[arg(long)]
+    stops: Option<String>,
+
+    /// Comma-separated stop IDs for the API to return alerts for, e.g. "place-pktrm,70061". Unlike --stops, filters server-side and takes IDs only.
+    #[arg(long, conflicts_with_all = SYNCS)]
+    stop_ids: Option<String>,
+
+    /// Only alerts for this direction of the routes, 0 or 1, filtered server-side.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=1), conflicts_with_all = SYNCS)]
+    direction_id: Option<u8>,
+
+    /// Comma-separated activities for the API to return alerts for, e.g. "board,using-wheelchair". Defaults to all of them.
+    #[arg(long, conflicts_with_all = SYNCS)]
+    activities: Option<String>,
+
+    /// Only alerts active at this time, filtered server-side: "now", RFC 3339, or a Boston time like "2025-10-20T08:00".
+    #[arg(long, value_name = "TIME", conflicts_with_all = SYNCS)]
+    active_at: Option<AlertTime>,
+
+    /// Comma-separated lifecycles for the API to return: new, ongoing, ongoing-upcoming, upcoming.
+    #[arg(long, conflicts_with_all = SYNCS)]
+    lifecycles: Option<String>,
+
+    /// Comma-separated severities (0-10) for the API to return.
+    #[arg(long, conflicts_with_all = SYNCS)]
+    severities: Option<String>,
+
+    /// Only alerts that are (true) or aren't (false) shown as a banner on mbta.com, filtered server-side.
+    #[arg(long, conflicts_with_all = SYNCS)]
+    banner: Option<bool>,
+
+    /// Comma-separated effects to show, e.g. "shuttle,suspension"; replaces the excluded effects. Overrides the configured filter.
+    #[arg(long)]
+    include_effects: Option<String>,
+
+    /// Comma-separated effects to hide. Overrides the configured filter, which excludes station-level issues.
+    #[arg(long)]
+    exclude_effects: Option<String>,
+
+    /// Hide alerts less severe than this (0-10). Overrides the configured filter.
+    #[arg(long)]
+    min_severity: Option<u8>,
+
+    /// Comma-separated lifecycles to show: new, ongoing, ongoing-upcoming, upcoming. Overrides the configured filter.
+    #[arg(long)]
+    include_lifecycles: Option<String>,
+
+    /// Comma-separated lifecycles to hide. Overrides the configured filter.
+    #[arg(long)]
+    exclude_lifecycles: Option<String>,
+}
+
+impl RawArgs {
+    /// A mode flag given alongside a subcommand, which would ignore it.
+    fn mode_beside_command(&self) -> Option<&'static str> {
+        self.command.as_ref()?;
+        [
+            (self.sync_calendar, "--sync-calendar"),
+            (self.dry_run, "--dry-run"),
+            (self.watch, "--watch"),
+            (self.cache_info, "--cache-info"),
+            (self.ics.is_some(), "--ics"),
+            (self.format != OutputFormat::Text, "--format"),
+            (self.upcoming.is_some(), "--upcoming"),
+            (self.when.is_some(), "--when"),
+        ]
+        .into_iter()
+        .find_map(|(given, flag)| given.then_some(flag))
+    }
 }
 
 #[derive(Debug)]
 struct Args {
     verbosity: Verbosity,
-    cache_mode: CacheMode,
+    command: Option<Command>,
+    cache: Cache,
+    cache_info: bool,
     sync_calendar: bool,
+    dry_run: bool,
+    watch: bool,
+    /// The agenda's first moment and how many days it covers.
+    agenda: Option<(DateTime<Tz>, u64)>,
+    format: OutputFormat,
+    ics: Option<PathBuf>,
+    config: Config,
 }
 
-fn parse_args() -> Args {
+fn parse_args() -> anyhow::Result<Args> {
     let raw = RawArgs::parse();
+    if let Some(flag) = raw.mode_beside_command() {
+        RawArgs::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("{flag} can't be used with a subcommand"),
+            )
+            .exit();
+    }
 
-    Args {
-        verbosity: raw.verbosity.into(),
-        cache_mode: (!raw.no_cache).into(),
-        sync_calendar: raw.sync_calendar,
+    let mut config = Config::load(raw.config.as_deref())?;
+
+    let query = &mut config.query;
+    if let Some(routes) = raw.routes {
+        query.routes = RouteFilter::parse_routes(&routes)?;
+    } else if let Some(types) = raw.route_types {
+        query.routes = RouteFilter::parse_route_types(&types)?;
     }
-}
+    if let Some(stops) = raw.stop_ids {
+        query.stops = AlertQuery::parse_stops(&stops);
+    }
+    query.direction_id = raw.direction_id;
+    if let Some(list) = raw.activities {
+        query.activities = AlertQuery::parse_activities(&list)?;
+    }
+    query.datetime = raw.active_at;
+    if let Some(list) = raw.lifecycles {
+        query.lifecycles = AlertQuery::parse_lifecycles(&list)?;
+    }
+    if let Some(list) = raw.severities {
+        query.severities = AlertQuery::parse_severities(&list)?;
+    }
+    query.banner = raw.banner;
 
-fn format_dt(s: &str) -> String {
-    DateTime::parse_from_rfc3339(s)
-        .map(|dt| dt.format("%-m/%-d/%Y %-I:%M%p").to_string().to_lowercase())
-        .unwrap_or_else(|_| s.to_owned())
-}
+    let agenda = if let Some(when) = raw.when {
+        query.datetime = Some(when);
+        Some((when.instant().with_timezone(&New_York), 1))
+    } else {
+        raw.upcoming
+            .map(|days| (Utc::now().with_timezone(&New_York), days))
+    };
 
-/// The body to print beneath the title.
-///
-/// The header's first sentence is dropped only when it *is* the title. An
-/// AI-generated title is unrelated to that sentence, so stripping it there would
-/// delete content that appears nowhere else in the output.
-fn alert_body(alert: &Alert, ai_generated_title: bool) -> &str {
-    let header = &alert.attributes.header;
+    if let Some(stops) = raw.stops {
+        config.stations = StationFilter::parse(&stops);
+    }
 
-    if ai_generated_title || !uses_first_sentence_summary(alert) {
-        return header;
+    let effects = &mut config.effects;
+    if let Some(list) = raw.include_effects {
+        effects.include_effects = parse_values(&list);
+    }
+    if let Some(list) = raw.exclude_effects {
+        effects.exclude_effects = parse_values(&list);
+    }
+    if let Some(min) = raw.min_severity {
+        effects.min_severity = Some(Severity(min));
+    }
+    if let Some(list) = raw.include_lifecycles {
+        effects.include_lifecycles = parse_values(&list);
     }
+    if let Some(list) = raw.exclude_lifecycles {
+        effects.exclude_lifecycles = parse_values(&list);
+    }
+    effects.validate()?;
+
+    Ok(Args {
+        verbosity: raw.verbosity.into(),
+        command: raw.command,
+        cache: if raw.no_cache {
+            Cache::disabled()
+        } else if let Some(refresh) = raw.refresh {
+            Cache::new(config.cache.dir(), minutes(refresh))
+        } else {
+            config.cache.cache()
+        },
+        cache_info: raw.cache_info,
+        sync_calendar: raw.sync_calendar,
+        dry_run: raw.dry_run,
+        watch: raw.watch,
+        agenda,
+        format: raw.format,
+        ics: raw.ics,
+        config,
+    })
+}
 
-    let first = first_sentence(header);
-    let rest = header[first.len()..].trim_start_matches(['.', ' ']);
-    if rest.is_empty() { header } else { rest }
+fn format_dt(dt: DateTime<FixedOffset>) -> String {
+    dt.format("%-m/%-d/%Y %-I:%M%p").to_string().to_lowercase()
 }
 
-async fn format_alert(alert: &Alert, summarizer: Option<&BedrockSummarizer>) -> String {
+async fn format_alert(
+    alert: &Alert,
+    summarizer: Option<&BedrockSummarizer>,
+    stops: &StopDirectory,
+    color: bool,
+) -> String {
     let effect = &alert.attributes.effect;
     let start = alert.period_start().map(format_dt);
     let end = alert.period_end().map(format_dt);
 
-    let summary = generate_or_fallback(summarizer, alert, LinePrefixMode::Include).await;
+    let summary = generate_or_fallback(summarizer, alert, LinePrefixMode::Include, stops).await;
 
-    let ai_generated_title = summary.raw.is_some();
-    let formatted_summary = if let Some(close) = summary.display.find(']') {
+    let derived_title = summary.raw.is_some() || is_accessibility_alert(alert);
+    let formatted_summary = if color && let Some(close) = summary.display.find(']') {
         let (prefix, rest) = summary.display.split_at(close + 1);
         format!("\x1b[1m{prefix}\x1b[22m{rest}")
     } else {
@@ -90,39 +328,316 @@ async fn format_alert(alert: &Alert, summarizer: Option<&BedrockSummarizer>) ->
         _ => String::new(),
     };
 
-    let body = alert_body(alert, ai_generated_title);
+    let body = alert_body(alert, derived_title);
+
+    let mut output = format!("{formatted_summary}{date_part}\n{effect} {body}");
+    for detail in affected_details(alert, stops) {
+        output.push('\n');
+        output.push_str(&detail);
+    }
+    output
+}
+
+async fn print_alerts(
+    mbta: &MbtaClient,
+    alerts: &Alerts,
+    config: &Config,
+    summarizer: Option<&BedrockSummarizer>,
+    cache: &Cache,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let (alerts, stops) =
+        alerts_to_sync(mbta, alerts, &[&config.effects], &config.stations, cache).await?;
+
+    let render: fn(&[AlertRecord]) -> anyhow::Result<String> = match format {
+        OutputFormat::Text => {
+            if alerts.is_empty() {
+                println!("No active alerts.");
+            }
+            for alert in alerts {
+                println!("{SEPARATOR}");
+                println!(
+                    "{}",
+                    format_alert(alert, summarizer, &stops, config.output.color).await
+                );
+            }
+            return Ok(());
+        }
+        OutputFormat::Json => output::to_json,
+        OutputFormat::Ndjson => output::to_ndjson,
+        OutputFormat::Csv => |records| Ok(output::to_csv(records)),
+        OutputFormat::Markdown => |records| Ok(output::to_markdown(records)),
+    };
+    let records = alert_records(&alerts, &TitleCache::new(summarizer), &stops).await;
+    let output = render(&records)?;
+    if !output.is_empty() {
+        println!("{output}");
+    }
+    Ok(())
+}
+
+/// Prints each day of the agenda, its lines, and their alerts' titles and
+/// hours. An alert spanning several days is summarized once.
+async fn print_agenda(
+    mbta: &MbtaClient,
+    alerts: &Alerts,
+    config: &Config,
+    summarizer: Option<&BedrockSummarizer>,
+    cache: &Cache,
+    (from, days): (DateTime<Tz>, u64),
+) -> anyhow::Result<()> {
+    let (alerts, stops) =
+        alerts_to_sync(mbta, alerts, &[&config.effects], &config.stations, cache).await?;
+
+    let mut titles: HashMap<&str, String> = HashMap::new();
+    for day in agenda(&alerts, from, days) {
+        println!("{SEPARATOR}");
+        let date = day.date.format("%A, %B %-d");
+        if config.output.color {
+            println!("\x1b[1m{date}\x1b[22m");
+        } else {
+            println!("{date}");
+        }
+        if day.lines.is_empty() {
+            println!("No planned disruptions.");
+            continue;
+        }
+
+        for (line, entries) in day.lines {
+            println!("{line}");
+            for entry in entries {
+                let alert = entry.alert;
+                if !titles.contains_key(alert.id.as_str()) {
+                    let summary =
+                        generate_or_fallback(summarizer, alert, LinePrefixMode::Omit, &stops).await;
+                    titles.insert(&alert.id, summary.display);
+                }
+                println!(
+                    "  {} ({}, {})",
+                    titles[alert.id.as_str()],
+                    alert.attributes.effect.label(),
+                    entry.hours()
+                );
+            }
+        }
+    }
+    Ok(())
+}
 
-    format!("{formatted_summary}{date_part}\n{effect} {body}")
+/// "5m", "2h 5m", "3d 4h": an age to the precision anyone cares about.
+fn format_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) if minutes == 0 => "<1m".to_owned(),
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+/// Lists the cached responses, oldest first, and whether each is still fresh.
+fn print_cache_info(cache: &Cache) -> anyhow::Result<()> {
+    let entries = cache.entries()?;
+    if entries.is_empty() {
+        println!("No cached responses.");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    for entry in entries {
+        let age = entry.age(now);
+        let status = if cache.max_age().is_some_and(|max| age <= max) {
+            "fresh"
+        } else {
+            "stale"
+        };
+        println!(
+            "{:<10} {:>7} ago  {status}  {:>5} KB  {}",
+            entry.name,
+            format_age(age),
+            entry.body.len().div_ceil(1024),
+            entry.key
+        );
+    }
+    Ok(())
 }
 
-async fn print_alerts(alerts: &Alerts, summarizer: Option<&BedrockSummarizer>) {
-    let mut printed = false;
-    for alert in alerts.data.iter().filter(|a| should_sync_alert(a)) {
+/// Prints the alerts in `changes` that pass the filters, each under what
+/// happened to it.
+async fn print_changes(
+    mbta: &MbtaClient,
+    alerts: &Alerts,
+    changes: &[AlertChange],
+    config: &Config,
+    summarizer: Option<&BedrockSummarizer>,
+    cache: &Cache,
+) -> anyhow::Result<()> {
+    let (kept, stops) =
+        alerts_to_sync(mbta, alerts, &[&config.effects], &config.stations, cache).await?;
+
+    for change in changes {
+        let (label, alert, summarizer) = match change {
+            AlertChange::Added(id) | AlertChange::Updated(id) => {
+                let Some(alert) = kept.iter().find(|alert| alert.id == *id) else {
+                    continue;
+                };
+                let label = if matches!(change, AlertChange::Added(_)) {
+                    "New"
+                } else {
+                    "Updated"
+                };
+                (label, *alert, summarizer)
+            }
+            // No new title for an alert that's gone.
+            AlertChange::Removed(alert) if config.effects.matches(alert) => {
+                ("Cleared", &**alert, None)
+            }
+            AlertChange::Removed(_) => continue,
+        };
         println!("{SEPARATOR}");
-        println!("{}", format_alert(alert, summarizer).await);
-        printed = true;
+        println!("{label}:");
+        println!(
+            "{}",
+            format_alert(alert, summarizer, &stops, config.output.color).await
+        );
     }
-    if !printed {
-        println!("No active alerts.");
+    Ok(())
+}
+
+/// Follows the alert stream until it fails for good.
+async fn watch(
+    mbta: &MbtaClient,
+    config: &Config,
+    sync_calendar: bool,
+    cache: &Cache,
+) -> anyhow::Result<()> {
+    let calendar = if sync_calendar {
+        Some(CalendarClient::new(config).await?)
+    } else {
+        None
+    };
+    let summarizer = BedrockSummarizer::new(&config.summarizer).await;
+
+    let mut stream = AlertStream::new(mbta, &config.query);
+    loop {
+        let changes = stream.next_batch().await?;
+        debug!("{} alert changes", changes.len());
+
+        match &calendar {
+            // Sync diffs against the calendar, so a calendar that fails to
+            // sync is caught up by the next one rather than ending the watch.
+            Some(calendar) => {
+                match sync_alerts(
+                    mbta,
+                    stream.alerts(),
+                    &config.effects,
+                    &config.stations,
+                    calendar,
+                )
+                .await
+                {
+                    Ok(report) => debug!("{report}"),
+                    Err(e) => warn!("Failed to sync alert changes: {e:#}"),
+                }
+            }
+            None => {
+                if let Err(e) = print_changes(
+                    mbta,
+                    stream.alerts(),
+                    &changes,
+                    config,
+                    summarizer.as_ref(),
+                    cache,
+                )
+                .await
+                {
+                    warn!("Failed to print alert changes: {e:#}");
+                }
+            }
+        }
     }
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     tls::install_default_provider();
 
-    let args = parse_args();
+    let args = parse_args()?;
+    // SAFETY: the runtime, and with it every other thread, starts below.
+    unsafe { args.config.export_model_id() };
+
+    tokio::runtime::Runtime::new()?.block_on(run(args))
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
     set_up_logger(APP_NAME, module_path!(), args.verbosity)?;
     debug!("{args:?}");
 
-    let alerts = mbtalerts::alerts(args.cache_mode).await?;
+    if args.cache_info {
+        print_cache_info(&args.cache)?;
+        return Ok(());
+    }
 
-    if args.sync_calendar {
-        let calendar = CalendarClient::from_env().await?;
-        sync_alerts(&alerts, &calendar).await?;
+    let config = &args.config;
+    let mbta = MbtaClient::new(config)?;
+
+    if let Some(Command::Serve { bind, interval }) = args.command {
+        serve(&mbta, config, &args.cache, bind, minutes(interval)).await?;
+    } else if args.watch {
+        watch(&mbta, config, args.sync_calendar, &args.cache).await?;
+    } else if args.dry_run {
+        for plan in dry_run(&mbta, config).await? {
+            print!("{plan}");
+        }
+    } else if args.sync_calendar {
+        // Skipping the cache also means syncing even if the feed is unchanged.
+        let force = !args.cache.is_enabled();
+        let report = sync_if_modified(&mbta, config, force).await?;
+        print!("{report}");
+        if report.has_errors() {
+            bail!("Some calendars failed to sync");
+        }
+    } else if let Some(dir) = &args.ics {
+        let alerts = mbtalerts::alerts(&mbta, &config.query, &args.cache).await?;
+        let summarizer = BedrockSummarizer::new(&config.summarizer).await;
+        let feeds = ics_feeds(
+            &mbta,
+            &alerts,
+            &config.effects,
+            &config.stations,
+            &TitleCache::new(summarizer.as_ref()),
+            &args.cache,
+        )
+        .await?;
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        for feed in &feeds {
+            feed.write(dir)?;
+        }
+        println!("Wrote {} feeds to {}", feeds.len(), dir.display());
+    } else if let Some(agenda) = args.agenda {
+        let alerts = mbtalerts::alerts(&mbta, &config.query, &args.cache).await?;
+        let summarizer = BedrockSummarizer::new(&config.summarizer).await;
+        print_agenda(
+            &mbta,
+            &alerts,
+            config,
+            summarizer.as_ref(),
+            &args.cache,
+            agenda,
+        )
+        .await?;
     } else {
-        let summarizer = BedrockSummarizer::from_env().await;
-        print_alerts(&alerts, summarizer.as_ref()).await;
+        let alerts = mbtalerts::alerts(&mbta, &config.query, &args.cache).await?;
+        let summarizer = BedrockSummarizer::new(&config.summarizer).await;
+        print_alerts(
+            &mbta,
+            &alerts,
+            config,
+            summarizer.as_ref(),
+            &args.cache,
+            args.format,
+        )
+        .await?;
     }
 
     Ok(())
@@ -141,76 +656,90 @@ mod test {
             .build()
     }
 
-    // --- format_dt ---
+    // --- args ---
 
     #[test]
-    fn test_format_dt_am() {
-        assert_eq!(format_dt("2024-01-15T10:30:00-05:00"), "1/15/2024 10:30am");
+    fn test_server_side_filters_conflict_with_sync() {
+        for (flag, value) in [("--severities", "7"), ("--active-at", "now")] {
+            for mode in ["--sync-calendar", "--watch"] {
+                assert!(RawArgs::try_parse_from(["mbtalerts", mode, flag, value]).is_err());
+            }
+            assert!(RawArgs::try_parse_from(["mbtalerts", flag, value]).is_ok());
+        }
     }
 
     #[test]
-    fn test_format_dt_pm() {
-        assert_eq!(format_dt("2024-01-15T14:45:00-05:00"), "1/15/2024 2:45pm");
+    fn test_modes_conflict_with_serve() -> anyhow::Result<()> {
+        for mode in [
+            &["--watch"][..],
+            &["--format", "json"],
+            &["--upcoming", "3"],
+        ] {
+            let args = [&["mbtalerts"], mode, &["serve"]].concat();
+            assert!(
+                RawArgs::try_parse_from(args)?
+                    .mode_beside_command()
+                    .is_some()
+            );
+        }
+        let serve = RawArgs::try_parse_from(["mbtalerts", "--stops", "Park Street", "serve"])?;
+        assert_eq!(serve.mode_beside_command(), None);
+        assert_eq!(
+            RawArgs::try_parse_from(["mbtalerts", "--watch"])?.mode_beside_command(),
+            None
+        );
+        Ok(())
     }
 
-    #[test]
-    fn test_format_dt_midnight() {
-        assert_eq!(format_dt("2024-01-15T00:00:00-05:00"), "1/15/2024 12:00am");
-    }
+    // --- format_dt ---
 
-    #[test]
-    fn test_format_dt_noon() {
-        assert_eq!(format_dt("2024-01-15T12:00:00-05:00"), "1/15/2024 12:00pm");
+    fn dt(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
     }
 
     #[test]
-    fn test_format_dt_invalid_passthrough() {
-        assert_eq!(format_dt("not-a-date"), "not-a-date");
+    fn test_format_dt_am() {
+        assert_eq!(
+            format_dt(dt("2024-01-15T10:30:00-05:00")),
+            "1/15/2024 10:30am"
+        );
     }
 
-    // --- alert_body ---
-
     #[test]
-    fn test_alert_body_keeps_first_sentence_when_title_is_ai_generated() {
-        // The AI title is unrelated to the first sentence, so stripping it would
-        // delete content that then appears nowhere in the output.
-        let mut alert = make_alert("Red", "SHUTTLE", None, None);
-        alert.attributes.header =
-            "Shuttle buses replace service. Expect delays of 20 minutes.".to_owned();
-
+    fn test_format_dt_pm() {
         assert_eq!(
-            alert_body(&alert, true),
-            "Shuttle buses replace service. Expect delays of 20 minutes."
+            format_dt(dt("2024-01-15T14:45:00-05:00")),
+            "1/15/2024 2:45pm"
         );
     }
 
     #[test]
-    fn test_alert_body_strips_first_sentence_when_it_is_the_title() {
-        let mut alert = make_alert("Red", "SHUTTLE", None, None);
-        alert.attributes.header =
-            "Shuttle buses replace service. Expect delays of 20 minutes.".to_owned();
-
-        assert_eq!(alert_body(&alert, false), "Expect delays of 20 minutes.");
+    fn test_format_dt_midnight() {
+        assert_eq!(
+            format_dt(dt("2024-01-15T00:00:00-05:00")),
+            "1/15/2024 12:00am"
+        );
     }
 
     #[test]
-    fn test_alert_body_keeps_header_when_summary_is_not_the_first_sentence() {
-        let mut alert = make_alert("Red", "DELAY", None, None);
-        alert.attributes.header =
-            "Red Line: Delays of about 20 minutes due to a signal problem.".to_owned();
-
+    fn test_format_dt_noon() {
         assert_eq!(
-            alert_body(&alert, false),
-            "Red Line: Delays of about 20 minutes due to a signal problem."
+            format_dt(dt("2024-01-15T12:00:00-05:00")),
+            "1/15/2024 12:00pm"
         );
     }
 
-    #[test]
-    fn test_alert_body_keeps_header_when_stripping_would_empty_it() {
-        let mut alert = make_alert("Red", "SHUTTLE", None, None);
-        alert.attributes.header = "Shuttle buses replace service.".to_owned();
+    // --- format_age ---
 
-        assert_eq!(alert_body(&alert, false), "Shuttle buses replace service.");
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(30)), "<1m");
+        assert_eq!(format_age(Duration::from_secs(5 * 60)), "5m");
+        assert_eq!(format_age(Duration::from_secs(125 * 60)), "2h 5m");
+        assert_eq!(
+            format_age(Duration::from_secs((3 * 24 + 4) * 3600)),
+            "3d 4h"
+        );
     }
 
     // --- format_alert ---
@@ -223,7 +752,7 @@ mod test {
             Some("2024-06-01T09:00:00-04:00"),
             Some("2024-06-01T23:00:00-04:00"),
         );
-        let output = format_alert(&alert, None).await;
+        let output = format_alert(&alert, None, &StopDirectory::default(), true).await;
         assert!(output.contains("DELAY"));
         assert!(output.contains("Red Line"));
         assert!(output.contains("6/1/2024 9:00am"));
@@ -238,12 +767,28 @@ mod test {
             .route("Orange")
             .effect("SUSPENSION")
             .build();
-        let output = format_alert(&alert, None).await;
+        let output = format_alert(&alert, None, &StopDirectory::default(), true).await;
         assert!(output.contains("SUSPENSION"));
         assert!(output.contains("Orange Line"));
         assert!(!output.contains('('));
     }
 
+    #[tokio::test]
+    async fn test_format_alert_lists_stations_and_direction() {
+        let alert = Alert::builder()
+            .header("Shuttle buses replace service")
+            .entity(mbtalerts::types::InformedEntity {
+                route: Some("Red".to_owned()),
+                stop: Some("place-jfk".to_owned()),
+                direction_id: Some(0),
+                ..Default::default()
+            })
+            .effect("SHUTTLE")
+            .build();
+        let output = format_alert(&alert, None, &StopDirectory::default(), true).await;
+        assert!(output.ends_with("\nStations: place-jfk\nDirection: Southbound"));
+    }
+
     #[tokio::test]
     async fn test_format_alert_green_line() {
         let alert = make_alert(
@@ -252,7 +797,7 @@ mod test {
             Some("2024-06-01T08:00:00-04:00"),
             Some("2024-06-01T20:00:00-04:00"),
         );
-        let output = format_alert(&alert, None).await;
+        let output = format_alert(&alert, None, &StopDirectory::default(), true).await;
         assert!(output.contains("Green Line"));
         assert!(output.contains("DETOUR"));
     }
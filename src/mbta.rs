@@ -1,13 +1,739 @@
-use jluszcz_rust_utils::query::http_get;
-use log::{debug, info};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::America::New_York;
+use jluszcz_rust_utils::query;
+use log::{debug, info, warn};
+use reqwest::header::{
+    ACCEPT, ETAG, HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::config::Config;
+use crate::filter::{parse_values, split_list};
+use crate::types::{Activity, Lifecycle, RouteType, Severity};
 
 const API_URL: &str = "https://api-v3.mbta.com";
+const API_KEY_HEADER: &str = "x-api-key";
 const ALERTS: &str = "alerts";
-const ROUTES: &str = "Red,Orange,Blue,Green-B,Green-C,Green-D,Green-E";
+const STOPS: &str = "stops";
+const FACILITIES: &str = "facilities";
+
+/// The rapid transit routes fetched when nothing else is configured.
+const SUBWAY_ROUTES: &[&str] = &[
+    "Red", "Orange", "Blue", "Green-B", "Green-C", "Green-D", "Green-E",
+];
+
+/// Which routes to fetch alerts for.
+///
+/// The API ANDs its filters together, so routes and route types can't be
+/// combined in one query: it's one or the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteFilter {
+    /// Explicit route IDs, e.g. `Red`, `Green-E`, `CR-Fitchburg`.
+    Routes(Vec<String>),
+    /// Every route of the given types.
+    RouteTypes(Vec<RouteType>),
+}
+
+impl Default for RouteFilter {
+    fn default() -> Self {
+        Self::Routes(SUBWAY_ROUTES.iter().map(|&r| r.to_owned()).collect())
+    }
+}
+
+impl RouteFilter {
+    /// Parses a comma-separated list of route IDs.
+    pub fn parse_routes(list: &str) -> Result<Self> {
+        let routes: Vec<String> = split_list(list).map(str::to_owned).collect();
+        if routes.is_empty() {
+            bail!("No routes given");
+        }
+        Ok(Self::Routes(routes))
+    }
+
+    /// Parses a comma-separated list of route types, by number or name.
+    pub fn parse_route_types(list: &str) -> Result<Self> {
+        let types = split_list(list)
+            .map(str::parse)
+            .collect::<Result<Vec<RouteType>>>()?;
+        if types.is_empty() {
+            bail!("No route types given");
+        }
+        Ok(Self::RouteTypes(types))
+    }
+
+    /// The query parameter selecting these routes.
+    pub fn query_param(&self) -> (&'static str, String) {
+        match self {
+            Self::Routes(routes) => ("filter[route]", routes.join(",")),
+            Self::RouteTypes(types) => (
+                "filter[route_type]",
+                types
+                    .iter()
+                    .map(|t| t.number().to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        }
+    }
+}
+
+/// The moment `filter[datetime]` asks about: alerts active then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertTime {
+    Now,
+    At(DateTime<FixedOffset>),
+}
+
+/// Parses `now`, an RFC 3339 timestamp (`2025-10-20T08:00:00-04:00`), or a
+/// Boston time without an offset (`2025-10-20T08:00`).
+impl FromStr for AlertTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("now") {
+            return Ok(Self::Now);
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self::At(time));
+        }
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M")
+            .ok()
+            .and_then(|time| New_York.from_local_datetime(&time).earliest())
+            .map(|time| Self::At(time.fixed_offset()))
+            .with_context(|| {
+                format!("Invalid time '{s}': expected 'now', RFC 3339, or YYYY-MM-DDTHH:MM")
+            })
+    }
+}
+
+impl AlertTime {
+    /// The moment itself, reading the clock for `Now`.
+    pub fn instant(&self) -> DateTime<Utc> {
+        match self {
+            Self::Now => Utc::now(),
+            Self::At(time) => time.to_utc(),
+        }
+    }
+
+    fn query_value(&self) -> String {
+        match self {
+            Self::Now => "NOW".to_owned(),
+            Self::At(time) => time.to_rfc3339(),
+        }
+    }
+}
+
+/// The alerts to ask the API for. Each field left at its default doesn't
+/// filter; the API ANDs together the ones that are set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlertQuery {
+    pub routes: RouteFilter,
+    /// Stop IDs, e.g. `place-pktrm`. Unlike [`crate::filter::StationFilter`],
+    /// no station names: the API matches IDs only.
+    pub stops: Vec<String>,
+    /// 0 or 1, in the GTFS sense of the routes' directions.
+    pub direction_id: Option<u8>,
+    /// Empty asks for every activity. The API's own default is boarding,
+    /// exiting and riding, which leaves out elevator and escalator outages.
+    pub activities: Vec<Activity>,
+    pub datetime: Option<AlertTime>,
+    pub lifecycles: Vec<Lifecycle>,
+    pub severities: Vec<Severity>,
+    /// Only alerts that are (or aren't) shown as a banner on mbta.com.
+    pub banner: Option<bool>,
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(T::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl AlertQuery {
+    pub fn new(routes: RouteFilter) -> Self {
+        Self {
+            routes,
+            ..Self::default()
+        }
+    }
+
+    /// Parses a comma-separated list of stop IDs.
+    pub fn parse_stops(list: &str) -> Vec<String> {
+        split_list(list).map(str::to_owned).collect()
+    }
+
+    /// Parses a comma-separated list of activities, e.g. `board,using-wheelchair`.
+    pub fn parse_activities(list: &str) -> Result<Vec<Activity>> {
+        let activities: Vec<Activity> = parse_values(list);
+        if let Some(activity) = activities.iter().find(|a| !a.is_known()) {
+            bail!("Unknown activity '{activity}'");
+        }
+        Ok(activities)
+    }
+
+    /// Parses a comma-separated list of lifecycles, e.g. `new,ongoing`.
+    pub fn parse_lifecycles(list: &str) -> Result<Vec<Lifecycle>> {
+        let lifecycles: Vec<Lifecycle> = parse_values(list);
+        if let Some(lifecycle) = lifecycles.iter().find(|l| !l.is_known()) {
+            bail!("Unknown lifecycle '{lifecycle}'");
+        }
+        Ok(lifecycles)
+    }
+
+    /// Parses a comma-separated list of severities, 0-10.
+    pub fn parse_severities(list: &str) -> Result<Vec<Severity>> {
+        split_list(list)
+            .map(|value| match value.parse::<u8>() {
+                Ok(n) if Severity(n).is_known() => Ok(Severity(n)),
+                _ => bail!("Invalid severity '{value}': expected 0-10"),
+            })
+            .collect()
+    }
+
+    /// The query parameters, in a stable order.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![self.routes.query_param()];
+        if !self.stops.is_empty() {
+            params.push(("filter[stop]", self.stops.join(",")));
+        }
+        if let Some(direction_id) = self.direction_id {
+            params.push(("filter[direction_id]", direction_id.to_string()));
+        }
+        params.push((
+            "filter[activity]",
+            if self.activities.is_empty() {
+                "ALL".to_owned()
+            } else {
+                join(&self.activities)
+            },
+        ));
+        if let Some(datetime) = &self.datetime {
+            params.push(("filter[datetime]", datetime.query_value()));
+        }
+        if !self.lifecycles.is_empty() {
+            params.push(("filter[lifecycle]", join(&self.lifecycles)));
+        }
+        if !self.severities.is_empty() {
+            params.push(("filter[severity]", join(&self.severities)));
+        }
+        if let Some(banner) = self.banner {
+            params.push(("filter[banner]", banner.to_string()));
+        }
+        params
+    }
+
+    /// The parameters as one string, e.g. `filter[route]=Red&filter[activity]=ALL`,
+    /// telling one query's responses from another's.
+    pub fn key(&self) -> String {
+        self.params()
+            .iter()
+            .map(|(param, value)| format!("{param}={value}"))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+/// How many times a request rate-limited with HTTP 429 is retried.
+const MAX_RETRIES: u32 = 3;
+/// The longest we'll wait on the rate limit before trying again.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+/// How long one connection to the alerts stream may stay open. The stream
+/// reconnects after, starting with a fresh `reset`.
+const STREAM_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+/// Below this many requests left in the window, each response is a warning.
+const LOW_REMAINING: u32 = 5;
+
+/// The API's `x-ratelimit-*` response headers: `limit` requests are allowed
+/// per window, `remaining` are left in this one, and it ends at `reset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset: DateTime<Utc>,
+}
+
+fn header<T: FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Some(Self {
+            limit: header(headers, "x-ratelimit-limit")?,
+            remaining: header(headers, "x-ratelimit-remaining")?,
+            reset: DateTime::from_timestamp(header(headers, "x-ratelimit-reset")?, 0)?,
+        })
+    }
+
+    /// How long until the window resets, if it's used up.
+    fn wait(&self, now: DateTime<Utc>) -> Option<Duration> {
+        if self.remaining > 0 {
+            return None;
+        }
+        (self.reset - now).to_std().ok()
+    }
+}
+
+/// How long to wait before retrying a request the API answered with 429:
+/// `retry-after` if given, else until the window resets, else exponential
+/// backoff from a second. Never longer than [`MAX_WAIT`].
+fn retry_delay(headers: &HeaderMap, attempt: u32, now: DateTime<Utc>) -> Duration {
+    let delay = header(headers, "retry-after")
+        .map(Duration::from_secs)
+        .or_else(|| RateLimit::from_headers(headers)?.wait(now))
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt));
+    delay.min(MAX_WAIT)
+}
+
+/// A response's `ETag` and `Last-Modified`, sent back as `If-None-Match` and
+/// `If-Modified-Since` so the API can answer 304 when nothing has changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let value = |name| Some(headers.get(name)?.to_str().ok()?.to_owned());
+        Self {
+            etag: value(ETAG),
+            last_modified: value(LAST_MODIFIED),
+        }
+    }
+
+    fn request_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let values = [
+            (IF_NONE_MATCH, &self.etag),
+            (IF_MODIFIED_SINCE, &self.last_modified),
+        ];
+        for (name, value) in values {
+            if let Some(value) = value.as_deref().and_then(|v| v.parse().ok()) {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+}
+
+/// The outcome of a conditional request.
+#[derive(Debug)]
+pub enum Fetched {
+    Modified {
+        body: String,
+        validators: Validators,
+    },
+    NotModified,
+}
+
+/// A client for the MBTA v3 API. Without an API key, requests are limited to
+/// 20 a minute per IP; with one, to 1000.
+pub struct MbtaClient {
+    client: &'static Client,
+    api_key: Option<String>,
+    /// The limits from the most recent response.
+    rate_limit: Mutex<Option<RateLimit>>,
+}
+
+impl MbtaClient {
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            client: query::http_client()?,
+            api_key: config
+                .mbta_api_key
+                .as_ref()
+                .map(|key| key.expose().to_owned()),
+            rate_limit: Mutex::new(None),
+        })
+    }
+
+    fn last_rate_limit(&self) -> Option<RateLimit> {
+        *self.rate_limit.lock().unwrap()
+    }
+
+    /// GETs `path`, waiting out the rate limit when the last response used it
+    /// up, and retrying when the API answers 429 anyway. `timeout` replaces the
+    /// shared client's, which is too short for a stream.
+    async fn send(
+        &self,
+        path: &str,
+        params: &(impl Serialize + ?Sized),
+        headers: HeaderMap,
+        timeout: Option<Duration>,
+    ) -> Result<Response> {
+        let url = format!("{API_URL}/{path}");
+        let mut attempt = 0;
+        loop {
+            if let Some(wait) = self
+                .last_rate_limit()
+                .and_then(|limit| limit.wait(Utc::now()))
+            {
+                let wait = wait.min(MAX_WAIT);
+                info!("MBTA API rate limit used up, waiting {}s", wait.as_secs());
+                sleep(wait).await;
+            }
+
+            let mut req = self.client.get(&url).query(params).headers(headers.clone());
+            if let Some(timeout) = timeout {
+                req = req.timeout(timeout);
+            }
+            if let Some(key) = &self.api_key {
+                req = req.header(API_KEY_HEADER, key);
+            }
+            let response = req
+                .send()
+                .await
+                .with_context(|| format!("Failed to query {url}"))?;
+
+            if let Some(limit) = RateLimit::from_headers(response.headers()) {
+                debug!(
+                    "MBTA API rate limit: {}/{} left until {}",
+                    limit.remaining, limit.limit, limit.reset
+                );
+                if limit.remaining < LOW_REMAINING {
+                    warn!(
+                        "MBTA API rate limit nearly used up: {} of {} requests left",
+                        limit.remaining, limit.limit
+                    );
+                }
+                *self.rate_limit.lock().unwrap() = Some(limit);
+            }
+
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+            if attempt == MAX_RETRIES {
+                bail!(
+                    "MBTA API rate limit exceeded after {MAX_RETRIES} retries{}",
+                    if self.api_key.is_none() {
+                        "; set MBTA_API_KEY for a higher limit"
+                    } else {
+                        ""
+                    }
+                );
+            }
+            let delay = retry_delay(response.headers(), attempt, Utc::now());
+            warn!(
+                "MBTA API rate limit exceeded, retrying in {}s",
+                delay.as_secs()
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn get(&self, path: &str, params: &[(&str, &str)]) -> Result<String> {
+        let response = self.send(path, params, HeaderMap::new(), None).await?;
+        Ok(response.error_for_status()?.text().await?)
+    }
+
+    pub async fn query_alerts(&self, query: &AlertQuery) -> Result<String> {
+        match self
+            .query_alerts_if_modified(query, &Validators::default())
+            .await?
+        {
+            Fetched::Modified { body, .. } => Ok(body),
+            Fetched::NotModified => bail!("MBTA API answered an unconditional request with 304"),
+        }
+    }
+
+    /// Fetches alerts unless they're unchanged since the response `previous`
+    /// came from.
+    pub async fn query_alerts_if_modified(
+        &self,
+        query: &AlertQuery,
+        previous: &Validators,
+    ) -> Result<Fetched> {
+        debug!("Fetching MBTA alerts for {query:?}");
+        let response = self
+            .send(ALERTS, &query.params(), previous.request_headers(), None)
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            info!("MBTA alerts unchanged");
+            return Ok(Fetched::NotModified);
+        }
+        let response = response.error_for_status()?;
+        let validators = Validators::from_headers(response.headers());
+        let body = response.text().await?;
+        info!("Fetched MBTA alerts");
+        Ok(Fetched::Modified { body, validators })
+    }
+
+    /// Opens the alerts feed as server-sent events: a `reset` with every
+    /// alert, then an `add`, `update` or `remove` as each one changes.
+    pub async fn stream_alerts(&self, query: &AlertQuery) -> Result<Response> {
+        debug!("Streaming MBTA alerts for {query:?}");
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+        let response = self
+            .send(ALERTS, &query.params(), headers, Some(STREAM_TIMEOUT))
+            .await?
+            .error_for_status()?;
+        info!("Streaming MBTA alerts");
+        Ok(response)
+    }
+
+    /// Fetches the stops in `ids` (comma-separated), with their parent stations.
+    pub async fn query_stops(&self, ids: &str) -> Result<String> {
+        debug!("Fetching MBTA stops");
+        let response = self
+            .get(STOPS, &[("filter[id]", ids), ("include", "parent_station")])
+            .await?;
+        info!("Fetched MBTA stops");
+        Ok(response)
+    }
+
+    /// Fetches the stops `route` serves, with their parent stations.
+    pub async fn query_route_stops(&self, route: &str) -> Result<String> {
+        debug!("Fetching MBTA stops for route {route}");
+        let response = self
+            .get(
+                STOPS,
+                &[("filter[route]", route), ("include", "parent_station")],
+            )
+            .await?;
+        info!("Fetched MBTA stops for route {route}");
+        Ok(response)
+    }
+
+    /// Fetches the facilities at the stations in `stop_ids` (comma-separated).
+    pub async fn query_facilities(&self, stop_ids: &str) -> Result<String> {
+        debug!("Fetching MBTA facilities");
+        let response = self.get(FACILITIES, &[("filter[stop]", stop_ids)]).await?;
+        info!("Fetched MBTA facilities");
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_is_subway_routes() {
+        assert_eq!(
+            RouteFilter::default().query_param(),
+            (
+                "filter[route]",
+                "Red,Orange,Blue,Green-B,Green-C,Green-D,Green-E".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_routes_trims_and_skips_empty() -> Result<()> {
+        assert_eq!(
+            RouteFilter::parse_routes(" CR-Fitchburg, Mattapan ,")?,
+            RouteFilter::Routes(vec!["CR-Fitchburg".to_owned(), "Mattapan".to_owned()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_routes_empty_errors() {
+        assert!(RouteFilter::parse_routes(" , ").is_err());
+    }
+
+    #[test]
+    fn test_route_types_query_param() -> Result<()> {
+        let filter = RouteFilter::parse_route_types("commuter-rail,4")?;
+        assert_eq!(
+            filter.query_param(),
+            ("filter[route_type]", "2,4".to_owned())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_route_types_unknown_errors() {
+        assert!(RouteFilter::parse_route_types("monorail").is_err());
+    }
+
+    #[test]
+    fn test_default_query_asks_for_every_activity() {
+        assert_eq!(
+            AlertQuery::new(RouteFilter::parse_routes("Red").unwrap()).key(),
+            "filter[route]=Red&filter[activity]=ALL"
+        );
+    }
+
+    #[test]
+    fn test_query_params() -> Result<()> {
+        let query = AlertQuery {
+            routes: RouteFilter::parse_routes("Red")?,
+            stops: AlertQuery::parse_stops("place-pktrm, 70061"),
+            direction_id: Some(1),
+            activities: AlertQuery::parse_activities("board,using-wheelchair")?,
+            datetime: Some("2025-10-20T08:00:00-04:00".parse()?),
+            lifecycles: AlertQuery::parse_lifecycles("new,ongoing-upcoming")?,
+            severities: AlertQuery::parse_severities("7,10")?,
+            banner: Some(false),
+        };
+        assert_eq!(
+            query.params(),
+            vec![
+                ("filter[route]", "Red".to_owned()),
+                ("filter[stop]", "place-pktrm,70061".to_owned()),
+                ("filter[direction_id]", "1".to_owned()),
+                ("filter[activity]", "BOARD,USING_WHEELCHAIR".to_owned()),
+                ("filter[datetime]", "2025-10-20T08:00:00-04:00".to_owned()),
+                ("filter[lifecycle]", "NEW,ONGOING_UPCOMING".to_owned()),
+                ("filter[severity]", "7,10".to_owned()),
+                ("filter[banner]", "false".to_owned()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_alert_time_parsing() -> Result<()> {
+        assert_eq!("NOW".parse::<AlertTime>()?, AlertTime::Now);
+        assert_eq!(AlertTime::Now.query_value(), "NOW");
+        assert!("tomorrow".parse::<AlertTime>().is_err());
+        assert_eq!(
+            "2025-12-20T08:00".parse::<AlertTime>()?,
+            "2025-12-20T08:00:00-05:00".parse()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_rejects_unknown_values() {
+        assert!(AlertQuery::parse_activities("board,skydive").is_err());
+        assert!(AlertQuery::parse_lifecycles("eventually").is_err());
+        assert!(AlertQuery::parse_severities("11").is_err());
+        assert!(AlertQuery::parse_severities("high").is_err());
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let headers = headers(&[
+            ("x-ratelimit-limit", "20"),
+            ("x-ratelimit-remaining", "3"),
+            ("x-ratelimit-reset", "1700000060"),
+        ]);
+        assert_eq!(
+            RateLimit::from_headers(&headers),
+            Some(RateLimit {
+                limit: 20,
+                remaining: 3,
+                reset: at(1_700_000_060),
+            })
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_missing_headers() {
+        let headers = headers(&[("x-ratelimit-limit", "20")]);
+        assert_eq!(RateLimit::from_headers(&headers), None);
+    }
+
+    #[test]
+    fn test_rate_limit_waits_only_when_used_up() {
+        let mut limit = RateLimit {
+            limit: 20,
+            remaining: 1,
+            reset: at(1_700_000_060),
+        };
+        assert_eq!(limit.wait(at(1_700_000_000)), None);
+
+        limit.remaining = 0;
+        assert_eq!(limit.wait(at(1_700_000_000)), Some(Duration::from_secs(60)));
+        // A reset already past means there's nothing to wait for.
+        assert_eq!(limit.wait(at(1_700_000_100)), None);
+    }
+
+    #[test]
+    fn test_retry_delay_prefers_retry_after() {
+        let headers = headers(&[
+            ("retry-after", "7"),
+            ("x-ratelimit-limit", "20"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000030"),
+        ]);
+        assert_eq!(
+            retry_delay(&headers, 0, at(1_700_000_000)),
+            Duration::from_secs(7)
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_waits_for_reset() {
+        let headers = headers(&[
+            ("x-ratelimit-limit", "20"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000030"),
+        ]);
+        assert_eq!(
+            retry_delay(&headers, 0, at(1_700_000_000)),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        let now = at(1_700_000_000);
+        assert_eq!(
+            retry_delay(&HeaderMap::new(), 0, now),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            retry_delay(&HeaderMap::new(), 2, now),
+            Duration::from_secs(4)
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let headers = headers(&[("retry-after", "3600")]);
+        assert_eq!(retry_delay(&headers, 0, at(1_700_000_000)), MAX_WAIT);
+    }
+
+    #[test]
+    fn test_validators_round_trip_as_conditional_headers() {
+        let validators = Validators::from_headers(&headers(&[
+            ("etag", "W/\"abc\""),
+            ("last-modified", "Tue, 14 Oct 2025 12:00:00 GMT"),
+        ]));
+        assert_eq!(
+            validators,
+            Validators {
+                etag: Some("W/\"abc\"".to_owned()),
+                last_modified: Some("Tue, 14 Oct 2025 12:00:00 GMT".to_owned()),
+            }
+        );
+
+        let request = validators.request_headers();
+        assert_eq!(request[IF_NONE_MATCH], "W/\"abc\"");
+        assert_eq!(request[IF_MODIFIED_SINCE], "Tue, 14 Oct 2025 12:00:00 GMT");
+    }
 
-pub async fn query_subway_alerts() -> anyhow::Result<String> {
-    debug!("Fetching MBTA subway alerts");
-    let response = http_get(&format!("{API_URL}/{ALERTS}"), &[("filter[route]", ROUTES)]).await?;
-    info!("Fetched MBTA subway alerts");
-    Ok(response)
+    #[test]
+    fn test_empty_validators_send_no_conditional_headers() {
+        assert!(Validators::default().request_headers().is_empty());
+    }
 }
@@ -0,0 +1,326 @@
+//! Alerts as data rather than terminal text, for piping into dashboards, `jq`
+//! and wiki pages.
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+use crate::accessibility::is_accessibility_alert;
+use crate::affected_details;
+use crate::stops::StopDirectory;
+use crate::summary::{LinePrefixMode, TitleCache, first_sentence, uses_first_sentence_summary};
+use crate::types::Alert;
+
+/// One alert as the CLI shows it: the computed title and line alongside the
+/// API's own fields.
+#[derive(Debug, Serialize)]
+pub struct AlertRecord {
+    pub id: String,
+    /// Bedrock's title when it's available, else the heuristic one.
+    pub title: String,
+    pub line: &'static str,
+    pub effect: String,
+    pub severity: u8,
+    pub lifecycle: String,
+    pub start: Option<DateTime<FixedOffset>>,
+    pub end: Option<DateTime<FixedOffset>>,
+    pub body: String,
+    /// Affected stations and direction, one line each.
+    pub details: Vec<String>,
+    pub url: Option<String>,
+}
+
+impl AlertRecord {
+    pub fn new(alert: &Alert, title: String, body: String, details: Vec<String>) -> Self {
+        let attributes = &alert.attributes;
+        Self {
+            id: alert.id.clone(),
+            title,
+            line: crate::line_name(alert),
+            effect: attributes.effect.as_str().to_owned(),
+            severity: attributes.severity.0,
+            lifecycle: attributes.lifecycle.as_str().to_owned(),
+            start: alert.period_start(),
+            end: alert.period_end(),
+            body,
+            details,
+            url: attributes.url.clone(),
+        }
+    }
+}
+
+/// The body to print beneath the title.
+///
+/// The header's first sentence is dropped only when it *is* the title. An
+/// AI-generated or accessibility title is unrelated to that sentence, so
+/// stripping it there would delete content that appears nowhere else in the
+/// output.
+pub fn alert_body(alert: &Alert, derived_title: bool) -> &str {
+    let header = &alert.attributes.header;
+
+    if derived_title || !uses_first_sentence_summary(alert) {
+        return header;
+    }
+
+    let first = first_sentence(header);
+    let rest = header[first.len()..].trim_start_matches(['.', ' ']);
+    if rest.is_empty() { header } else { rest }
+}
+
+/// Each of `alerts` with its title, without the line prefix the line field
+/// makes redundant.
+pub async fn alert_records(
+    alerts: &[&Alert],
+    titles: &TitleCache<'_>,
+    stops: &StopDirectory,
+) -> Vec<AlertRecord> {
+    let mut records = Vec::with_capacity(alerts.len());
+    for &alert in alerts {
+        let summary = titles.summary(alert, LinePrefixMode::Omit, stops).await;
+        let derived_title = summary.raw.is_some() || is_accessibility_alert(alert);
+        records.push(AlertRecord::new(
+            alert,
+            summary.display,
+            alert_body(alert, derived_title).to_owned(),
+            affected_details(alert, stops),
+        ));
+    }
+    records
+}
+
+/// A JSON array of the records.
+pub fn to_json(records: &[AlertRecord]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(records)?)
+}
+
+/// One JSON object per line.
+pub fn to_ndjson(records: &[AlertRecord]) -> Result<String> {
+    let lines = records
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    Ok(lines.join("\n"))
+}
+
+const CSV_HEADER: &[&str] = &[
+    "id",
+    "title",
+    "line",
+    "effect",
+    "severity",
+    "lifecycle",
+    "start",
+    "end",
+    "body",
+    "details",
+    "url",
+];
+
+/// Quotes a field when it holds a comma, quote or line break, per RFC 4180.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn rfc3339(time: Option<DateTime<FixedOffset>>) -> String {
+    time.map(|t| t.to_rfc3339()).unwrap_or_default()
+}
+
+/// A header row, then a row per record. Details are joined with "; ".
+pub fn to_csv(records: &[AlertRecord]) -> String {
+    let mut rows = vec![CSV_HEADER.join(",")];
+    for record in records {
+        let fields = [
+            record.id.clone(),
+            record.title.clone(),
+            record.line.to_owned(),
+            record.effect.clone(),
+            record.severity.to_string(),
+            record.lifecycle.clone(),
+            rfc3339(record.start),
+            rfc3339(record.end),
+            record.body.clone(),
+            record.details.join("; "),
+            record.url.clone().unwrap_or_default(),
+        ];
+        rows.push(
+            fields
+                .iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+    rows.join("\n")
+}
+
+/// Escapes what would break out of a table cell.
+fn markdown_cell(text: &str) -> String {
+    text.trim()
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+fn markdown_time(time: Option<DateTime<FixedOffset>>) -> String {
+    time.map(|t| t.format("%a %-m/%-d %-I:%M%P").to_string())
+        .unwrap_or_default()
+}
+
+/// A table with a row per record, the title linked to the alert's page when
+/// it has one.
+pub fn to_markdown(records: &[AlertRecord]) -> String {
+    let mut rows = vec![
+        "| Line | Alert | Effect | Start | End | Details |".to_owned(),
+        "| --- | --- | --- | --- | --- | --- |".to_owned(),
+    ];
+    for record in records {
+        let title = markdown_cell(&record.title);
+        let title = match &record.url {
+            Some(url) => format!("[{title}]({url})"),
+            None => title,
+        };
+        let details = std::iter::once(record.body.as_str())
+            .chain(record.details.iter().map(String::as_str))
+            .map(markdown_cell)
+            .collect::<Vec<_>>()
+            .join("<br>");
+        rows.push(format!(
+            "| {} | {title} | {} | {} | {} | {details} |",
+            record.line,
+            record.effect,
+            markdown_time(record.start),
+            markdown_time(record.end),
+        ));
+    }
+    rows.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn make_alert(effect: &str) -> Alert {
+        Alert::builder().route("Red").effect(effect).build()
+    }
+
+    fn record() -> AlertRecord {
+        let alert = Alert::builder()
+            .id("123")
+            .header("Shuttle buses replace Red Line service, between Alewife and Harvard.")
+            .effect("SHUTTLE")
+            .severity(7)
+            .route("Red")
+            .url("https://mbta.com/alerts/red")
+            .period(
+                Some("2025-10-24T20:45:00-04:00"),
+                Some("2025-10-27T02:30:00-04:00"),
+            )
+            .build();
+        AlertRecord::new(
+            &alert,
+            "Shuttles Alewife to Harvard".to_owned(),
+            "Riders should \"allow\" extra time | plan ahead.\nThanks".to_owned(),
+            vec!["Stations: Alewife, Davis".to_owned()],
+        )
+    }
+
+    // --- alert_body ---
+
+    #[test]
+    fn test_alert_body_keeps_first_sentence_when_title_is_ai_generated() {
+        // The AI title is unrelated to the first sentence, so stripping it would
+        // delete content that then appears nowhere in the output.
+        let mut alert = make_alert("SHUTTLE");
+        alert.attributes.header =
+            "Shuttle buses replace service. Expect delays of 20 minutes.".to_owned();
+
+        assert_eq!(
+            alert_body(&alert, true),
+            "Shuttle buses replace service. Expect delays of 20 minutes."
+        );
+    }
+
+    #[test]
+    fn test_alert_body_strips_first_sentence_when_it_is_the_title() {
+        let mut alert = make_alert("SHUTTLE");
+        alert.attributes.header =
+            "Shuttle buses replace service. Expect delays of 20 minutes.".to_owned();
+
+        assert_eq!(alert_body(&alert, false), "Expect delays of 20 minutes.");
+    }
+
+    #[test]
+    fn test_alert_body_keeps_header_when_summary_is_not_the_first_sentence() {
+        let mut alert = make_alert("DELAY");
+        alert.attributes.header =
+            "Red Line: Delays of about 20 minutes due to a signal problem.".to_owned();
+
+        assert_eq!(
+            alert_body(&alert, false),
+            "Red Line: Delays of about 20 minutes due to a signal problem."
+        );
+    }
+
+    #[test]
+    fn test_alert_body_keeps_header_when_stripping_would_empty_it() {
+        let mut alert = make_alert("SHUTTLE");
+        alert.attributes.header = "Shuttle buses replace service.".to_owned();
+
+        assert_eq!(alert_body(&alert, false), "Shuttle buses replace service.");
+    }
+
+    #[test]
+    fn test_record_fields() {
+        let record = record();
+        assert_eq!(record.line, "Red Line");
+        assert_eq!(record.effect, "SHUTTLE");
+        assert_eq!(record.severity, 7);
+        assert_eq!(
+            record.start.map(|t| t.to_rfc3339()).as_deref(),
+            Some("2025-10-24T20:45:00-04:00")
+        );
+    }
+
+    #[test]
+    fn test_ndjson_is_one_object_per_line() -> Result<()> {
+        let ndjson = to_ndjson(&[record(), record()])?;
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let value: serde_json::Value = serde_json::from_str(lines[0])?;
+        assert_eq!(value["title"], "Shuttles Alewife to Harvard");
+        assert_eq!(value["end"], "2025-10-27T02:30:00-04:00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let csv = to_csv(&[record()]);
+        let mut lines = csv.splitn(2, '\n');
+        assert_eq!(lines.next(), Some(CSV_HEADER.join(",").as_str()));
+        assert_eq!(
+            lines.next(),
+            Some(
+                "123,Shuttles Alewife to Harvard,Red Line,SHUTTLE,7,NEW,\
+                 2025-10-24T20:45:00-04:00,2025-10-27T02:30:00-04:00,\
+                 \"Riders should \"\"allow\"\" extra time | plan ahead.\nThanks\",\
+                 \"Stations: Alewife, Davis\",https://mbta.com/alerts/red"
+            )
+        );
+    }
+
+    #[test]
+    fn test_markdown_escapes_cells() {
+        let markdown = to_markdown(&[record()]);
+        let row = markdown.lines().nth(2).unwrap();
+        assert_eq!(
+            row,
+            "| Red Line | [Shuttles Alewife to Harvard](https://mbta.com/alerts/red) | SHUTTLE \
+             | Fri 10/24 8:45pm | Mon 10/27 2:30am \
+             | Riders should \"allow\" extra time \\| plan ahead.<br>Thanks<br>Stations: Alewife, Davis |"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! What a calendar sync did, for the CLI to print and the Lambda to return.
+
+use std::fmt;
+
+use log::warn;
+use serde::Serialize;
+use serde::ser::{SerializeStruct, Serializer};
+
+use crate::calendar::EventKey;
+
+/// Every calendar's part of one sync. Serialized with whether any calendar
+/// had errors.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// The MBTA feed hadn't changed since the last sync, so nothing was synced.
+    pub feed_unchanged: bool,
+    /// In calendar ID order.
+    pub calendars: Vec<CalendarReport>,
+}
+
+impl SyncReport {
+    pub fn unchanged() -> Self {
+        Self {
+            feed_unchanged: true,
+            calendars: Vec::new(),
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.calendars.iter().any(|c| !c.errors.is_empty())
+    }
+}
+
+impl Serialize for SyncReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut report = serializer.serialize_struct("SyncReport", 3)?;
+        report.serialize_field("feed_unchanged", &self.feed_unchanged)?;
+        report.serialize_field("has_errors", &self.has_errors())?;
+        report.serialize_field("calendars", &self.calendars)?;
+        report.end()
+    }
+}
+
+/// Events that met the same end. Serialized with how many there are.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Outcome {
+    pub events: Vec<EventKey>,
+}
+
+impl Outcome {
+    pub fn add(&mut self, key: EventKey) {
+        self.events.push(key);
+    }
+
+    pub fn count(&self) -> usize {
+        self.events.len()
+    }
+}
+
+impl Serialize for Outcome {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut outcome = serializer.serialize_struct("Outcome", 2)?;
+        outcome.serialize_field("count", &self.count())?;
+        outcome.serialize_field("events", &self.events)?;
+        outcome.end()
+    }
+}
+
+/// What a sync did to one calendar.
+#[derive(Debug, Default, Serialize)]
+pub struct CalendarReport {
+    pub calendar_id: String,
+    pub created: Outcome,
+    pub updated: Outcome,
+    /// Already up to date.
+    pub skipped: Outcome,
+    pub deleted: Outcome,
+    /// Writes that failed even after retrying; each one's error is in `errors`.
+    pub failed: Outcome,
+    pub errors: Vec<String>,
+    /// Alerts titled without Bedrock although it's configured, because it
+    /// failed for them.
+    pub bedrock_fallbacks: Vec<String>,
+}
+
+impl CalendarReport {
+    pub fn new(calendar_id: &str) -> Self {
+        Self {
+            calendar_id: calendar_id.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds `key` to the outcome `outcome` picks if `result` is a success, and
+    /// to `failed` otherwise.
+    pub fn record(
+        &mut self,
+        key: EventKey,
+        result: anyhow::Result<()>,
+        outcome: impl FnOnce(&mut Self) -> &mut Outcome,
+    ) {
+        match result {
+            Ok(()) => outcome(self).add(key),
+            Err(e) => {
+                warn!("{}: {e:#}", self.calendar_id);
+                self.errors.push(format!("{e:#}"));
+                self.failed.add(key);
+            }
+        }
+    }
+
+    /// A calendar whose sync failed before it did anything.
+    pub fn failed(calendar_id: &str, error: &anyhow::Error) -> Self {
+        Self {
+            errors: vec![format!("{error:#}")],
+            ..Self::new(calendar_id)
+        }
+    }
+}
+
+fn write_events(f: &mut fmt::Formatter<'_>, label: &str, outcome: &Outcome) -> fmt::Result {
+    if outcome.events.is_empty() {
+        return Ok(());
+    }
+    let events: Vec<String> = outcome.events.iter().map(EventKey::to_string).collect();
+    writeln!(f, "  {label}: {}", events.join(", "))
+}
+
+/// Counts per calendar, then what changed. Skipped events are only counted.
+impl fmt::Display for CalendarReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} created, {} updated, {} skipped, {} deleted",
+            self.calendar_id,
+            self.created.count(),
+            self.updated.count(),
+            self.skipped.count(),
+            self.deleted.count()
+        )?;
+        write_events(f, "created", &self.created)?;
+        write_events(f, "updated", &self.updated)?;
+        write_events(f, "deleted", &self.deleted)?;
+        write_events(f, "failed", &self.failed)?;
+        if !self.bedrock_fallbacks.is_empty() {
+            writeln!(
+                f,
+                "  Bedrock fell back for: {}",
+                self.bedrock_fallbacks.join(", ")
+            )?;
+        }
+        for error in &self.errors {
+            writeln!(f, "  error: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.feed_unchanged {
+            return writeln!(
+                f,
+                "MBTA alerts unchanged since the last sync; nothing synced"
+            );
+        }
+        for calendar in &self.calendars {
+            write!(f, "{calendar}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::calendar::PeriodId;
+
+    fn key(alert_id: &str, period: usize) -> EventKey {
+        EventKey {
+            alert_id: alert_id.to_owned(),
+            period: PeriodId::Index(period),
+        }
+    }
+
+    #[test]
+    fn test_calendar_report_display() {
+        let mut report = CalendarReport::new("red");
+        report.created.add(key("1", 0));
+        report.created.add(key("1", 1));
+        report.skipped.add(key("2", 0));
+        report.deleted.add(key("3", 0));
+        report.bedrock_fallbacks.push("1".to_owned());
+
+        assert_eq!(
+            report.to_string(),
+            "red: 2 created, 0 updated, 1 skipped, 1 deleted\n\
+             \x20 created: 1 period 0, 1 period 1\n\
+             \x20 deleted: 3 period 0\n\
+             \x20 Bedrock fell back for: 1\n"
+        );
+    }
+
+    #[test]
+    fn test_report_json_has_counts_and_ids() -> anyhow::Result<()> {
+        let mut calendar = CalendarReport::failed("red", &anyhow::anyhow!("403 Forbidden"));
+        calendar.updated.add(key("1", 2));
+        let report = SyncReport {
+            feed_unchanged: false,
+            calendars: vec![calendar],
+        };
+        assert!(report.has_errors());
+
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(json["has_errors"], true);
+        let red = &json["calendars"][0];
+        assert_eq!(red["updated"]["count"], 1);
+        assert_eq!(red["updated"]["events"][0]["alert_id"], "1");
+        assert_eq!(red["updated"]["events"][0]["period"], "2");
+        assert_eq!(red["errors"][0], "403 Forbidden");
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_counts_failures_apart() {
+        let mut report = CalendarReport::new("red");
+        report.record(key("1", 0), Ok(()), |report| &mut report.created);
+        report.record(
+            key("2", 0),
+            Err(anyhow::anyhow!("Failed to create the event for 2 period 0")),
+            |report| &mut report.created,
+        );
+
+        assert_eq!(report.created.events, vec![key("1", 0)]);
+        assert_eq!(report.failed.events, vec![key("2", 0)]);
+        assert_eq!(
+            report.errors,
+            vec!["Failed to create the event for 2 period 0"]
+        );
+    }
+
+    #[test]
+    fn test_unchanged_feed() {
+        let report = SyncReport::unchanged();
+        assert!(!report.has_errors());
+        assert_eq!(
+            report.to_string(),
+            "MBTA alerts unchanged since the last sync; nothing synced\n"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Retrying calendar requests that fail transiently: the calendar services'
+//! error responses are kept as [`StatusError`]s, so their status and
+//! `Retry-After` decide whether, and when, a request is made again.
+
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+
+/// How many times a request that failed transiently is retried, and the wait
+/// before the first retry, doubled for each one after.
+const MAX_RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest a `Retry-After` is waited out; the Lambda has a minute in all.
+const MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// A calendar service's answer to a request that failed.
+#[derive(Debug)]
+pub struct StatusError {
+    pub status: StatusCode,
+    /// How long the service asked to be left alone for.
+    pub retry_after: Option<Duration>,
+    url: String,
+    body: String,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} answered {}", self.url, self.status)?;
+        if !self.body.is_empty() {
+            write!(f, ": {}", self.body)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// `response`, if it's a success, else a [`StatusError`] for it.
+pub async fn check(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+        .map(Duration::from_secs);
+    let url = response.url().to_string();
+    let body = response.text().await.unwrap_or_default();
+    Err(StatusError {
+        status,
+        retry_after,
+        url,
+        body,
+    }
+    .into())
+}
+
+fn status(error: &anyhow::Error) -> Option<StatusCode> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<StatusError>())
+        .map(|e| e.status)
+}
+
+/// Whether `error` says what was asked for isn't there (404) or no longer is
+/// (410).
+fn is_gone(error: &anyhow::Error) -> bool {
+    status(error).is_some_and(|s| s == StatusCode::NOT_FOUND || s == StatusCode::GONE)
+}
+
+/// The outcome of deleting `event_id`, which is done if the event is gone.
+pub fn ignore_gone<T>(result: Result<T>, event_id: &str) -> Result<()> {
+    match result {
+        Err(e) if is_gone(&e) => {
+            debug!("Calendar event {event_id} was already gone");
+            Ok(())
+        }
+        result => result.map(drop),
+    }
+}
+
+/// Which failures a request may be retried after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    /// A request with the same effect however often it's made: retried when
+    /// the service is rate limiting (429), failed (5xx), or didn't answer.
+    Idempotent,
+    /// A create, which the service may have carried out although it failed
+    /// or timed out, so that making it again would duplicate the event. Only
+    /// retried when it can't have been: on 429, or when there was no
+    /// connection to send it on.
+    IfNotApplied,
+}
+
+/// How long to wait before retrying a request that failed with `error`, if
+/// it may be retried at all: `Retry-After` if the service gave one, else an
+/// exponential backoff.
+fn retry_delay(error: &anyhow::Error, retry: Retry, attempt: u32) -> Option<Duration> {
+    let backoff = RETRY_DELAY * (1 << attempt);
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<StatusError>() {
+            let transient = match retry {
+                Retry::Idempotent => {
+                    e.status == StatusCode::TOO_MANY_REQUESTS || e.status.is_server_error()
+                }
+                Retry::IfNotApplied => e.status == StatusCode::TOO_MANY_REQUESTS,
+            };
+            return transient.then(|| e.retry_after.unwrap_or(backoff).min(MAX_WAIT));
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            let transient = match retry {
+                Retry::Idempotent => e.is_connect() || e.is_timeout(),
+                Retry::IfNotApplied => e.is_connect(),
+            };
+            return transient.then_some(backoff);
+        }
+    }
+    None
+}
+
+/// Runs `request` until it succeeds, fails for good, or has been retried
+/// [`MAX_RETRIES`] times.
+pub async fn with_retries<T, F>(
+    what: &str,
+    retry: Retry,
+    mut request: impl FnMut() -> F,
+) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        let result = request().await;
+        let delay = match &result {
+            Err(e) if attempt < MAX_RETRIES => retry_delay(e, retry, attempt),
+            _ => None,
+        };
+        let Some(delay) = delay else {
+            return result.with_context(|| format!("Failed to {what}"));
+        };
+        if let Err(e) = &result {
+            warn!(
+                "Failed to {what}, retrying in {}ms: {e:#}",
+                delay.as_millis()
+            );
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn answered(status: StatusCode, retry_after: Option<u64>) -> anyhow::Error {
+        anyhow::Error::new(StatusError {
+            status,
+            retry_after: retry_after.map(Duration::from_secs),
+            url: "https://www.googleapis.com/calendar/v3/calendars/red/events".to_owned(),
+            body: String::new(),
+        })
+        .context("Failed to create the event for 1 period 0")
+    }
+
+    #[test]
+    fn test_retry_delay_honors_retry_after() {
+        let error = answered(StatusCode::TOO_MANY_REQUESTS, Some(7));
+        for retry in [Retry::Idempotent, Retry::IfNotApplied] {
+            assert_eq!(retry_delay(&error, retry, 0), Some(Duration::from_secs(7)));
+        }
+        let error = answered(StatusCode::SERVICE_UNAVAILABLE, Some(3600));
+        assert_eq!(retry_delay(&error, Retry::Idempotent, 0), Some(MAX_WAIT));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        let error = answered(StatusCode::INTERNAL_SERVER_ERROR, None);
+        assert_eq!(retry_delay(&error, Retry::Idempotent, 0), Some(RETRY_DELAY));
+        assert_eq!(
+            retry_delay(&error, Retry::Idempotent, 2),
+            Some(RETRY_DELAY * 4)
+        );
+    }
+
+    #[test]
+    fn test_creates_are_not_retried_after_server_errors() {
+        // The event may have been created before the service failed.
+        let error = answered(StatusCode::BAD_GATEWAY, None);
+        assert_eq!(retry_delay(&error, Retry::IfNotApplied, 0), None);
+    }
+
+    #[test]
+    fn test_client_errors_are_not_retried() {
+        for status in [StatusCode::BAD_REQUEST, StatusCode::FORBIDDEN] {
+            assert_eq!(
+                retry_delay(&answered(status, None), Retry::Idempotent, 0),
+                None
+            );
+        }
+        assert_eq!(
+            retry_delay(&anyhow::anyhow!("no such calendar"), Retry::Idempotent, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ignore_gone() {
+        for status in [StatusCode::NOT_FOUND, StatusCode::GONE] {
+            assert!(ignore_gone::<()>(Err(answered(status, None)), "e1").is_ok());
+        }
+        assert!(ignore_gone::<()>(Err(answered(StatusCode::FORBIDDEN, None)), "e1").is_err());
+        assert!(ignore_gone(Ok(()), "e1").is_ok());
+    }
+}
@@ -0,0 +1,209 @@
+//! The iCalendar feeds and the alerts as JSON over HTTP, refreshed from the
+//! MBTA API on an interval: subscribable calendars without Google service
+//! accounts or a Lambda.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use log::{debug, info, warn};
+use tokio::net::TcpListener;
+use tokio::time::sleep;
+
+use crate::ai::BedrockSummarizer;
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::ics::ics_feeds;
+use crate::mbta::{MbtaClient, Validators};
+use crate::output::{alert_records, to_json};
+use crate::summary::TitleCache;
+use crate::{alerts_if_modified, alerts_to_sync};
+
+/// Everything served, from one fetch of the alerts.
+#[derive(Debug, Default)]
+struct Snapshot {
+    /// iCalendar text by feed name, e.g. `red`.
+    feeds: BTreeMap<String, String>,
+    alerts_json: String,
+}
+
+/// Swapped whole by each refresh, so a request never sees half of one.
+type SharedSnapshot = Arc<RwLock<Snapshot>>;
+
+/// Fetches the alerts, serves them on `addr`, and checks for changes every
+/// `interval`. Only the first fetch has to succeed: after that, a failed
+/// refresh keeps serving what was last fetched.
+pub async fn serve(
+    mbta: &MbtaClient,
+    config: &Config,
+    cache: &Cache,
+    addr: SocketAddr,
+    interval: Duration,
+) -> Result<()> {
+    let summarizer = BedrockSummarizer::new(&config.summarizer).await;
+    let mut titles = TitleCache::new(summarizer.as_ref());
+    let snapshot = SharedSnapshot::default();
+    let mut validators = Validators::default();
+
+    refresh(mbta, config, &mut titles, cache, &mut validators, &snapshot).await?;
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {addr}"))?;
+    info!("Serving alerts on http://{addr}");
+    let server = axum::serve(listener, router(snapshot.clone()));
+
+    let refresher = async {
+        loop {
+            sleep(interval).await;
+            if let Err(e) =
+                refresh(mbta, config, &mut titles, cache, &mut validators, &snapshot).await
+            {
+                warn!("Failed to refresh alerts, still serving the last ones: {e:#}");
+            }
+        }
+    };
+
+    tokio::select! {
+        result = server => result.context("HTTP server failed"),
+        () = refresher => unreachable!("the refresher never returns"),
+    }
+}
+
+/// Re-renders the snapshot, unless the alerts are unchanged since the fetch
+/// `validators` came from. Alerts are only summarized when they're new or
+/// updated, once each however many feeds they're in.
+async fn refresh(
+    mbta: &MbtaClient,
+    config: &Config,
+    titles: &mut TitleCache<'_>,
+    cache: &Cache,
+    validators: &mut Validators,
+    snapshot: &SharedSnapshot,
+) -> Result<()> {
+    let Some((alerts, latest)) = alerts_if_modified(mbta, &config.query, validators).await? else {
+        debug!("MBTA alerts unchanged, keeping the served ones");
+        return Ok(());
+    };
+
+    titles.retain(&alerts.data);
+    let feeds = ics_feeds(
+        mbta,
+        &alerts,
+        &config.effects,
+        &config.stations,
+        titles,
+        cache,
+    )
+    .await?;
+    let (kept, stops) =
+        alerts_to_sync(mbta, &alerts, &[&config.effects], &config.stations, cache).await?;
+    let alerts_json = to_json(&alert_records(&kept, titles, &stops).await)?;
+
+    *snapshot.write().unwrap() = Snapshot {
+        feeds: feeds
+            .into_iter()
+            .map(|feed| (feed.name, feed.ics))
+            .collect(),
+        alerts_json,
+    };
+    *validators = latest;
+    info!("Refreshed served alerts");
+    Ok(())
+}
+
+fn router(snapshot: SharedSnapshot) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/alerts.json", get(alerts_json))
+        .route("/calendar/{file}", get(feed))
+        .with_state(snapshot)
+}
+
+/// The paths there are to fetch, one a line.
+async fn index(State(snapshot): State<SharedSnapshot>) -> String {
+    let snapshot = snapshot.read().unwrap();
+    let mut paths = vec!["/alerts.json".to_owned()];
+    paths.extend(
+        snapshot
+            .feeds
+            .keys()
+            .map(|name| format!("/calendar/{name}.ics")),
+    );
+    paths.join("\n") + "\n"
+}
+
+async fn alerts_json(State(snapshot): State<SharedSnapshot>) -> Response {
+    let json = snapshot.read().unwrap().alerts_json.clone();
+    ([(CONTENT_TYPE, "application/json")], json).into_response()
+}
+
+async fn feed(State(snapshot): State<SharedSnapshot>, Path(file): Path<String>) -> Response {
+    let snapshot = snapshot.read().unwrap();
+    match file
+        .strip_suffix(".ics")
+        .and_then(|name| snapshot.feeds.get(name))
+    {
+        Some(ics) => (
+            [(CONTENT_TYPE, "text/calendar; charset=utf-8")],
+            ics.clone(),
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, format!("No feed named {file}\n")).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot() -> SharedSnapshot {
+        Arc::new(RwLock::new(Snapshot {
+            feeds: BTreeMap::from([
+                ("green-e".to_owned(), "BEGIN:VCALENDAR\r\n".to_owned()),
+                ("red".to_owned(), "BEGIN:VCALENDAR\r\n".to_owned()),
+            ]),
+            alerts_json: "[]".to_owned(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_feed_is_served_as_calendar() {
+        let response = feed(State(snapshot()), Path("green-e.ics".to_owned())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CONTENT_TYPE],
+            "text/calendar; charset=utf-8"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_feed_is_not_found() {
+        for file in ["blue.ics", "red", "red.json"] {
+            let response = feed(State(snapshot()), Path(file.to_owned())).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{file}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_index_lists_paths() {
+        assert_eq!(
+            index(State(snapshot())).await,
+            "/alerts.json\n/calendar/green-e.ics\n/calendar/red.ics\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alerts_json_is_served_as_json() {
+        let response = alerts_json(State(snapshot())).await;
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    }
+}
@@ -0,0 +1,156 @@
+//! Stop and station names from the MBTA `/stops` endpoint, used to say which
+//! stations an alert affects, the stops on routes, and the elevators and
+//! escalators in stations from `/facilities`.
+
+use std::collections::HashMap;
+
+use crate::types::{Alert, Facilities, Facility, Stops};
+
+/// Names and parent stations for the stops alerts mention.
+///
+/// Lookups fall back to the raw stop ID, so an empty directory (say, because
+/// the `/stops` request failed) still renders something rather than nothing.
+#[derive(Debug, Default)]
+pub struct StopDirectory {
+    names: HashMap<String, String>,
+    parents: HashMap<String, String>,
+    /// The stops each looked-up route serves.
+    routes: HashMap<String, Vec<String>>,
+    facilities: HashMap<String, Facility>,
+}
+
+fn is_station_id(id: &str) -> bool {
+    id.starts_with("place-")
+}
+
+impl StopDirectory {
+    pub fn extend(&mut self, stops: Stops) {
+        for stop in stops.data.into_iter().chain(stops.included) {
+            if let Some(parent) = stop.parent_station() {
+                self.parents.insert(stop.id.clone(), parent.to_owned());
+            }
+            self.names.insert(stop.id, stop.attributes.name);
+        }
+    }
+
+    /// Adds the stops `route` serves, from a `/stops` response filtered by it.
+    pub fn extend_route(&mut self, route: &str, stops: Stops) {
+        let ids = stops.data.iter().map(|stop| stop.id.clone()).collect();
+        self.routes.insert(route.to_owned(), ids);
+        self.extend(stops);
+    }
+
+    /// The stops `route` serves, if they were looked up.
+    pub fn route_stops(&self, route: &str) -> Option<&[String]> {
+        self.routes.get(route).map(Vec::as_slice)
+    }
+
+    pub fn extend_facilities(&mut self, facilities: Facilities) {
+        for facility in facilities.data {
+            self.facilities.insert(facility.id.clone(), facility);
+        }
+    }
+
+    pub fn facility(&self, facility_id: &str) -> Option<&Facility> {
+        self.facilities.get(facility_id)
+    }
+
+    /// The parent station of `stop_id`, or `stop_id` itself when it is already
+    /// a station, has no parent, or is unknown.
+    pub fn station_id<'a>(&'a self, stop_id: &'a str) -> &'a str {
+        self.parents.get(stop_id).map_or(stop_id, String::as_str)
+    }
+
+    pub fn name<'a>(&'a self, stop_id: &'a str) -> &'a str {
+        self.names.get(stop_id).map_or(stop_id, String::as_str)
+    }
+
+    /// Distinct stations the alert touches, as stop IDs in first-seen order.
+    ///
+    /// Platforms resolve to their parent station. Subway alerts name the
+    /// stations alongside their platforms, so when a platform can't be resolved
+    /// it is dropped rather than listed as a bare ID next to its own station.
+    pub fn stations<'a>(&'a self, alert: &'a Alert) -> Vec<&'a str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for stop in alert.stop_ids() {
+            let station = self.station_id(stop);
+            if !ids.contains(&station) {
+                ids.push(station);
+            }
+        }
+        if ids.iter().any(|id| is_station_id(id)) {
+            ids.retain(|id| is_station_id(id) || self.names.contains_key(*id));
+        }
+        ids
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn directory() -> StopDirectory {
+        let stops: Stops = serde_json::from_str(
+            r#"{
+                "data": [
+                    {"id": "70075", "attributes": {"name": "Park Street"},
+                     "relationships": {"parent_station": {"data": {"id": "place-pktrm", "type": "stop"}}}},
+                    {"id": "6822", "attributes": {"name": "Washington St @ Elm St"},
+                     "relationships": {"parent_station": {"data": null}}}
+                ],
+                "included": [
+                    {"id": "place-pktrm", "attributes": {"name": "Park Street"},
+                     "relationships": {"parent_station": {"data": null}}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut directory = StopDirectory::default();
+        directory.extend(stops);
+        directory
+    }
+
+    #[test]
+    fn test_station_id_resolves_platform_to_parent() {
+        assert_eq!(directory().station_id("70075"), "place-pktrm");
+    }
+
+    #[test]
+    fn test_station_id_unknown_is_itself() {
+        assert_eq!(directory().station_id("70999"), "70999");
+    }
+
+    #[test]
+    fn test_name_falls_back_to_id() {
+        let directory = directory();
+        assert_eq!(directory.name("place-pktrm"), "Park Street");
+        assert_eq!(directory.name("place-nope"), "place-nope");
+    }
+
+    #[test]
+    fn test_stations_deduplicates_platforms_into_station() {
+        let alert = Alert::builder()
+            .stop("Red", "70075")
+            .stop("Red", "place-pktrm")
+            .build();
+        assert_eq!(directory().stations(&alert), vec!["place-pktrm"]);
+    }
+
+    #[test]
+    fn test_stations_drops_unresolved_platforms_beside_stations() {
+        let alert = Alert::builder()
+            .stop("Red", "70085")
+            .stop("Red", "place-asmnl")
+            .build();
+        assert_eq!(
+            StopDirectory::default().stations(&alert),
+            vec!["place-asmnl"]
+        );
+    }
+
+    #[test]
+    fn test_stations_keeps_bus_stops_without_parents() {
+        let alert = Alert::builder().stop("426", "6822").build();
+        assert_eq!(directory().stations(&alert), vec!["6822"]);
+    }
+}
@@ -0,0 +1,419 @@
+//! Following the alerts feed live. The API streams alert changes as
+//! server-sent events; [`AlertStream`] keeps an in-memory alert set current
+//! from them and hands out the changes in batches.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use log::{debug, info, warn};
+use reqwest::Response;
+use serde::Deserialize;
+use tokio::time::{Instant, sleep, timeout_at};
+
+use crate::mbta::{AlertQuery, MbtaClient};
+use crate::types::{Alert, Alerts};
+
+/// How long a batch stays open after its first change, so that a burst of
+/// updates is handled together.
+const BATCH_WINDOW: Duration = Duration::from_secs(10);
+/// Consecutive failed connections before giving up.
+const MAX_RECONNECTS: u32 = 5;
+const MAX_RECONNECT_WAIT: Duration = Duration::from_secs(60);
+
+/// One server-sent event: its type and its (possibly multi-line) data.
+#[derive(Debug, PartialEq, Eq)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+/// Splits a `text/event-stream` body into events as its chunks arrive. Chunks
+/// needn't end on a line, or even a character, boundary.
+#[derive(Debug, Default)]
+struct SseParser {
+    buf: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feeds a chunk of the stream, returning the events it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: std::mem::take(&mut self.event),
+                        data: self.data.join("\n"),
+                    });
+                    self.data.clear();
+                }
+                self.event.clear();
+                continue;
+            }
+            // Lines starting with a colon are comments, sent as keep-alives.
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_owned(),
+                "data" => self.data.push(value.to_owned()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// What a `remove` event carries: just the resource's identity.
+#[derive(Debug, Deserialize)]
+struct ResourceId {
+    id: String,
+}
+
+#[derive(Debug)]
+enum AlertEvent {
+    /// Every current alert, sent when the stream opens.
+    Reset(Vec<Alert>),
+    Add(Alert),
+    Update(Alert),
+    Remove(String),
+}
+
+impl AlertEvent {
+    /// `None` for event types that don't change alerts.
+    fn parse(sse: &SseEvent) -> Result<Option<Self>> {
+        Ok(Some(match sse.event.as_str() {
+            "reset" => Self::Reset(serde_json::from_str(&sse.data)?),
+            "add" => Self::Add(serde_json::from_str(&sse.data)?),
+            "update" => Self::Update(serde_json::from_str(&sse.data)?),
+            "remove" => Self::Remove(serde_json::from_str::<ResourceId>(&sse.data)?.id),
+            other => {
+                debug!("Ignoring {other:?} event in the alert stream");
+                return Ok(None);
+            }
+        }))
+    }
+}
+
+/// A change to the alert set. Added and updated alerts are in the set, by ID;
+/// removed ones are handed over.
+#[derive(Debug)]
+pub enum AlertChange {
+    Added(String),
+    Updated(String),
+    Removed(Box<Alert>),
+}
+
+/// The current alerts, as the stream has described them so far.
+#[derive(Debug)]
+struct AlertSet {
+    alerts: Alerts,
+}
+
+impl Default for AlertSet {
+    fn default() -> Self {
+        Self {
+            alerts: Alerts { data: Vec::new() },
+        }
+    }
+}
+
+impl AlertSet {
+    fn position(&self, id: &str) -> Option<usize> {
+        self.alerts.data.iter().position(|alert| alert.id == id)
+    }
+
+    fn apply(&mut self, event: AlertEvent) -> Vec<AlertChange> {
+        match event {
+            // A reset after a reconnect mostly repeats what we have, so it's
+            // diffed against the set rather than reported wholesale.
+            AlertEvent::Reset(alerts) => {
+                let mut previous: HashMap<String, Alert> = self
+                    .alerts
+                    .data
+                    .drain(..)
+                    .map(|alert| (alert.id.clone(), alert))
+                    .collect();
+
+                let mut changes = Vec::new();
+                for alert in &alerts {
+                    match previous.remove(&alert.id) {
+                        None => changes.push(AlertChange::Added(alert.id.clone())),
+                        Some(old) if old.attributes.updated_at != alert.attributes.updated_at => {
+                            changes.push(AlertChange::Updated(alert.id.clone()));
+                        }
+                        Some(_) => {}
+                    }
+                }
+                changes.extend(
+                    previous
+                        .into_values()
+                        .map(|alert| AlertChange::Removed(Box::new(alert))),
+                );
+
+                self.alerts.data = alerts;
+                changes
+            }
+            AlertEvent::Add(alert) | AlertEvent::Update(alert) => {
+                let id = alert.id.clone();
+                match self.position(&id) {
+                    Some(i) => {
+                        self.alerts.data[i] = alert;
+                        vec![AlertChange::Updated(id)]
+                    }
+                    None => {
+                        self.alerts.data.push(alert);
+                        vec![AlertChange::Added(id)]
+                    }
+                }
+            }
+            AlertEvent::Remove(id) => match self.position(&id) {
+                Some(i) => vec![AlertChange::Removed(Box::new(self.alerts.data.remove(i)))],
+                None => Vec::new(),
+            },
+        }
+    }
+}
+
+/// A live view of the alerts for a query, reconnecting when the stream
+/// drops.
+pub struct AlertStream<'a> {
+    mbta: &'a MbtaClient,
+    query: &'a AlertQuery,
+    set: AlertSet,
+    connection: Option<(Response, SseParser)>,
+    pending: VecDeque<AlertEvent>,
+    failures: u32,
+}
+
+impl<'a> AlertStream<'a> {
+    pub fn new(mbta: &'a MbtaClient, query: &'a AlertQuery) -> Self {
+        Self {
+            mbta,
+            query,
+            set: AlertSet::default(),
+            connection: None,
+            pending: VecDeque::new(),
+            failures: 0,
+        }
+    }
+
+    /// Every current alert.
+    pub fn alerts(&self) -> &Alerts {
+        &self.set.alerts
+    }
+
+    /// Waits for the alerts to change, then returns the changes made within
+    /// [`BATCH_WINDOW`] of the first. The first batch is every alert, added.
+    ///
+    /// Fails only once the stream can't be reopened after several attempts.
+    pub async fn next_batch(&mut self) -> Result<Vec<AlertChange>> {
+        let mut changes = Vec::new();
+        let mut deadline = None;
+        loop {
+            match self.next_event(deadline).await {
+                Ok(Some(event)) => {
+                    self.failures = 0;
+                    let applied = self.set.apply(event);
+                    if !applied.is_empty() && deadline.is_none() {
+                        deadline = Some(Instant::now() + BATCH_WINDOW);
+                    }
+                    changes.extend(applied);
+                }
+                Ok(None) => return Ok(changes),
+                Err(e) => {
+                    self.connection = None;
+                    self.pending.clear();
+                    // Hand over what we have; the next call reconnects.
+                    if !changes.is_empty() {
+                        warn!("MBTA alert stream interrupted: {e:#}");
+                        return Ok(changes);
+                    }
+
+                    self.failures += 1;
+                    if self.failures > MAX_RECONNECTS {
+                        return Err(e.context("MBTA alert stream failed"));
+                    }
+                    let wait = Duration::from_secs(1 << self.failures).min(MAX_RECONNECT_WAIT);
+                    warn!(
+                        "MBTA alert stream interrupted, reconnecting in {}s: {e:#}",
+                        wait.as_secs()
+                    );
+                    sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    /// The next alert event, connecting first if need be. `None` once
+    /// `deadline` passes.
+    async fn next_event(&mut self, deadline: Option<Instant>) -> Result<Option<AlertEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            if self.connection.is_none() {
+                let response = self.mbta.stream_alerts(self.query).await?;
+                info!("Connected to the MBTA alert stream");
+                self.connection = Some((response, SseParser::default()));
+            }
+            let (response, parser) = self.connection.as_mut().expect("connected above");
+
+            let chunk = match deadline {
+                Some(deadline) => match timeout_at(deadline, response.chunk()).await {
+                    Ok(chunk) => chunk?,
+                    Err(_) => return Ok(None),
+                },
+                None => response.chunk().await?,
+            };
+            let Some(chunk) = chunk else {
+                bail!("MBTA alert stream closed");
+            };
+
+            for sse in parser.push(&chunk) {
+                if let Some(event) = AlertEvent::parse(&sse)? {
+                    self.pending.push_back(event);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sse(event: &str, data: &str) -> SseEvent {
+        SseEvent {
+            event: event.to_owned(),
+            data: data.to_owned(),
+        }
+    }
+
+    fn alert(id: &str) -> Alert {
+        Alert::builder().id(id).build()
+    }
+
+    fn ids(set: &AlertSet) -> Vec<&str> {
+        set.alerts.data.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    fn describe(changes: &[AlertChange]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|change| match change {
+                AlertChange::Added(id) => format!("+{id}"),
+                AlertChange::Updated(id) => format!("~{id}"),
+                AlertChange::Removed(alert) => format!("-{}", alert.id),
+            })
+            .collect()
+    }
+
+    // --- SseParser ---
+
+    #[test]
+    fn test_parser_splits_events() {
+        let mut parser = SseParser::default();
+        assert_eq!(
+            parser.push(b"event: add\ndata: {\"id\": \"1\"}\n\nevent: remove\ndata: 2\n\n"),
+            vec![sse("add", "{\"id\": \"1\"}"), sse("remove", "2")]
+        );
+    }
+
+    #[test]
+    fn test_parser_handles_split_chunks_and_crlf() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: upd").is_empty());
+        assert!(parser.push(b"ate\r\ndata: a\r\n").is_empty());
+        assert_eq!(parser.push(b"\r\n"), vec![sse("update", "a")]);
+    }
+
+    #[test]
+    fn test_parser_handles_split_characters() {
+        let mut parser = SseParser::default();
+        let bytes = "data: café\n\n".as_bytes();
+        let (first, rest) = bytes.split_at(10);
+        assert!(parser.push(first).is_empty());
+        assert_eq!(parser.push(rest), vec![sse("", "café")]);
+    }
+
+    #[test]
+    fn test_parser_joins_data_lines_and_skips_comments() {
+        let mut parser = SseParser::default();
+        assert_eq!(
+            parser.push(b": keep-alive\n\nevent: reset\ndata: [\ndata: ]\n\n"),
+            vec![sse("reset", "[\n]")]
+        );
+    }
+
+    // --- AlertEvent ---
+
+    #[test]
+    fn test_parse_remove_event() -> Result<()> {
+        let event = AlertEvent::parse(&sse("remove", r#"{"id": "42", "type": "alert"}"#))?;
+        assert!(matches!(event, Some(AlertEvent::Remove(id)) if id == "42"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ignores_other_events() -> Result<()> {
+        assert!(AlertEvent::parse(&sse("message", "hello"))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bad_data_errors() {
+        assert!(AlertEvent::parse(&sse("add", "not json")).is_err());
+    }
+
+    // --- AlertSet ---
+
+    #[test]
+    fn test_add_update_remove() {
+        let mut set = AlertSet::default();
+        assert_eq!(describe(&set.apply(AlertEvent::Add(alert("1")))), ["+1"]);
+        assert_eq!(describe(&set.apply(AlertEvent::Update(alert("1")))), ["~1"]);
+        assert_eq!(describe(&set.apply(AlertEvent::Add(alert("2")))), ["+2"]);
+        assert_eq!(
+            describe(&set.apply(AlertEvent::Remove("1".to_owned()))),
+            ["-1"]
+        );
+        assert!(set.apply(AlertEvent::Remove("1".to_owned())).is_empty());
+        assert_eq!(ids(&set), ["2"]);
+    }
+
+    #[test]
+    fn test_reset_diffs_against_current_alerts() {
+        let mut set = AlertSet::default();
+        assert_eq!(
+            describe(&set.apply(AlertEvent::Reset(vec![alert("1"), alert("2")]))),
+            ["+1", "+2"]
+        );
+
+        let mut updated = alert("2");
+        updated.attributes.updated_at += chrono::Duration::hours(1);
+        let changes = set.apply(AlertEvent::Reset(vec![alert("3"), updated]));
+        assert_eq!(describe(&changes), ["+3", "~2", "-1"]);
+        assert_eq!(ids(&set), ["3", "2"]);
+    }
+
+    #[test]
+    fn test_reset_with_nothing_new_changes_nothing() {
+        let mut set = AlertSet::default();
+        set.apply(AlertEvent::Reset(vec![alert("1")]));
+        assert!(set.apply(AlertEvent::Reset(vec![alert("1")])).is_empty());
+    }
+}
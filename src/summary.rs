@@ -1,7 +1,13 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use chrono::{DateTime, FixedOffset};
 use log::warn;
 
-use crate::ai::BedrockSummarizer;
-use crate::types::Alert;
+use crate::accessibility::accessibility_title;
+use crate::ai::{BedrockSummarizer, Summarize};
+use crate::stops::StopDirectory;
+use crate::types::{Alert, Effect};
 
 #[derive(Clone, Copy)]
 pub enum LinePrefixMode {
@@ -11,27 +17,30 @@ pub enum LinePrefixMode {
 
 /// Longest text before a colon still treated as a line prefix. Guards against
 /// stripping a whole sentence that happens to mention "Line" and contain a colon
-/// (the real prefixes are short: "Red Line", "Green Line B, Green Line C").
+/// (the real prefixes are short: "Red Line", "Green Line B, Green Line C",
+/// "Red Line Braintree Branch").
 const MAX_LINE_PREFIX_LEN: usize = 35;
 
 pub fn strip_line_prefix(header: &str) -> &str {
     if let Some(colon_idx) = header.find(": ") {
         let prefix = &header[..colon_idx];
-        if prefix.contains("Line") && prefix.len() <= MAX_LINE_PREFIX_LEN {
+        if (prefix.contains("Line") || prefix.contains("Trolley"))
+            && prefix.len() <= MAX_LINE_PREFIX_LEN
+        {
             return header[colon_idx + 2..].trim_start();
         }
     }
     header.trim_start()
 }
 
-pub fn effect_label(effect: &str) -> Option<&str> {
+pub fn effect_label(effect: &Effect) -> Option<&'static str> {
     match effect {
-        "SHUTTLE" => Some("Shuttle"),
-        "DELAY" => Some("Delay"),
-        "SUSPENSION" => Some("Suspension"),
-        "SERVICE_CHANGE" => Some("Service change"),
-        "SCHEDULE_CHANGE" => Some("Schedule change"),
-        "DETOUR" => Some("Detour"),
+        Effect::Shuttle => Some("Shuttle"),
+        Effect::Delay => Some("Delay"),
+        Effect::Suspension => Some("Suspension"),
+        Effect::ServiceChange => Some("Service change"),
+        Effect::ScheduleChange => Some("Schedule change"),
+        Effect::Detour => Some("Detour"),
         _ => None,
     }
 }
@@ -121,7 +130,7 @@ fn apply_line_prefix(raw: &str, alert: &Alert, mode: LinePrefixMode) -> String {
 
 pub fn event_summary(alert: &Alert, line_prefix: LinePrefixMode) -> String {
     let content = strip_line_prefix(&alert.attributes.header);
-    if alert.attributes.effect == "DELAY"
+    if alert.attributes.effect == Effect::Delay
         && let Some(duration) = delay_duration_phrase(content)
     {
         return match line_prefix {
@@ -151,7 +160,7 @@ pub fn event_summary(alert: &Alert, line_prefix: LinePrefixMode) -> String {
 /// i.e. when no structured format (delay duration or location phrase) applies.
 pub fn uses_first_sentence_summary(alert: &Alert) -> bool {
     let content = strip_line_prefix(&alert.attributes.header);
-    if alert.attributes.effect == "DELAY" && delay_duration_phrase(content).is_some() {
+    if alert.attributes.effect == Effect::Delay && delay_duration_phrase(content).is_some() {
         return false;
     }
     if effect_label(&alert.attributes.effect).is_some() && location_phrase(content).is_some() {
@@ -160,6 +169,7 @@ pub fn uses_first_sentence_summary(alert: &Alert) -> bool {
     true
 }
 
+#[derive(Clone)]
 pub struct AlertSummary {
     /// AI-generated summary without the line prefix, when Bedrock produced one.
     pub raw: Option<String>,
@@ -167,28 +177,102 @@ pub struct AlertSummary {
     pub display: String,
 }
 
+/// An alert's title given Bedrock's for it, if there is one.
+fn summary_from(raw: Option<String>, alert: &Alert, line_prefix: LinePrefixMode) -> AlertSummary {
+    match raw {
+        Some(raw) => AlertSummary {
+            display: apply_line_prefix(&raw, alert, line_prefix),
+            raw: Some(raw),
+        },
+        None => AlertSummary {
+            raw: None,
+            display: event_summary(alert, line_prefix),
+        },
+    }
+}
+
+async fn generate(summarizer: &impl Summarize, alert: &Alert) -> Option<String> {
+    summarizer
+        .generate_summary(&alert.attributes.header)
+        .await
+        .inspect_err(|e| warn!("Bedrock inference failed for alert {}: {e:#}", alert.id))
+        .ok()
+}
+
+/// The title for an alert: a fixed format for accessibility outages, else
+/// Bedrock's when it's available, else [`event_summary`]'s.
 pub async fn generate_or_fallback(
-    summarizer: Option<&BedrockSummarizer>,
+    summarizer: Option<&impl Summarize>,
     alert: &Alert,
     line_prefix: LinePrefixMode,
+    stops: &StopDirectory,
 ) -> AlertSummary {
-    if let Some(s) = summarizer {
-        match s.generate_summary(&alert.attributes.header).await {
-            Ok(raw) => {
-                let display = apply_line_prefix(&raw, alert, line_prefix);
-                return AlertSummary {
-                    raw: Some(raw),
-                    display,
-                };
-            }
-            Err(e) => {
-                warn!("Bedrock inference failed for alert {}: {e:#}", alert.id);
-            }
+    if let Some(display) = accessibility_title(alert, stops) {
+        return AlertSummary { raw: None, display };
+    }
+    let raw = match summarizer {
+        Some(s) => generate(s, alert).await,
+        None => None,
+    };
+    summary_from(raw, alert, line_prefix)
+}
+
+/// An alert's ID, and when it was last updated.
+type Revision = (String, DateTime<FixedOffset>);
+
+/// Bedrock's titles, kept by alert and revision, so that an alert titled in
+/// several places is summarized once, and only again once it's updated.
+pub struct TitleCache<'a> {
+    summarizer: Option<&'a BedrockSummarizer>,
+    /// `None` where Bedrock failed.
+    titles: Mutex<HashMap<Revision, Option<String>>>,
+}
+
+impl<'a> TitleCache<'a> {
+    pub fn new(summarizer: Option<&'a BedrockSummarizer>) -> Self {
+        Self {
+            summarizer,
+            titles: Mutex::default(),
+        }
+    }
+
+    /// Forgets the titles of revisions other than `alerts`', and Bedrock's
+    /// failures, so they're asked about again.
+    pub fn retain(&mut self, alerts: &[Alert]) {
+        let current: HashSet<(&str, DateTime<FixedOffset>)> = alerts
+            .iter()
+            .map(|alert| (alert.id.as_str(), alert.attributes.updated_at))
+            .collect();
+        self.titles
+            .get_mut()
+            .unwrap()
+            .retain(|(id, updated_at), raw| {
+                raw.is_some() && current.contains(&(id.as_str(), *updated_at))
+            });
+    }
+
+    /// Like [`generate_or_fallback`], asking Bedrock only about revisions it
+    /// hasn't been asked about.
+    pub async fn summary(
+        &self,
+        alert: &Alert,
+        line_prefix: LinePrefixMode,
+        stops: &StopDirectory,
+    ) -> AlertSummary {
+        if let Some(display) = accessibility_title(alert, stops) {
+            return AlertSummary { raw: None, display };
         }
+        summary_from(self.raw(alert).await, alert, line_prefix)
     }
-    AlertSummary {
-        raw: None,
-        display: event_summary(alert, line_prefix),
+
+    async fn raw(&self, alert: &Alert) -> Option<String> {
+        let key = (alert.id.clone(), alert.attributes.updated_at);
+        if let Some(raw) = self.titles.lock().unwrap().get(&key) {
+            return raw.clone();
+        }
+        let raw = generate(self.summarizer?, alert).await;
+        self.titles.lock().unwrap().insert(key, raw.clone());
+        raw
     }
 }
 
@@ -209,27 +293,43 @@ mod test {
 
     #[test]
     fn test_effect_label_shuttle() {
-        assert_eq!(effect_label("SHUTTLE"), Some("Shuttle"));
+        assert_eq!(effect_label(&Effect::Shuttle), Some("Shuttle"));
     }
 
     #[test]
     fn test_effect_label_service_change() {
-        assert_eq!(effect_label("SERVICE_CHANGE"), Some("Service change"));
+        assert_eq!(effect_label(&Effect::ServiceChange), Some("Service change"));
     }
 
     #[test]
     fn test_effect_label_delay() {
-        assert_eq!(effect_label("DELAY"), Some("Delay"));
+        assert_eq!(effect_label(&Effect::Delay), Some("Delay"));
     }
 
     #[test]
     fn test_effect_label_unknown_returns_none() {
-        assert_eq!(effect_label("STATION_ISSUE"), None);
-        assert_eq!(effect_label("SOME_NEW_EFFECT"), None);
+        assert_eq!(effect_label(&Effect::StationIssue), None);
+        assert_eq!(effect_label(&Effect::from("SOME_NEW_EFFECT")), None);
     }
 
     // --- first_sentence ---
 
+    #[test]
+    fn test_strip_line_prefix_branch() {
+        assert_eq!(
+            strip_line_prefix("Red Line Braintree Branch: Delays of about 10 minutes"),
+            "Delays of about 10 minutes"
+        );
+    }
+
+    #[test]
+    fn test_strip_line_prefix_trolley() {
+        assert_eq!(
+            strip_line_prefix("Mattapan Trolley: Shuttle buses replace trolleys"),
+            "Shuttle buses replace trolleys"
+        );
+    }
+
     #[test]
     fn test_first_sentence_multi_sentence() {
         assert_eq!(
@@ -424,4 +524,44 @@ mod test {
         let alert = make_alert("Orange", "STATION_ISSUE");
         assert!(uses_first_sentence_summary(&alert));
     }
+
+    // --- TitleCache ---
+
+    fn cache_title(titles: &TitleCache<'_>, alert: &Alert, raw: Option<&str>) {
+        titles.titles.lock().unwrap().insert(
+            (alert.id.clone(), alert.attributes.updated_at),
+            raw.map(str::to_owned),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_title_cache_reuses_titles_for_a_revision() {
+        let alert = make_alert("Red", "SHUTTLE");
+        let titles = TitleCache::new(None);
+        cache_title(&titles, &alert, Some("Shuttles Replace Trains"));
+
+        let stops = StopDirectory::default();
+        let summary = titles
+            .summary(&alert, LinePrefixMode::Include, &stops)
+            .await;
+        assert_eq!(summary.display, "[Red Line] Shuttles Replace Trains");
+        assert_eq!(summary.raw.as_deref(), Some("Shuttles Replace Trains"));
+    }
+
+    #[test]
+    fn test_title_cache_forgets_updated_alerts_and_failures() {
+        let shuttle = make_alert("Red", "SHUTTLE");
+        let mut delay = make_alert("Orange", "DELAY");
+        delay.id = "alert-43".to_owned();
+        let mut titles = TitleCache::new(None);
+        cache_title(&titles, &shuttle, Some("Shuttles Replace Trains"));
+        cache_title(&titles, &delay, None);
+        let mut updated = make_alert("Red", "SHUTTLE");
+        updated.attributes.updated_at += chrono::Duration::minutes(5);
+        cache_title(&titles, &updated, Some("Shuttles Extended"));
+
+        titles.retain(&[updated, delay]);
+        let kept: Vec<Option<String>> = titles.titles.into_inner().unwrap().into_values().collect();
+        assert_eq!(kept, vec![Some("Shuttles Extended".to_owned())]);
+    }
 }
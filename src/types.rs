@@ -1,5 +1,291 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset};
 use serde::Deserialize;
 
+/// Declares an enum over one of the MBTA API's SCREAMING_SNAKE_CASE string
+/// fields. Values the API adds later deserialize to `Unknown` with the raw
+/// string kept, rather than failing the whole response.
+macro_rules! api_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+        #[serde(from = "String")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)+
+            Unknown(String),
+        }
+
+        impl $name {
+            /// The value as the API spells it.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)+
+                    Self::Unknown(s) => s,
+                }
+            }
+
+            /// Whether this is a value we know, rather than an `Unknown` fallback.
+            pub fn is_known(&self) -> bool {
+                !matches!(self, Self::Unknown(_))
+            }
+
+            /// Sentence-case rendering for display, e.g. "Track work".
+            pub fn label(&self) -> String {
+                let lower = self.as_str().replace('_', " ").to_lowercase();
+                let mut chars = lower.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> Self {
+                match s.as_str() {
+                    $($value => Self::$variant,)+
+                    _ => Self::Unknown(s),
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                Self::from(s.to_owned())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+api_enum! {
+    pub enum Effect {
+        AccessIssue => "ACCESS_ISSUE",
+        AdditionalService => "ADDITIONAL_SERVICE",
+        AmberAlert => "AMBER_ALERT",
+        BikeIssue => "BIKE_ISSUE",
+        Cancellation => "CANCELLATION",
+        Delay => "DELAY",
+        Detour => "DETOUR",
+        DockClosure => "DOCK_CLOSURE",
+        DockIssue => "DOCK_ISSUE",
+        ElevatorClosure => "ELEVATOR_CLOSURE",
+        EscalatorClosure => "ESCALATOR_CLOSURE",
+        ExtraService => "EXTRA_SERVICE",
+        FacilityIssue => "FACILITY_ISSUE",
+        ModifiedService => "MODIFIED_SERVICE",
+        NoService => "NO_SERVICE",
+        OtherEffect => "OTHER_EFFECT",
+        ParkingClosure => "PARKING_CLOSURE",
+        ParkingIssue => "PARKING_ISSUE",
+        PolicyChange => "POLICY_CHANGE",
+        ScheduleChange => "SCHEDULE_CHANGE",
+        ServiceChange => "SERVICE_CHANGE",
+        Shuttle => "SHUTTLE",
+        SnowRoute => "SNOW_ROUTE",
+        StationClosure => "STATION_CLOSURE",
+        StationIssue => "STATION_ISSUE",
+        StopClosure => "STOP_CLOSURE",
+        StopMove => "STOP_MOVE",
+        StopMoved => "STOP_MOVED",
+        Summary => "SUMMARY",
+        Suspension => "SUSPENSION",
+        TrackChange => "TRACK_CHANGE",
+        UnknownEffect => "UNKNOWN_EFFECT",
+    }
+}
+
+api_enum! {
+    pub enum Cause {
+        Accident => "ACCIDENT",
+        Amtrak => "AMTRAK",
+        AnEarlierMechanicalProblem => "AN_EARLIER_MECHANICAL_PROBLEM",
+        AnEarlierSignalProblem => "AN_EARLIER_SIGNAL_PROBLEM",
+        AutosImpedingService => "AUTOS_IMPEDING_SERVICE",
+        CoastGuardRestriction => "COAST_GUARD_RESTRICTION",
+        Congestion => "CONGESTION",
+        Construction => "CONSTRUCTION",
+        CrossingMalfunction => "CROSSING_MALFUNCTION",
+        Demonstration => "DEMONSTRATION",
+        DisabledBus => "DISABLED_BUS",
+        DisabledTrain => "DISABLED_TRAIN",
+        DrawbridgeBeingRaised => "DRAWBRIDGE_BEING_RAISED",
+        ElectricalWork => "ELECTRICAL_WORK",
+        Fire => "FIRE",
+        FireDepartmentActivity => "FIRE_DEPARTMENT_ACTIVITY",
+        Flooding => "FLOODING",
+        Fog => "FOG",
+        FreightTrainInterference => "FREIGHT_TRAIN_INTERFERENCE",
+        HazmatCondition => "HAZMAT_CONDITION",
+        HeavyRidership => "HEAVY_RIDERSHIP",
+        HighWinds => "HIGH_WINDS",
+        Holiday => "HOLIDAY",
+        Hurricane => "HURRICANE",
+        IceInHarbor => "ICE_IN_HARBOR",
+        Maintenance => "MAINTENANCE",
+        MechanicalProblem => "MECHANICAL_PROBLEM",
+        MedicalEmergency => "MEDICAL_EMERGENCY",
+        OtherCause => "OTHER_CAUSE",
+        Parade => "PARADE",
+        PoliceAction => "POLICE_ACTION",
+        PoliceActivity => "POLICE_ACTIVITY",
+        PowerProblem => "POWER_PROBLEM",
+        SevereWeather => "SEVERE_WEATHER",
+        SignalProblem => "SIGNAL_PROBLEM",
+        SlipperyRail => "SLIPPERY_RAIL",
+        Snow => "SNOW",
+        SpecialEvent => "SPECIAL_EVENT",
+        SpeedRestriction => "SPEED_RESTRICTION",
+        SwitchProblem => "SWITCH_PROBLEM",
+        TieReplacement => "TIE_REPLACEMENT",
+        TrackProblem => "TRACK_PROBLEM",
+        TrackWork => "TRACK_WORK",
+        Traffic => "TRAFFIC",
+        UnknownCause => "UNKNOWN_CAUSE",
+        UnrulyPassenger => "UNRULY_PASSENGER",
+        Weather => "WEATHER",
+    }
+}
+
+api_enum! {
+    /// Where the alert is in its life, relative to now.
+    pub enum Lifecycle {
+        New => "NEW",
+        Ongoing => "ONGOING",
+        OngoingUpcoming => "ONGOING_UPCOMING",
+        Upcoming => "UPCOMING",
+    }
+}
+
+api_enum! {
+    /// How sure the MBTA is about when the alert ends.
+    pub enum DurationCertainty {
+        Estimated => "ESTIMATED",
+        Known => "KNOWN",
+        /// The API's `UNKNOWN`: no end is in sight. Named apart from the
+        /// `Unknown(String)` fallback every API enum carries.
+        Indefinite => "UNKNOWN",
+    }
+}
+
+api_enum! {
+    /// What a rider is doing when an alert applies to them.
+    pub enum Activity {
+        Board => "BOARD",
+        BringingBike => "BRINGING_BIKE",
+        Exit => "EXIT",
+        ParkCar => "PARK_CAR",
+        Ride => "RIDE",
+        StoreBike => "STORE_BIKE",
+        UsingEscalator => "USING_ESCALATOR",
+        UsingWheelchair => "USING_WHEELCHAIR",
+    }
+}
+
+api_enum! {
+    /// The kind of station amenity a facility is.
+    pub enum FacilityType {
+        BikeStorage => "BIKE_STORAGE",
+        BridgePlate => "BRIDGE_PLATE",
+        ElevatedSubplatform => "ELEVATED_SUBPLATFORM",
+        Elevator => "ELEVATOR",
+        Escalator => "ESCALATOR",
+        FullyElevatedPlatform => "FULLY_ELEVATED_PLATFORM",
+        Other => "OTHER",
+        ParkingArea => "PARKING_AREA",
+        PortableBoardingLift => "PORTABLE_BOARDING_LIFT",
+        Ramp => "RAMP",
+    }
+}
+
+/// GTFS route type: the mode an informed entity's route runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "u8")]
+pub enum RouteType {
+    LightRail,
+    Subway,
+    CommuterRail,
+    Bus,
+    Ferry,
+    Unknown(u8),
+}
+
+impl From<u8> for RouteType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::LightRail,
+            1 => Self::Subway,
+            2 => Self::CommuterRail,
+            3 => Self::Bus,
+            4 => Self::Ferry,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl RouteType {
+    pub fn number(self) -> u8 {
+        match self {
+            Self::LightRail => 0,
+            Self::Subway => 1,
+            Self::CommuterRail => 2,
+            Self::Bus => 3,
+            Self::Ferry => 4,
+            Self::Unknown(other) => other,
+        }
+    }
+}
+
+/// Parses either the GTFS number or a name like `commuter-rail`.
+impl FromStr for RouteType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let route_type = match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "light-rail" => Self::LightRail,
+            "subway" => Self::Subway,
+            "commuter-rail" => Self::CommuterRail,
+            "bus" => Self::Bus,
+            "ferry" => Self::Ferry,
+            other => match other.parse::<u8>() {
+                Ok(n) if n <= 4 => Self::from(n),
+                _ => anyhow::bail!("Unknown route type '{s}'"),
+            },
+        };
+        Ok(route_type)
+    }
+}
+
+/// MBTA severity, 0 (least) through 10 (most severe).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(transparent)]
+pub struct Severity(pub u8);
+
+impl Severity {
+    /// Whether this is on the MBTA's scale.
+    pub fn is_known(&self) -> bool {
+        self.0 <= 10
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Alerts {
     pub data: Vec<Alert>,
@@ -14,31 +300,197 @@ pub struct Alert {
 #[derive(Debug, Deserialize)]
 pub struct AlertAttributes {
     pub header: String,
+    pub short_header: Option<String>,
     pub description: Option<String>,
     pub url: Option<String>,
     pub active_period: Vec<ActivePeriod>,
-    pub effect: String,
+    pub effect: Effect,
+    pub cause: Cause,
+    pub severity: Severity,
+    pub lifecycle: Lifecycle,
+    pub timeframe: Option<String>,
+    pub service_effect: Option<String>,
+    /// Set only for alerts the MBTA promotes site-wide.
+    pub banner: Option<String>,
+    pub duration_certainty: Option<DurationCertainty>,
+    pub image: Option<String>,
+    pub image_alternative_text: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
     pub informed_entity: Vec<InformedEntity>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ActivePeriod {
-    pub start: Option<String>,
-    pub end: Option<String>,
+    pub start: Option<DateTime<FixedOffset>>,
+    pub end: Option<DateTime<FixedOffset>>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Something an alert applies to. Every field narrows the scope: an entity with
+/// a route and a stop means that route at that stop, not the whole route.
+#[derive(Debug, Default, Deserialize)]
 pub struct InformedEntity {
     pub route: Option<String>,
+    pub route_type: Option<RouteType>,
+    pub stop: Option<String>,
+    /// GTFS direction: 0 and 1, whose meaning depends on the route.
+    pub direction_id: Option<u8>,
+    pub trip: Option<String>,
+    pub facility: Option<String>,
+    #[serde(default)]
+    pub activities: Vec<Activity>,
+}
+
+/// Response from the MBTA `/stops` endpoint, with parent stations included.
+#[derive(Debug, Deserialize)]
+pub struct Stops {
+    pub data: Vec<Stop>,
+    #[serde(default)]
+    pub included: Vec<Stop>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Stop {
+    pub id: String,
+    pub attributes: StopAttributes,
+    pub relationships: Option<StopRelationships>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopAttributes {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopRelationships {
+    pub parent_station: Option<Relationship>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Relationship {
+    pub data: Option<ResourceId>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResourceId {
+    pub id: String,
+}
+
+/// Response from the MBTA `/facilities` endpoint.
+#[derive(Debug, Deserialize)]
+pub struct Facilities {
+    pub data: Vec<Facility>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Facility {
+    pub id: String,
+    pub attributes: FacilityAttributes,
+    pub relationships: Option<FacilityRelationships>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FacilityAttributes {
+    /// E.g. "Park Street Elevator 804 (Red Line platform to Green Line platform)".
+    pub long_name: Option<String>,
+    /// E.g. "Red Line platform to Green Line platform".
+    pub short_name: Option<String>,
+    #[serde(rename = "type")]
+    pub facility_type: FacilityType,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FacilityRelationships {
+    pub stop: Option<Relationship>,
+}
+
+impl Facility {
+    /// The station the facility is in.
+    pub fn stop(&self) -> Option<&str> {
+        Some(
+            self.relationships
+                .as_ref()?
+                .stop
+                .as_ref()?
+                .data
+                .as_ref()?
+                .id
+                .as_str(),
+        )
+    }
+}
+
+impl Stop {
+    pub fn parent_station(&self) -> Option<&str> {
+        Some(
+            self.relationships
+                .as_ref()?
+                .parent_station
+                .as_ref()?
+                .data
+                .as_ref()?
+                .id
+                .as_str(),
+        )
+    }
 }
 
 impl Alert {
-    pub fn period_start(&self) -> Option<&str> {
-        self.attributes.active_period.first()?.start.as_deref()
+    pub fn period_start(&self) -> Option<DateTime<FixedOffset>> {
+        self.attributes.active_period.first()?.start
+    }
+
+    pub fn period_end(&self) -> Option<DateTime<FixedOffset>> {
+        self.attributes.active_period.first()?.end
+    }
+
+    /// The `index`th entry of `active_period`, if the alert has that many.
+    pub fn period(&self, index: usize) -> Option<&ActivePeriod> {
+        self.attributes.active_period.get(index)
     }
 
-    pub fn period_end(&self) -> Option<&str> {
-        self.attributes.active_period.first()?.end.as_deref()
+    /// Distinct values of `field` across the informed entities, in first-seen
+    /// order.
+    fn distinct<'a>(
+        &'a self,
+        field: impl Fn(&'a InformedEntity) -> Option<&'a str>,
+    ) -> Vec<&'a str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for id in self.attributes.informed_entity.iter().filter_map(field) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /// Distinct route IDs across the informed entities, in first-seen order.
+    pub fn route_ids(&self) -> Vec<&str> {
+        self.distinct(|entity| entity.route.as_deref())
+    }
+
+    /// Distinct stop IDs across the informed entities, in first-seen order.
+    pub fn stop_ids(&self) -> Vec<&str> {
+        self.distinct(|entity| entity.stop.as_deref())
+    }
+
+    /// Distinct facility IDs across the informed entities, in first-seen order.
+    pub fn facility_ids(&self) -> Vec<&str> {
+        self.distinct(|entity| entity.facility.as_deref())
+    }
+
+    /// The direction the alert is limited to, when every entity with a route
+    /// or stop names the same one. One that names none covers both, so `None`
+    /// means both directions (or unknown).
+    pub fn direction_id(&self) -> Option<u8> {
+        let mut directions = self
+            .attributes
+            .informed_entity
+            .iter()
+            .filter(|entity| entity.route.is_some() || entity.stop.is_some())
+            .map(|entity| entity.direction_id);
+        let first = directions.next()??;
+        directions.all(|d| d == Some(first)).then_some(first)
     }
 
     /// Builder with placeholder defaults, intended for tests. Production
@@ -50,7 +502,10 @@ impl Alert {
             description: None,
             url: None,
             active_period: Vec::new(),
-            effect: "DELAY".to_owned(),
+            effect: Effect::Delay,
+            cause: Cause::UnknownCause,
+            severity: Severity(3),
+            lifecycle: Lifecycle::New,
             informed_entity: Vec::new(),
         }
     }
@@ -62,10 +517,17 @@ pub struct AlertBuilder {
     description: Option<String>,
     url: Option<String>,
     active_period: Vec<ActivePeriod>,
-    effect: String,
+    effect: Effect,
+    cause: Cause,
+    severity: Severity,
+    lifecycle: Lifecycle,
     informed_entity: Vec<InformedEntity>,
 }
 
+fn parse_test_timestamp(s: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(s).unwrap_or_else(|e| panic!("bad timestamp {s:?}: {e}"))
+}
+
 impl AlertBuilder {
     pub fn id(mut self, id: &str) -> Self {
         self.id = id.to_owned();
@@ -88,43 +550,275 @@ impl AlertBuilder {
     }
 
     pub fn effect(mut self, effect: &str) -> Self {
-        self.effect = effect.to_owned();
+        self.effect = effect.into();
+        self
+    }
+
+    pub fn cause(mut self, cause: &str) -> Self {
+        self.cause = cause.into();
+        self
+    }
+
+    pub fn severity(mut self, severity: u8) -> Self {
+        self.severity = Severity(severity);
+        self
+    }
+
+    pub fn lifecycle(mut self, lifecycle: &str) -> Self {
+        self.lifecycle = lifecycle.into();
         self
     }
 
     /// Adds an informed entity for `route`; call repeatedly for multi-route alerts.
-    pub fn route(mut self, route: &str) -> Self {
-        self.informed_entity.push(InformedEntity {
+    pub fn route(self, route: &str) -> Self {
+        self.entity(InformedEntity {
             route: Some(route.to_owned()),
-        });
-        self
+            ..Default::default()
+        })
     }
 
     /// Adds an informed entity with no route.
-    pub fn null_route(mut self) -> Self {
-        self.informed_entity.push(InformedEntity { route: None });
+    pub fn null_route(self) -> Self {
+        self.entity(InformedEntity::default())
+    }
+
+    /// Adds an informed entity for `route` at `stop`.
+    pub fn stop(self, route: &str, stop: &str) -> Self {
+        self.entity(InformedEntity {
+            route: Some(route.to_owned()),
+            stop: Some(stop.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    pub fn entity(mut self, entity: InformedEntity) -> Self {
+        self.informed_entity.push(entity);
         self
     }
 
+    /// Adds an active period from RFC 3339 timestamps. Panics on a malformed
+    /// timestamp, since a test passing one is itself broken.
     pub fn period(mut self, start: Option<&str>, end: Option<&str>) -> Self {
         self.active_period.push(ActivePeriod {
-            start: start.map(str::to_owned),
-            end: end.map(str::to_owned),
+            start: start.map(parse_test_timestamp),
+            end: end.map(parse_test_timestamp),
         });
         self
     }
 
     pub fn build(self) -> Alert {
+        let timestamp = parse_test_timestamp("2024-01-01T00:00:00-05:00");
         Alert {
             id: self.id,
             attributes: AlertAttributes {
                 header: self.header,
+                short_header: None,
                 description: self.description,
                 url: self.url,
                 active_period: self.active_period,
                 effect: self.effect,
+                cause: self.cause,
+                severity: self.severity,
+                lifecycle: self.lifecycle,
+                timeframe: None,
+                service_effect: None,
+                banner: None,
+                duration_certainty: None,
+                image: None,
+                image_alternative_text: None,
+                created_at: timestamp,
+                updated_at: timestamp,
                 informed_entity: self.informed_entity,
             },
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_api_enum_known_value() {
+        assert_eq!(Effect::from("SHUTTLE"), Effect::Shuttle);
+        assert_eq!(Effect::Shuttle.as_str(), "SHUTTLE");
+    }
+
+    #[test]
+    fn test_api_enum_unknown_value_keeps_raw_string() {
+        let effect = Effect::from("HOVERCRAFT_ISSUE");
+        assert_eq!(effect, Effect::Unknown("HOVERCRAFT_ISSUE".to_owned()));
+        assert_eq!(effect.as_str(), "HOVERCRAFT_ISSUE");
+    }
+
+    #[test]
+    fn test_api_enum_label() {
+        assert_eq!(Cause::TrackWork.label(), "Track work");
+        assert_eq!(Effect::Delay.label(), "Delay");
+    }
+
+    #[test]
+    fn test_duration_certainty_unknown_is_indefinite() {
+        assert_eq!(
+            DurationCertainty::from("UNKNOWN"),
+            DurationCertainty::Indefinite
+        );
+    }
+
+    #[test]
+    fn test_route_type_from_number() {
+        assert_eq!(RouteType::from(1), RouteType::Subway);
+        assert_eq!(RouteType::from(9), RouteType::Unknown(9));
+    }
+
+    #[test]
+    fn test_route_type_parses_names_and_numbers() -> anyhow::Result<()> {
+        assert_eq!(
+            "commuter-rail".parse::<RouteType>()?,
+            RouteType::CommuterRail
+        );
+        assert_eq!("Light_Rail".parse::<RouteType>()?, RouteType::LightRail);
+        assert_eq!("4".parse::<RouteType>()?, RouteType::Ferry);
+        assert!("7".parse::<RouteType>().is_err());
+        assert!("monorail".parse::<RouteType>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_informed_entity() -> anyhow::Result<()> {
+        let entity: InformedEntity = serde_json::from_str(
+            r#"{"stop": "6822", "direction_id": 0, "route_type": 3, "route": "426",
+                "activities": ["BOARD", "EXIT", "TELEPORT"]}"#,
+        )?;
+        assert_eq!(entity.route.as_deref(), Some("426"));
+        assert_eq!(entity.route_type, Some(RouteType::Bus));
+        assert_eq!(entity.stop.as_deref(), Some("6822"));
+        assert_eq!(entity.direction_id, Some(0));
+        assert_eq!(
+            entity.activities,
+            vec![
+                Activity::Board,
+                Activity::Exit,
+                Activity::Unknown("TELEPORT".to_owned())
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_ids_are_distinct_in_order() {
+        let alert = Alert::builder()
+            .stop("Red", "place-pktrm")
+            .stop("Red", "70075")
+            .stop("Green-B", "place-pktrm")
+            .build();
+        assert_eq!(alert.stop_ids(), vec!["place-pktrm", "70075"]);
+        assert_eq!(alert.route_ids(), vec!["Red", "Green-B"]);
+    }
+
+    #[test]
+    fn test_deserialize_facility() -> anyhow::Result<()> {
+        let facilities: Facilities = serde_json::from_str(
+            r#"{"data": [{
+                "id": "804",
+                "type": "facility",
+                "attributes": {
+                    "long_name": "Park Street Elevator 804 (Red Line platform to Green Line platform)",
+                    "short_name": "Red Line platform to Green Line platform",
+                    "type": "ELEVATOR"
+                },
+                "relationships": {"stop": {"data": {"id": "place-pktrm", "type": "stop"}}}
+            }]}"#,
+        )?;
+        let facility = &facilities.data[0];
+        assert_eq!(facility.attributes.facility_type, FacilityType::Elevator);
+        assert_eq!(facility.stop(), Some("place-pktrm"));
+        Ok(())
+    }
+
+    fn directional(direction_id: Option<u8>) -> InformedEntity {
+        InformedEntity {
+            route: Some("426".to_owned()),
+            direction_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_direction_id_shared() {
+        let alert = Alert::builder()
+            .entity(directional(Some(0)))
+            .entity(directional(Some(0)))
+            .entity(InformedEntity {
+                facility: Some("955".to_owned()),
+                ..Default::default()
+            })
+            .build();
+        assert_eq!(alert.direction_id(), Some(0));
+    }
+
+    #[test]
+    fn test_direction_id_with_route_wide_entity_is_none() {
+        // The entity without a direction covers both.
+        let alert = Alert::builder()
+            .entity(directional(Some(0)))
+            .entity(directional(Some(0)))
+            .entity(directional(None))
+            .build();
+        assert_eq!(alert.direction_id(), None);
+    }
+
+    #[test]
+    fn test_direction_id_mixed_is_none() {
+        let alert = Alert::builder()
+            .entity(directional(Some(0)))
+            .entity(directional(Some(1)))
+            .build();
+        assert_eq!(alert.direction_id(), None);
+    }
+
+    #[test]
+    fn test_deserialize_attributes() -> anyhow::Result<()> {
+        let alert: Alert = serde_json::from_str(
+            r#"{
+                "id": "1",
+                "attributes": {
+                    "header": "Header",
+                    "short_header": "Short",
+                    "description": null,
+                    "url": null,
+                    "active_period": [{"start": "2025-03-24T03:00:00-04:00", "end": null}],
+                    "effect": "SHUTTLE",
+                    "cause": "TRACK_WORK",
+                    "severity": 7,
+                    "lifecycle": "ONGOING_UPCOMING",
+                    "timeframe": "Through Friday",
+                    "service_effect": "Red Line shuttle",
+                    "banner": null,
+                    "duration_certainty": "KNOWN",
+                    "image": null,
+                    "image_alternative_text": null,
+                    "created_at": "2025-03-17T13:35:48-04:00",
+                    "updated_at": "2025-03-30T03:01:17-04:00",
+                    "informed_entity": []
+                }
+            }"#,
+        )?;
+        let attributes = &alert.attributes;
+
+        assert_eq!(attributes.effect, Effect::Shuttle);
+        assert_eq!(attributes.cause, Cause::TrackWork);
+        assert_eq!(attributes.severity, Severity(7));
+        assert_eq!(attributes.lifecycle, Lifecycle::OngoingUpcoming);
+        assert_eq!(
+            attributes.duration_certainty,
+            Some(DurationCertainty::Known)
+        );
+        assert_eq!(
+            alert.period_start(),
+            Some(DateTime::parse_from_rfc3339("2025-03-24T03:00:00-04:00")?)
+        );
+        assert_eq!(alert.period_end(), None);
+        Ok(())
+    }
+}